@@ -0,0 +1,60 @@
+use criterion::{BatchSize, Criterion, Throughput, criterion_group, criterion_main};
+use rgb_emu::{Cpu, MemBus, Rom};
+use std::hint::black_box;
+
+const INSTRUCTIONS: u64 = 10_000;
+
+// Builds a 32KiB ROM-only cartridge with the program repeated after the header.
+// The zeroed header executes as NOPs from the entry point at 0x0100.
+fn cpu_with_program(setup: &[u8], program: &[u8]) -> Cpu {
+    let mut data: Vec<u8> = vec![0x00; 0x8000];
+    let mut code: Vec<u8> = setup.to_vec();
+    while code.len() < 0x8000 - 0x0150 {
+        code.extend_from_slice(program);
+    }
+    for (offset, byte) in code.iter().take(0x8000 - 0x0150).enumerate() {
+        data[0x0150 + offset] = *byte;
+    }
+    let rom: Rom = Rom::from_bytes(data).expect("valid benchmark ROM");
+    Cpu::new(MemBus::new(rom).expect("supported benchmark mapper"))
+}
+
+fn run(cpu: &mut Cpu) {
+    for _ in 0..INSTRUCTIONS {
+        black_box(cpu.step());
+    }
+}
+
+fn register_only(c: &mut Criterion) {
+    // LD B,C / LD D,B / ADD A,B / XOR A,D / CP A,E
+    let program: [u8; 5] = [0x41, 0x50, 0x80, 0xAA, 0xBB];
+    let mut group = c.benchmark_group("cpu");
+    group.throughput(Throughput::Elements(INSTRUCTIONS));
+    group.bench_function("register_only", |b| {
+        b.iter_batched(
+            || cpu_with_program(&[], &program),
+            |mut cpu| run(&mut cpu),
+            BatchSize::LargeInput,
+        )
+    });
+    group.finish();
+}
+
+fn memory_access(c: &mut Criterion) {
+    // LD HL,0xC000 and LD DE,0xC100 once, then LD (HL),A / LD B,(HL) / ADD A,(HL) / LD (DE),A / LD A,(HL)
+    let setup: [u8; 6] = [0x21, 0x00, 0xC0, 0x11, 0x00, 0xC1];
+    let program: [u8; 5] = [0x77, 0x46, 0x86, 0x12, 0x7E];
+    let mut group = c.benchmark_group("cpu");
+    group.throughput(Throughput::Elements(INSTRUCTIONS));
+    group.bench_function("memory_access", |b| {
+        b.iter_batched(
+            || cpu_with_program(&setup, &program),
+            |mut cpu| run(&mut cpu),
+            BatchSize::LargeInput,
+        )
+    });
+    group.finish();
+}
+
+criterion_group!(benches, register_only, memory_access);
+criterion_main!(benches);
@@ -0,0 +1,60 @@
+use rgb_emu::{Emulator, Error, FRAMES_PER_SECOND, Rom};
+use std::env;
+use std::process;
+use std::time::{Duration, Instant};
+
+// Runs a ROM headless with no frame limiter and reports how many emulated seconds pass per real second.
+// cargo run --release --example turbo -- [--frames <n>] [--no-render] <rom-path>
+const USAGE: &str = "usage: turbo [--frames <n>] [--no-render] <rom-path>";
+
+fn main() {
+    let mut frames: u32 = 3600;
+    let mut render: bool = true;
+    let mut path: Option<String> = None;
+    let mut args = env::args().skip(1);
+    while let Some(arg) = args.next() {
+        match arg.as_str() {
+            "--frames" => match args.next().map(|n| n.parse::<u32>()) {
+                Some(Ok(n)) if n > 0 => frames = n,
+                _ => {
+                    eprintln!("--frames needs a whole number above 0\n{USAGE}");
+                    process::exit(2);
+                }
+            },
+            "--no-render" => render = false,
+            _ if path.is_none() => path = Some(arg),
+            _ => {
+                eprintln!("Unexpected argument {arg}\n{USAGE}");
+                process::exit(2);
+            }
+        };
+    }
+    let Some(path) = path else {
+        eprintln!("Missing ROM path\n{USAGE}");
+        process::exit(2);
+    };
+    let mut emulator: Emulator = match Rom::new(&path)
+        .map_err(Error::from)
+        .and_then(Emulator::from_rom)
+    {
+        Ok(emulator) => emulator,
+        Err(e) => {
+            eprintln!("Could not load {path}: {e}");
+            process::exit(1);
+        }
+    };
+    if !render {
+        // Only the very first frame is drawn. PPU timing and interrupts still run.
+        emulator.set_frame_skip(u32::MAX);
+    }
+    let start: Instant = Instant::now();
+    emulator.run_frames(frames);
+    let elapsed: Duration = start.elapsed();
+    let emulated: f64 = frames as f64 / FRAMES_PER_SECOND;
+    println!(
+        "{frames} frames ({emulated:.2}s emulated) in {:.2}s: {:.1} frames/s, {:.2}x real time",
+        elapsed.as_secs_f64(),
+        frames as f64 / elapsed.as_secs_f64(),
+        emulated / elapsed.as_secs_f64()
+    );
+}
@@ -1,12 +1,26 @@
+#[cfg(feature = "gui")]
 use eframe::App;
+use serde::{Deserialize, Serialize};
+use serde_big_array::BigArray;
 use std::collections::HashMap;
-use std::fs::File;
-use std::io::Result;
-use std::io::prelude::*;
+use std::fmt;
+use std::fs;
+use std::io::{self, Read, Write};
+use std::net::{TcpListener, TcpStream, ToSocketAddrs};
+use std::path::{Path, PathBuf};
+use std::time::Duration;
+use zip::ZipArchive;
+
+const NINTENDO_LOGO: [u8; 48] = [
+    0xCE, 0xED, 0x66, 0x66, 0xCC, 0x0D, 0x00, 0x0B, 0x03, 0x73, 0x00, 0x83, 0x00, 0x0C, 0x00, 0x0D,
+    0x00, 0x08, 0x11, 0x1F, 0x88, 0x89, 0x00, 0x0E, 0xDC, 0xCC, 0x6E, 0xE6, 0xDD, 0xDD, 0xD9, 0x99,
+    0xBB, 0xBB, 0x67, 0x63, 0x6E, 0x0E, 0xEC, 0xCC, 0xDD, 0xDC, 0x99, 0x9F, 0xBB, 0xB9, 0x33, 0x3E,
+];
 
 pub struct Rom {
-    data: HashMap<u16, u8>, // Program will crash when attempting to read ROMs larger than 64KiB to be fixed later
+    data: Vec<u8>,
     pub title: String,
+    cgb_flag: u8,
     cart_type: CartType,
     rom_size: u32,
     rom_banks: u32,
@@ -14,7 +28,25 @@ pub struct Rom {
     ram_banks: u32,
 }
 
-enum CartType {
+// Everything the cartridge header at 0x0134-0x014C describes, for ROM managers and info panels
+#[derive(Clone, PartialEq, Debug)]
+pub struct CartridgeHeader {
+    pub title: String,
+    pub cart_type: CartType,
+    pub rom_size: u32,
+    pub rom_banks: u32,
+    pub ram_size: u32,
+    pub ram_banks: u32,
+    pub cgb_flag: u8,
+    pub sgb_flag: u8,
+    // Two character new licensee code, or the old one byte code in hex
+    pub licensee: String,
+    pub version: u8,
+}
+
+#[allow(clippy::upper_case_acronyms)]
+#[derive(Clone, Copy, PartialEq, Debug, Serialize, Deserialize)]
+pub enum CartType {
     ROMONLY,
     MBC1,
     MBC1RAM,
@@ -45,12 +77,299 @@ enum CartType {
     HuC1RAMBATTERY,
 }
 
+#[derive(Debug)]
+pub enum RomError {
+    Io(std::io::Error),
+    UnknownCartType(u8),
+    UnsupportedMapper(u8),
+    Zip(zip::result::ZipError),
+    NoRomInArchive,
+    Patch(PatchError),
+    InvalidRomSize(u8),
+    InvalidRamSize(u8),
+}
+
+impl fmt::Display for RomError {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match self {
+            RomError::Io(e) => write!(f, "Unable to read ROM file. {e}"),
+            RomError::UnknownCartType(byte) => write!(f, "Unknown cartridge type: {:X?}", byte),
+            RomError::UnsupportedMapper(byte) => {
+                write!(
+                    f,
+                    "Cartridge type {:X?} uses a mapper that is not supported",
+                    byte
+                )
+            }
+            RomError::Zip(e) => write!(f, "Unable to read zip archive. {e}"),
+            RomError::NoRomInArchive => write!(f, "No .gb or .gbc file found in zip archive"),
+            RomError::Patch(e) => write!(f, "Unable to apply patch. {e}"),
+            RomError::InvalidRomSize(byte) => write!(f, "Invalid ROM size in header: {byte:02X}"),
+            RomError::InvalidRamSize(byte) => write!(f, "Invalid RAM size in header: {byte:02X}"),
+        }
+    }
+}
+
+impl std::error::Error for RomError {}
+
+#[derive(Debug, PartialEq)]
+pub enum PatchError {
+    UnknownFormat,
+    Truncated,
+    WrongSource { expected: u32, actual: u32 },
+    WrongTarget,
+    CorruptPatch,
+}
+
+impl fmt::Display for PatchError {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match self {
+            PatchError::UnknownFormat => write!(f, "Not an IPS or BPS patch"),
+            PatchError::Truncated => write!(f, "Patch ended unexpectedly"),
+            PatchError::WrongSource { expected, actual } => write!(
+                f,
+                "Patch is for a ROM with CRC32 {expected:08X}, this ROM has {actual:08X}"
+            ),
+            PatchError::WrongTarget => {
+                write!(f, "Patched ROM does not match the checksum in the patch")
+            }
+            PatchError::CorruptPatch => {
+                write!(f, "Patch checksum does not match, the file is damaged")
+            }
+        }
+    }
+}
+
+impl std::error::Error for PatchError {}
+
+#[derive(Debug)]
+pub enum StateError {
+    InvalidHeader,
+    WrongRom,
+    Encode(bincode::error::EncodeError),
+    Decode(bincode::error::DecodeError),
+}
+
+impl fmt::Display for StateError {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match self {
+            StateError::InvalidHeader => write!(f, "Not an rgb-emu save state"),
+            StateError::WrongRom => write!(f, "Save state does not match the loaded ROM"),
+            StateError::Encode(e) => write!(f, "Could not encode emulator: {e}"),
+            StateError::Decode(e) => write!(f, "Could not decode emulator: {e}"),
+        }
+    }
+}
+
+impl std::error::Error for StateError {}
+
+// Everything the Emulator API can fail with. The component errors stay available for callers that only
+// use one part, like Rom or SymbolTable.
+#[derive(Debug)]
+pub enum Error {
+    Rom(RomError),
+    State(StateError),
+    Cheat(CheatError),
+    UnimplementedOpcode { opcode: u8, pc: u16 },
+    Io(io::Error),
+    InvalidMovie,
+}
+
+impl fmt::Display for Error {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match self {
+            Error::Rom(e) => write!(f, "{e}"),
+            Error::State(e) => write!(f, "{e}"),
+            Error::Cheat(e) => write!(f, "{e}"),
+            Error::UnimplementedOpcode { opcode, pc } => {
+                write!(f, "Opcode {opcode:02X} at {pc:04X} is not implemented")
+            }
+            Error::Io(e) => write!(f, "{e}"),
+            Error::InvalidMovie => write!(f, "Not an rgb-emu movie"),
+        }
+    }
+}
+
+impl std::error::Error for Error {
+    fn source(&self) -> Option<&(dyn std::error::Error + 'static)> {
+        match self {
+            Error::Rom(e) => Some(e),
+            Error::State(e) => Some(e),
+            Error::Cheat(e) => Some(e),
+            Error::Io(e) => Some(e),
+            Error::UnimplementedOpcode { .. } | Error::InvalidMovie => None,
+        }
+    }
+}
+
+impl From<RomError> for Error {
+    fn from(e: RomError) -> Self {
+        Error::Rom(e)
+    }
+}
+
+impl From<StateError> for Error {
+    fn from(e: StateError) -> Self {
+        Error::State(e)
+    }
+}
+
+impl From<CheatError> for Error {
+    fn from(e: CheatError) -> Self {
+        Error::Cheat(e)
+    }
+}
+
+impl From<io::Error> for Error {
+    fn from(e: io::Error) -> Self {
+        Error::Io(e)
+    }
+}
+
+impl fmt::Display for CartType {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        let name: &str = match self {
+            CartType::ROMONLY => "ROM ONLY",
+            CartType::MBC1 => "MBC1",
+            CartType::MBC1RAM => "MBC1+RAM",
+            CartType::MBC1RAMBATTERY => "MBC1+RAM+BATTERY",
+            CartType::MBC2 => "MBC2",
+            CartType::MBC2BATTERY => "MBC2+BATTERY",
+            CartType::ROMRAM => "ROM+RAM",
+            CartType::ROMRAMBATTERY => "ROM+RAM+BATTERY",
+            CartType::MMM01 => "MMM01",
+            CartType::MMM01RAM => "MMM01+RAM",
+            CartType::MMM01RAMBATTERY => "MMM01+RAM+BATTERY",
+            CartType::MBC3TIMERBATTERY => "MBC3+TIMER+BATTERY",
+            CartType::MBC3TIMERRAMBATTERY => "MBC3+TIMER+RAM+BATTERY",
+            CartType::MBC3 => "MBC3",
+            CartType::MBC3RAM => "MBC3+RAM",
+            CartType::MBC3RAMBATTERY => "MBC3+RAM+BATTERY",
+            CartType::MBC5 => "MBC5",
+            CartType::MBC5RAM => "MBC5+RAM",
+            CartType::MBC5RAMBATTERY => "MBC5+RAM+BATTERY",
+            CartType::MBC5RUMBLE => "MBC5+RUMBLE",
+            CartType::MBC5RUMBLERAM => "MBC5+RUMBLE+RAM",
+            CartType::MBC5RUMBLERAMBATTERY => "MBC5+RUMBLE+RAM+BATTERY",
+            CartType::MBC6 => "MBC6",
+            CartType::MBC7SENSORRUMBLERAMBATTERY => "MBC7+SENSOR+RUMBLE+RAM+BATTERY",
+            CartType::POCKETCAMERA => "POCKET CAMERA",
+            CartType::BANDAITAMA5 => "BANDAI TAMA5",
+            CartType::HuC3 => "HuC3",
+            CartType::HuC1RAMBATTERY => "HuC1+RAM+BATTERY",
+        };
+        write!(f, "{name}")
+    }
+}
+
+impl CartType {
+    // Whether the bus has a mapper for this cartridge type
+    pub fn is_supported(&self) -> bool {
+        matches!(
+            self,
+            CartType::ROMONLY
+                | CartType::ROMRAM
+                | CartType::ROMRAMBATTERY
+                | CartType::MBC1
+                | CartType::MBC1RAM
+                | CartType::MBC1RAMBATTERY
+                | CartType::MBC2
+                | CartType::MBC2BATTERY
+                | CartType::MMM01
+                | CartType::MMM01RAM
+                | CartType::MMM01RAMBATTERY
+                | CartType::MBC3TIMERBATTERY
+                | CartType::MBC3TIMERRAMBATTERY
+                | CartType::MBC3
+                | CartType::MBC3RAM
+                | CartType::MBC3RAMBATTERY
+                | CartType::MBC5
+                | CartType::MBC5RAM
+                | CartType::MBC5RAMBATTERY
+                | CartType::MBC5RUMBLE
+                | CartType::MBC5RUMBLERAM
+                | CartType::MBC5RUMBLERAMBATTERY
+        )
+    }
+
+    // MBC2 has its own built in RAM, which doesn't count as external cartridge RAM here
+    pub fn has_ram(&self) -> bool {
+        matches!(
+            self,
+            CartType::MBC1RAM
+                | CartType::MBC1RAMBATTERY
+                | CartType::ROMRAM
+                | CartType::ROMRAMBATTERY
+                | CartType::MMM01RAM
+                | CartType::MMM01RAMBATTERY
+                | CartType::MBC3TIMERRAMBATTERY
+                | CartType::MBC3RAM
+                | CartType::MBC3RAMBATTERY
+                | CartType::MBC5RAM
+                | CartType::MBC5RAMBATTERY
+                | CartType::MBC5RUMBLERAM
+                | CartType::MBC5RUMBLERAMBATTERY
+                | CartType::MBC7SENSORRUMBLERAMBATTERY
+                | CartType::HuC1RAMBATTERY
+        )
+    }
+
+    // Only battery backed RAM is worth writing to a save file
+    pub fn has_battery(&self) -> bool {
+        matches!(
+            self,
+            CartType::MBC1RAMBATTERY
+                | CartType::MBC2BATTERY
+                | CartType::ROMRAMBATTERY
+                | CartType::MMM01RAMBATTERY
+                | CartType::MBC3TIMERBATTERY
+                | CartType::MBC3TIMERRAMBATTERY
+                | CartType::MBC3RAMBATTERY
+                | CartType::MBC5RAMBATTERY
+                | CartType::MBC5RUMBLERAMBATTERY
+                | CartType::MBC7SENSORRUMBLERAMBATTERY
+                | CartType::HuC1RAMBATTERY
+        )
+    }
+
+    pub fn has_timer(&self) -> bool {
+        matches!(
+            self,
+            CartType::MBC3TIMERBATTERY | CartType::MBC3TIMERRAMBATTERY
+        )
+    }
+
+    pub fn has_rumble(&self) -> bool {
+        matches!(
+            self,
+            CartType::MBC5RUMBLE
+                | CartType::MBC5RUMBLERAM
+                | CartType::MBC5RUMBLERAMBATTERY
+                | CartType::MBC7SENSORRUMBLERAMBATTERY
+        )
+    }
+}
+
 impl Rom {
-    pub fn new(path: &String) -> Self {
-        let data: HashMap<u16, u8> = Rom::read_rom(path);
+    pub fn new(path: &String) -> Result<Self, RomError> {
+        let data: Vec<u8> = Rom::read_rom(path)?;
+        Rom::from_bytes(data)
+    }
+
+    pub fn new_with_patch(path: &String, patch: &[u8]) -> Result<Self, RomError> {
+        let data: Vec<u8> = Rom::read_rom(path)?;
+        Rom::from_bytes_with_patch(data, patch)
+    }
+
+    // Applies an IPS or BPS patch before the header is read, so a translation can change the title too
+    pub fn from_bytes_with_patch(data: Vec<u8>, patch: &[u8]) -> Result<Self, RomError> {
+        Rom::from_bytes(apply_patch(&data, patch).map_err(RomError::Patch)?)
+    }
+
+    pub fn from_bytes(data: Vec<u8>) -> Result<Self, RomError> {
         let mut title: Vec<char> = Vec::new();
         for i in 0x0134..0x0143 {
-            let value: Option<&u8> = data.get(&i);
+            let value: Option<&u8> = data.get(i);
             match value {
                 Some(byte) => {
                     if *byte != 0x00 {
@@ -61,55 +380,65 @@ impl Rom {
             };
         }
         let title: String = title.into_iter().collect();
-        let cart_type: &u8 = match data.get(&0x0147) {
-            Some(value) => value,
-            None => &0x00,
+        let cart_type: u8 = match data.get(0x0147) {
+            Some(value) => *value,
+            None => 0x00,
         };
-        let cart_type: CartType = Rom::get_cart_type(cart_type);
-        let rom_size: &u8 = match data.get(&0x0148) {
-            Some(value) => value,
-            None => &0x00,
+        let cart_type: CartType = Rom::get_cart_type(cart_type)?;
+        let rom_size: u8 = match data.get(0x0148) {
+            Some(value) => *value,
+            None => 0x00,
         };
-        let (rom_size, rom_banks) = Rom::get_rom_size_banks(rom_size);
-        let ram_size: &u8 = match data.get(&0x0149) {
-            Some(value) => value,
-            None => &0,
+        let (rom_size, rom_banks) = Rom::get_rom_size_banks(rom_size)?;
+        let ram_size: u8 = match data.get(0x0149) {
+            Some(value) => *value,
+            None => 0,
         };
-        let (ram_size, ram_banks) = Rom::get_ram_size_banks(ram_size);
-        Self {
-            data: data,
-            title: title,
-            cart_type: cart_type,
-            rom_size: rom_size,
-            rom_banks: rom_banks,
-            ram_size: ram_size,
-            ram_banks: ram_banks,
-        }
+        let (ram_size, ram_banks) = Rom::get_ram_size_banks(ram_size)?;
+        let cgb_flag: u8 = match data.get(0x0143) {
+            Some(value) => *value,
+            None => 0x00,
+        };
+        Ok(Self {
+            data,
+            title,
+            cgb_flag,
+            cart_type,
+            rom_size,
+            rom_banks,
+            ram_size,
+            ram_banks,
+        })
     }
 
-    fn read_rom(path: &String) -> HashMap<u16, u8> {
-        let file: Result<File> = File::open(path);
-
-        let file: File = match file {
-            Ok(f) => f,
-            Err(e) => panic!("ROM file not found. {e}"),
-        };
+    fn read_rom(path: &String) -> Result<Vec<u8>, RomError> {
+        if path.to_lowercase().ends_with(".zip") {
+            return Rom::read_zip(path);
+        }
+        match fs::read(path) {
+            Ok(data) => Ok(data),
+            Err(e) => Err(RomError::Io(e)),
+        }
+    }
 
-        let mut data: HashMap<u16, u8> = HashMap::new();
-        for (addr, byte) in (0_u16..).zip(file.bytes()) {
-            match byte {
-                Ok(entry) => data.insert(addr, entry),
-                Err(e) => {
-                    eprintln!("Unable to parse byte at addr: {:X?}. Error: {e}", addr);
-                    continue;
-                }
-            };
+    // Loads the first .gb or .gbc file in the archive
+    fn read_zip(path: &String) -> Result<Vec<u8>, RomError> {
+        let file: fs::File = fs::File::open(path).map_err(RomError::Io)?;
+        let mut archive: ZipArchive<fs::File> = ZipArchive::new(file).map_err(RomError::Zip)?;
+        for i in 0..archive.len() {
+            let mut entry = archive.by_index(i).map_err(RomError::Zip)?;
+            let name: String = entry.name().map_err(RomError::Zip)?.to_lowercase();
+            if name.ends_with(".gb") || name.ends_with(".gbc") {
+                let mut data: Vec<u8> = Vec::new();
+                entry.read_to_end(&mut data).map_err(RomError::Io)?;
+                return Ok(data);
+            }
         }
-        data
+        Err(RomError::NoRomInArchive)
     }
 
-    fn get_cart_type(byte: &u8) -> CartType {
-        match byte {
+    fn get_cart_type(byte: u8) -> Result<CartType, RomError> {
+        let cart_type: CartType = match byte {
             0x00 => CartType::ROMONLY,
             0x01 => CartType::MBC1,
             0x02 => CartType::MBC1RAM,
@@ -138,33 +467,36 @@ impl Rom {
             0xFD => CartType::BANDAITAMA5,
             0xFE => CartType::HuC3,
             0xFF => CartType::HuC1RAMBATTERY,
-            _ => panic!("Unknown cartridge type: {:X?}", byte),
-        }
+            _ => return Err(RomError::UnknownCartType(byte)),
+        };
+        Ok(cart_type)
     }
 
-    fn get_rom_size_banks(byte: &u8) -> (u32, u32) {
+    fn get_rom_size_banks(byte: u8) -> Result<(u32, u32), RomError> {
         match byte {
-            0x00 => (32768, 2),
-            0x01 => (65536, 4),
-            0x02 => (131072, 8),
-            0x03 => (262144, 16),
-            0x04 => (524288, 32),
-            0x05 => (1048576, 64),
-            0x06 => (2097152, 128),
-            0x07 => (4194304, 256),
-            0x08 => (8388608, 512),
-            _ => panic!("Invalid rom size value: {:X?}", byte),
+            0x00 => Ok((32768, 2)),
+            0x01 => Ok((65536, 4)),
+            0x02 => Ok((131072, 8)),
+            0x03 => Ok((262144, 16)),
+            0x04 => Ok((524288, 32)),
+            0x05 => Ok((1048576, 64)),
+            0x06 => Ok((2097152, 128)),
+            0x07 => Ok((4194304, 256)),
+            0x08 => Ok((8388608, 512)),
+            _ => Err(RomError::InvalidRomSize(byte)),
         }
     }
 
-    fn get_ram_size_banks(byte: &u8) -> (u32, u32) {
+    fn get_ram_size_banks(byte: u8) -> Result<(u32, u32), RomError> {
         match byte {
-            0x00 => (0, 0),
-            0x02 => (8192, 1),
-            0x03 => (32768, 4),
-            0x04 => (131072, 16),
-            0x05 => (65536, 8),
-            _ => panic!("Invalid ram size value: {:X?}", byte),
+            0x00 => Ok((0, 0)),
+            // Listed as unused, but some old dumps have it. It meant 2 KiB.
+            0x01 => Ok((2048, 1)),
+            0x02 => Ok((8192, 1)),
+            0x03 => Ok((32768, 4)),
+            0x04 => Ok((131072, 16)),
+            0x05 => Ok((65536, 8)),
+            _ => Err(RomError::InvalidRamSize(byte)),
         }
     }
 
@@ -172,616 +504,6490 @@ impl Rom {
         &self.rom_size
     }
 
-    pub fn get_value(&self, addr: u16) -> &u8 {
-        match &self.data.get(&addr) {
-            Some(byte) => byte,
-            None => &0x00,
+    pub fn get_value(&self, addr: u16) -> u8 {
+        match self.data.get(addr as usize) {
+            Some(byte) => *byte,
+            None => 0x00,
+        }
+    }
+
+    pub fn bytes(&self) -> impl Iterator<Item = u8> + '_ {
+        self.data.iter().copied()
+    }
+
+    // Stops early at the end of the ROM instead of padding
+    pub fn slice(&self, start: u16, len: u16) -> Vec<u8> {
+        self.data
+            .iter()
+            .skip(start as usize)
+            .take(len as usize)
+            .copied()
+            .collect()
+    }
+
+    // Reads addr from within the given 16KiB ROM bank
+    pub fn get_banked_value(&self, bank: usize, addr: u16) -> u8 {
+        match self.data.get(bank * 0x4000 + (addr as usize & 0x3FFF)) {
+            Some(byte) => *byte,
+            None => 0x00,
+        }
+    }
+
+    // Picks the mapper the bus should use for this cartridge
+    fn create_mapper(&self) -> Result<Box<dyn Mapper>, RomError> {
+        if !self.cart_type.is_supported() {
+            return Err(RomError::UnsupportedMapper(self.get_value(0x0147)));
         }
+        let rom_banks: usize = self.rom_banks as usize;
+        let ram_size: usize = self.ram_size as usize;
+        let mapper: Box<dyn Mapper> = match self.cart_type {
+            CartType::MBC1 | CartType::MBC1RAM | CartType::MBC1RAMBATTERY => {
+                Box::new(Mbc1::new(rom_banks, ram_size))
+            }
+            CartType::MBC2 | CartType::MBC2BATTERY => Box::new(Mbc2::new(rom_banks)),
+            CartType::MMM01 | CartType::MMM01RAM | CartType::MMM01RAMBATTERY => {
+                Box::new(Mmm01::new(rom_banks, ram_size))
+            }
+            CartType::MBC3TIMERBATTERY
+            | CartType::MBC3TIMERRAMBATTERY
+            | CartType::MBC3
+            | CartType::MBC3RAM
+            | CartType::MBC3RAMBATTERY => Box::new(Mbc3::new(rom_banks, ram_size)),
+            CartType::MBC5 | CartType::MBC5RAM | CartType::MBC5RAMBATTERY => {
+                Box::new(Mbc5::new(rom_banks, ram_size))
+            }
+            CartType::MBC5RUMBLE | CartType::MBC5RUMBLERAM | CartType::MBC5RUMBLERAMBATTERY => {
+                Box::new(Mbc5::with_rumble(rom_banks, ram_size))
+            }
+            _ => Box::new(RomOnly::new(ram_size)),
+        };
+        Ok(mapper)
     }
 
     pub fn print_rom(&self) {
         for addr in 0..self.rom_size {
             print!("{:X?}:", addr);
-            println!("{:X?}", &self.get_value(addr as u16));
+            println!("{:X?}", self.get_value(addr as u16));
         }
     }
 
     pub fn get_title(&self) -> &String {
         &self.title
     }
-}
-
-pub struct Cpu {
-    a: u8,
-    b: u8,
-    c: u8,
-    d: u8,
-    e: u8,
-    f: u8,
-    h: u8,
-    l: u8,
-    sp: u16,
-    pc: u16,
-    membus: MemBus,
-}
-
-enum Register {
-    A,
-    B,
-    C,
-    D,
-    E,
-    F,
-    H,
-    L,
-    SP,
-    PC,
-    AF,
-    BC,
-    DE,
-    HL,
-}
 
-impl Cpu {
-    pub fn new(membus: MemBus) -> Self {
-        Cpu {
-            a: 0x00,
-            b: 0x00,
-            c: 0x00,
-            d: 0x00,
-            e: 0x00,
-            f: 0x00,
-            h: 0x00,
-            l: 0x00,
-            sp: 0xFFFE,
-            pc: 0x0100,
-            membus: membus,
+    // The boot ROM refuses to start a cartridge whose 0x014D byte doesn't match this sum of 0x0134-0x014C
+    pub fn header_checksum(&self) -> u8 {
+        let mut checksum: u8 = 0;
+        for addr in 0x0134..=0x014C {
+            checksum = checksum.wrapping_sub(self.get_value(addr)).wrapping_sub(1);
         }
+        checksum
     }
 
-    fn get_af(&self) -> u16 {
-        (self.a as u16) << 8 | self.f as u16
+    pub fn header_checksum_valid(&self) -> bool {
+        self.header_checksum() == self.get_value(0x014D)
     }
 
-    fn get_bc(&self) -> u16 {
-        (self.b as u16) << 8 | self.c as u16
+    // FNV-1a over the whole image, so save states tell cartridges apart even when their headers are blank
+    fn fingerprint(&self) -> [u8; 4] {
+        let mut hash: u32 = 0x811C_9DC5;
+        for byte in self.data.iter() {
+            hash = (hash ^ *byte as u32).wrapping_mul(0x0100_0193);
+        }
+        hash.to_le_bytes()
     }
 
-    fn get_de(&self) -> u16 {
-        (self.d as u16) << 8 | self.e as u16
+    // An empty stand-in for the cartridge while a save state is decoded
+    fn placeholder() -> Self {
+        Rom {
+            data: Vec::new(),
+            title: String::new(),
+            cgb_flag: 0,
+            cart_type: CartType::ROMONLY,
+            rom_size: 0,
+            rom_banks: 0,
+            ram_size: 0,
+            ram_banks: 0,
+        }
     }
 
-    fn get_hl(&self) -> u16 {
-        (self.h as u16) << 8 | self.l as u16
+    // The boot ROM compares the logo at 0x0104-0x0133 against its own copy and locks up on a mismatch,
+    // so running a real boot ROM already stops there like hardware
+    pub fn logo_matches(&self) -> bool {
+        NINTENDO_LOGO
+            .iter()
+            .enumerate()
+            .all(|(i, byte)| self.get_value(0x0104 + i as u16) == *byte)
     }
 
-    fn set_af(&mut self, value: u16) {
-        self.a = (value >> 8) as u8;
-        self.f = (value & 0x0F) as u8;
+    // Human readable summary of the header, one field per line
+    pub fn info(&self) -> String {
+        let header: CartridgeHeader = self.header();
+        let cgb: &str = if self.requires_cgb() {
+            "CGB only"
+        } else if self.supports_cgb() {
+            "CGB enhanced"
+        } else {
+            "DMG"
+        };
+        let checksum: String = if self.header_checksum_valid() {
+            String::from("OK")
+        } else {
+            format!(
+                "bad (header says {:02X}, computed {:02X})",
+                self.get_value(0x014D),
+                self.header_checksum()
+            )
+        };
+        let logo: &str = if self.logo_matches() {
+            "OK"
+        } else {
+            "does not match, the boot ROM will lock up"
+        };
+        let lines: [String; 10] = [
+            format!("Title: {}", header.title),
+            format!("Cartridge type: {}", header.cart_type),
+            format!(
+                "ROM size: {} KiB ({} banks)",
+                header.rom_size / 1024,
+                header.rom_banks
+            ),
+            format!(
+                "RAM size: {} KiB ({} banks)",
+                header.ram_size / 1024,
+                header.ram_banks
+            ),
+            format!("CGB flag: {:02X} ({cgb})", header.cgb_flag),
+            format!(
+                "SGB support: {}",
+                if self.supports_sgb() { "yes" } else { "no" }
+            ),
+            format!("Licensee: {}", header.licensee),
+            format!("Version: {}", header.version),
+            format!("Header checksum: {checksum}"),
+            format!("Nintendo logo: {logo}"),
+        ];
+        lines.join("\n")
     }
 
-    fn set_bc(&mut self, value: u16) {
-        self.b = (value >> 8) as u8;
-        self.c = (value & 0x0F) as u8;
+    pub fn header(&self) -> CartridgeHeader {
+        // Old licensee 0x33 means the code is in the new two character field at 0x0144
+        let licensee: String = match self.get_value(0x014B) {
+            0x33 => self
+                .slice(0x0144, 2)
+                .iter()
+                .map(|byte| *byte as char)
+                .collect(),
+            code => format!("{code:02X}"),
+        };
+        CartridgeHeader {
+            title: self.title.clone(),
+            cart_type: self.cart_type,
+            rom_size: self.rom_size,
+            rom_banks: self.rom_banks,
+            ram_size: self.ram_size,
+            ram_banks: self.ram_banks,
+            cgb_flag: self.cgb_flag,
+            sgb_flag: self.get_value(0x0146),
+            licensee,
+            version: self.get_value(0x014C),
+        }
     }
-    fn set_de(&mut self, value: u16) {
-        self.d = (value >> 8) as u8;
-        self.e = (value & 0x0F) as u8;
+
+    // Header byte 0x0143. 0x80 means the game also runs on a DMG, 0xC0 that it needs a CGB.
+    pub fn supports_cgb(&self) -> bool {
+        self.cgb_flag & 0x80 != 0
     }
-    fn set_hl(&mut self, value: u16) {
-        self.h = (value >> 8) as u8;
-        self.l = (value & 0x0F) as u8;
+
+    pub fn requires_cgb(&self) -> bool {
+        self.cgb_flag == 0xC0
     }
 
-    fn get_16b_value(&self) -> u16 {
-        (*self.membus.access(self.pc) as u16) << 8 | (*self.membus.access(self.pc + 1) as u16)
+    // SGB functions need 0x03 at 0x0146 and the new licensee code marker at 0x014B
+    pub fn supports_sgb(&self) -> bool {
+        self.get_value(0x0146) == 0x03 && self.get_value(0x014B) == 0x33
     }
+}
 
-    fn inc_pc(&mut self) {
-        self.pc += 1;
+// Returns the ROM with an IPS or BPS patch applied, picked by the patch's magic bytes
+pub fn apply_patch(rom: &[u8], patch: &[u8]) -> Result<Vec<u8>, PatchError> {
+    if patch.starts_with(b"PATCH") {
+        apply_ips(rom, patch)
+    } else if patch.starts_with(b"BPS1") {
+        apply_bps(rom, patch)
+    } else {
+        Err(PatchError::UnknownFormat)
     }
+}
 
-    fn not_implemented(&self) {
-        eprintln!("Instruction {:X?} not yet implemented", self.pc)
+// IPS is a list of (3 byte offset, 2 byte length, data) records ending in "EOF". A length of 0 marks a
+// run of one repeated byte. Some patches add a 3 byte size to truncate the ROM to after "EOF".
+fn apply_ips(rom: &[u8], patch: &[u8]) -> Result<Vec<u8>, PatchError> {
+    let mut output: Vec<u8> = rom.to_vec();
+    let mut reader: PatchReader = PatchReader::new(&patch[5..]);
+    loop {
+        let record: &[u8] = reader.read_bytes(3)?;
+        if record == b"EOF" {
+            break;
+        }
+        let offset: usize = u32::from_be_bytes([0, record[0], record[1], record[2]]) as usize;
+        let length: usize = reader.read_u16_be()? as usize;
+        let data: Vec<u8> = if length == 0 {
+            let run: usize = reader.read_u16_be()? as usize;
+            vec![reader.read_u8()?; run]
+        } else {
+            reader.read_bytes(length)?.to_vec()
+        };
+        if output.len() < offset + data.len() {
+            output.resize(offset + data.len(), 0x00);
+        }
+        output[offset..offset + data.len()].copy_from_slice(&data);
+    }
+    if let Ok(size) = reader.read_bytes(3) {
+        output.truncate(u32::from_be_bytes([0, size[0], size[1], size[2]]) as usize);
     }
+    Ok(output)
+}
 
-    // Operations need flag logic and timing logic
-    fn nop(&mut self) {
-        self.inc_pc();
+// BPS builds the patched ROM from copies out of the original, the patch and the output so far. CRC32s
+// of the original, the result and the patch itself sit in the last 12 bytes.
+fn apply_bps(rom: &[u8], patch: &[u8]) -> Result<Vec<u8>, PatchError> {
+    if patch.len() < 16 {
+        return Err(PatchError::Truncated);
     }
-    fn load_r8r8(&mut self, source: Register, dest: Register) {
-        let value: &u8 = match source {
-            Register::A => &self.a,
-            Register::B => &self.b,
-            Register::C => &self.c,
-            Register::D => &self.d,
-            Register::E => &self.e,
-            Register::F => &self.f,
-            Register::H => &self.h,
-            Register::L => &self.l,
+    let footer: usize = patch.len() - 12;
+    let checksum = |offset: usize| -> u32 {
+        u32::from_le_bytes([
+            patch[offset],
+            patch[offset + 1],
+            patch[offset + 2],
+            patch[offset + 3],
+        ])
+    };
+    if crc32(&patch[..patch.len() - 4]) != checksum(footer + 8) {
+        return Err(PatchError::CorruptPatch);
+    }
+    let source_crc: u32 = crc32(rom);
+    if source_crc != checksum(footer) {
+        return Err(PatchError::WrongSource {
+            expected: checksum(footer),
+            actual: source_crc,
+        });
+    }
+    let mut reader: PatchReader = PatchReader::new(&patch[4..footer]);
+    let _source_size: usize = reader.read_number()?;
+    let target_size: usize = reader.read_number()?;
+    let metadata_size: usize = reader.read_number()?;
+    reader.read_bytes(metadata_size)?;
+    let mut output: Vec<u8> = Vec::with_capacity(target_size);
+    let mut source_offset: usize = 0;
+    let mut target_offset: usize = 0;
+    while !reader.is_empty() {
+        let command: usize = reader.read_number()?;
+        let length: usize = (command >> 2) + 1;
+        match command & 0x03 {
+            // SourceRead copies the original at the same position
+            0 => {
+                let start: usize = output.len();
+                let bytes: &[u8] = rom
+                    .get(start..start + length)
+                    .ok_or(PatchError::Truncated)?;
+                output.extend_from_slice(bytes);
+            }
+            // TargetRead copies bytes stored in the patch
+            1 => output.extend_from_slice(reader.read_bytes(length)?),
+            // SourceCopy and TargetCopy move a cursor by a signed offset and copy from there. TargetCopy
+            // may overlap what it writes, so it goes a byte at a time.
+            2 => {
+                source_offset = reader.read_offset(source_offset)?;
+                let bytes: &[u8] = rom
+                    .get(source_offset..source_offset + length)
+                    .ok_or(PatchError::Truncated)?;
+                output.extend_from_slice(bytes);
+                source_offset += length;
+            }
             _ => {
-                eprintln!("Invalid register");
-                &0x00
+                target_offset = reader.read_offset(target_offset)?;
+                for _ in 0..length {
+                    let byte: u8 = *output.get(target_offset).ok_or(PatchError::Truncated)?;
+                    output.push(byte);
+                    target_offset += 1;
+                }
             }
         };
-        match dest {
-            Register::A => self.a = *value,
-            Register::B => self.b = *value,
-            Register::C => self.c = *value,
-            Register::D => self.d = *value,
-            Register::E => self.e = *value,
-            Register::F => self.f = *value,
-            Register::H => self.h = *value,
-            Register::L => self.l = *value,
-            _ => eprintln!("Invalid register"),
-        };
-        self.inc_pc();
     }
-    fn load_r8n8(&mut self, dest: Register) {
-        self.inc_pc();
-        match dest {
-            Register::A => self.a = *self.membus.access(self.pc),
-            Register::B => self.b = *self.membus.access(self.pc),
-            Register::C => self.c = *self.membus.access(self.pc),
-            Register::D => self.d = *self.membus.access(self.pc),
-            Register::E => self.e = *self.membus.access(self.pc),
-            Register::F => self.f = *self.membus.access(self.pc),
-            Register::H => self.h = *self.membus.access(self.pc),
-            Register::L => self.l = *self.membus.access(self.pc),
-            _ => eprintln!("Invalid register"),
-        };
-        self.inc_pc();
+    if output.len() != target_size || crc32(&output) != checksum(footer + 4) {
+        return Err(PatchError::WrongTarget);
     }
-    fn load_hlr8(&mut self, source: Register) {
-        match source {
-            Register::A => self.membus.write(self.get_hl(), self.a),
-            Register::B => self.membus.write(self.get_hl(), self.b),
-            Register::C => self.membus.write(self.get_hl(), self.c),
-            Register::D => self.membus.write(self.get_hl(), self.d),
-            Register::E => self.membus.write(self.get_hl(), self.e),
-            Register::F => self.membus.write(self.get_hl(), self.f),
-            Register::H => self.membus.write(self.get_hl(), self.h),
-            Register::L => self.membus.write(self.get_hl(), self.l),
-            _ => eprintln!("Invalid register"),
-        };
-        self.inc_pc();
+    Ok(output)
+}
+
+// CRC-32 as used by zip and BPS
+fn crc32(data: &[u8]) -> u32 {
+    let mut crc: u32 = 0xFFFF_FFFF;
+    for byte in data {
+        crc ^= *byte as u32;
+        for _ in 0..8 {
+            crc = if crc & 1 != 0 {
+                (crc >> 1) ^ 0xEDB8_8320
+            } else {
+                crc >> 1
+            };
+        }
     }
-    fn load_hln8(&mut self) {
-        self.inc_pc();
-        self.membus
-            .write(self.get_hl(), *self.membus.access(self.pc));
-        self.inc_pc();
+    !crc
+}
+
+struct PatchReader<'a> {
+    data: &'a [u8],
+    pos: usize,
+}
+
+impl<'a> PatchReader<'a> {
+    fn new(data: &'a [u8]) -> Self {
+        PatchReader { data, pos: 0 }
     }
-    fn load_r8hl(&mut self, dest: Register) {
-        self.inc_pc();
-        match dest {
-            Register::A => self.a = *self.membus.access(self.get_hl()),
-            Register::B => self.b = *self.membus.access(self.get_hl()),
-            Register::C => self.c = *self.membus.access(self.get_hl()),
-            Register::D => self.d = *self.membus.access(self.get_hl()),
-            Register::E => self.e = *self.membus.access(self.get_hl()),
-            Register::F => self.f = *self.membus.access(self.get_hl()),
-            Register::H => self.h = *self.membus.access(self.get_hl()),
-            Register::L => self.l = *self.membus.access(self.get_hl()),
-            _ => eprintln!("Invalid register"),
-        };
-        self.inc_pc();
+
+    fn is_empty(&self) -> bool {
+        self.pos >= self.data.len()
     }
-    fn load_r16n16(&mut self, dest: Register) {
-        self.inc_pc();
-        match dest {
-            Register::AF => self.set_af(self.get_16b_value()),
-            Register::BC => self.set_bc(self.get_16b_value()),
-            Register::DE => self.set_de(self.get_16b_value()),
-            Register::HL => self.set_hl(self.get_16b_value()),
-            Register::PC => self.pc = self.get_16b_value(),
-            Register::SP => self.sp = self.get_16b_value(),
-            _ => eprintln!("Invalid register"),
+
+    fn read_bytes(&mut self, len: usize) -> Result<&'a [u8], PatchError> {
+        match self.data.get(self.pos..self.pos + len) {
+            Some(bytes) => {
+                self.pos += len;
+                Ok(bytes)
+            }
+            None => Err(PatchError::Truncated),
         }
-        self.inc_pc();
-        self.inc_pc();
     }
-    fn load_r16a(&mut self, addr: Register) {
-        match addr {
-            Register::AF => self.membus.write(self.get_af(), self.a),
-            Register::BC => self.membus.write(self.get_bc(), self.a),
-            Register::DE => self.membus.write(self.get_de(), self.a),
-            Register::HL => self.membus.write(self.get_hl(), self.a),
-            Register::PC => self.membus.write(self.pc, self.a),
-            Register::SP => self.membus.write(self.sp, self.a),
-            _ => eprintln!("Invalid register"),
-        }
-        self.inc_pc();
+
+    fn read_u8(&mut self) -> Result<u8, PatchError> {
+        Ok(self.read_bytes(1)?[0])
     }
-    fn load_n16a(&mut self) {
-        self.inc_pc();
-        self.membus.write(self.get_16b_value(), self.a);
-        self.inc_pc();
-        self.inc_pc();
+
+    fn read_u16_be(&mut self) -> Result<u16, PatchError> {
+        let bytes: &[u8] = self.read_bytes(2)?;
+        Ok(u16::from_be_bytes([bytes[0], bytes[1]]))
     }
 
-    fn exec(&mut self) {
-        let op: &u8 = self.membus.access(self.pc);
-        match op {
-            0x00 => self.nop(),
-            0x01 => self.load_r16n16(Register::BC),
-            0x02 => self.load_r16a(Register::BC),
-            0x03 => self.not_implemented(),
-            0x04 => self.not_implemented(),
-            0x05 => self.not_implemented(),
-            0x06 => self.load_r8n8(Register::B),
-            0x07 => self.not_implemented(),
-            0x08 => self.not_implemented(),
-            0x09 => self.not_implemented(),
-            0x0A => self.not_implemented(),
-            0x0B => self.not_implemented(),
-            0x0C => self.not_implemented(),
-            0x0D => self.not_implemented(),
-            0x0E => self.load_r8n8(Register::C),
-            0x0F => self.not_implemented(),
-            0x10 => self.not_implemented(),
-            0x11 => self.load_r16n16(Register::DE),
-            0x12 => self.load_r16a(Register::DE),
-            0x13 => self.not_implemented(),
-            0x14 => self.not_implemented(),
-            0x15 => self.not_implemented(),
-            0x16 => self.load_r8n8(Register::D),
-            0x17 => self.not_implemented(),
-            0x18 => self.not_implemented(),
-            0x19 => self.not_implemented(),
-            0x1A => self.not_implemented(),
-            0x1B => self.not_implemented(),
-            0x1C => self.not_implemented(),
-            0x1D => self.not_implemented(),
-            0x1E => self.load_r8n8(Register::E),
-            0x1F => self.not_implemented(),
-            0x20 => self.not_implemented(),
-            0x21 => self.not_implemented(),
-            0x22 => self.not_implemented(),
-            0x23 => self.not_implemented(),
-            0x24 => self.not_implemented(),
-            0x25 => self.not_implemented(),
-            0x26 => self.load_r8n8(Register::H),
-            0x27 => self.not_implemented(),
-            0x28 => self.not_implemented(),
-            0x29 => self.not_implemented(),
-            0x2A => self.not_implemented(),
-            0x2B => self.not_implemented(),
-            0x2C => self.not_implemented(),
-            0x2D => self.not_implemented(),
-            0x2E => self.load_r8n8(Register::L),
-            0x2F => self.not_implemented(),
-            0x30 => self.not_implemented(),
-            0x31 => self.not_implemented(),
-            0x32 => self.not_implemented(),
-            0x33 => self.not_implemented(),
-            0x34 => self.not_implemented(),
-            0x35 => self.not_implemented(),
-            0x36 => self.not_implemented(),
-            0x37 => self.not_implemented(),
-            0x38 => self.not_implemented(),
-            0x39 => self.not_implemented(),
-            0x3A => self.not_implemented(),
-            0x3B => self.not_implemented(),
-            0x3C => self.not_implemented(),
-            0x3D => self.not_implemented(),
-            0x3E => self.load_r8n8(Register::A),
-            0x3F => self.not_implemented(),
-            0x40 => self.load_r8r8(Register::B, Register::B),
-            0x41 => self.load_r8r8(Register::C, Register::B),
-            0x42 => self.load_r8r8(Register::D, Register::B),
-            0x43 => self.load_r8r8(Register::E, Register::B),
-            0x44 => self.load_r8r8(Register::B, Register::H),
-            0x45 => self.load_r8r8(Register::B, Register::L),
-            0x46 => self.load_r8hl(Register::B),
-            0x47 => self.load_r8r8(Register::B, Register::A),
-            0x48 => self.load_r8r8(Register::C, Register::B),
-            0x49 => self.load_r8r8(Register::C, Register::C),
-            0x4A => self.load_r8r8(Register::C, Register::D),
-            0x4B => self.load_r8r8(Register::C, Register::E),
-            0x4C => self.load_r8r8(Register::C, Register::H),
-            0x4D => self.load_r8r8(Register::C, Register::L),
-            0x4E => self.load_r8hl(Register::C),
-            0x4F => self.load_r8r8(Register::C, Register::A),
-            0x50 => self.load_r8r8(Register::D, Register::B),
-            0x51 => self.load_r8r8(Register::D, Register::C),
-            0x52 => self.load_r8r8(Register::D, Register::D),
-            0x53 => self.load_r8r8(Register::D, Register::E),
-            0x54 => self.load_r8r8(Register::D, Register::H),
-            0x55 => self.load_r8r8(Register::D, Register::L),
-            0x56 => self.load_r8hl(Register::D),
-            0x57 => self.load_r8r8(Register::D, Register::A),
-            0x58 => self.load_r8r8(Register::E, Register::B),
-            0x59 => self.load_r8r8(Register::E, Register::C),
-            0x5A => self.load_r8r8(Register::E, Register::D),
-            0x5B => self.load_r8r8(Register::E, Register::E),
-            0x5C => self.load_r8r8(Register::E, Register::H),
-            0x5D => self.load_r8r8(Register::E, Register::L),
-            0x5E => self.load_r8hl(Register::E),
-            0x5F => self.load_r8r8(Register::E, Register::A),
-            0x60 => self.load_r8r8(Register::H, Register::B),
-            0x61 => self.load_r8r8(Register::H, Register::C),
-            0x62 => self.load_r8r8(Register::H, Register::D),
-            0x63 => self.load_r8r8(Register::H, Register::E),
-            0x64 => self.load_r8r8(Register::H, Register::H),
-            0x65 => self.load_r8r8(Register::H, Register::L),
-            0x66 => self.load_r8hl(Register::H),
-            0x67 => self.load_r8r8(Register::H, Register::A),
-            0x68 => self.load_r8r8(Register::L, Register::B),
-            0x69 => self.load_r8r8(Register::L, Register::C),
-            0x6A => self.load_r8r8(Register::L, Register::D),
-            0x6B => self.load_r8r8(Register::L, Register::E),
-            0x6C => self.load_r8r8(Register::L, Register::H),
-            0x6D => self.load_r8r8(Register::L, Register::L),
-            0x6E => self.load_r8hl(Register::L),
-            0x6F => self.load_r8r8(Register::L, Register::A),
-            0x70 => self.load_hlr8(Register::B),
-            0x71 => self.load_hlr8(Register::C),
-            0x72 => self.load_hlr8(Register::D),
-            0x73 => self.load_hlr8(Register::E),
-            0x74 => self.load_hlr8(Register::H),
-            0x75 => self.load_hlr8(Register::L),
-            0x76 => self.not_implemented(),
-            0x77 => self.load_hlr8(Register::A),
-            0x78 => self.load_r8r8(Register::A, Register::B),
-            0x79 => self.load_r8r8(Register::A, Register::C),
-            0x7A => self.load_r8r8(Register::A, Register::D),
-            0x7B => self.load_r8r8(Register::A, Register::E),
-            0x7C => self.load_r8r8(Register::A, Register::H),
-            0x7D => self.load_r8r8(Register::A, Register::L),
-            0x7E => self.load_r8hl(Register::A),
-            0x7F => self.load_r8r8(Register::A, Register::A),
-            0x80 => self.not_implemented(),
-            0x81 => self.not_implemented(),
-            0x82 => self.not_implemented(),
-            0x83 => self.not_implemented(),
-            0x84 => self.not_implemented(),
-            0x85 => self.not_implemented(),
-            0x86 => self.not_implemented(),
-            0x87 => self.not_implemented(),
-            0x88 => self.not_implemented(),
-            0x89 => self.not_implemented(),
-            0x8A => self.not_implemented(),
-            0x8B => self.not_implemented(),
-            0x8C => self.not_implemented(),
-            0x8D => self.not_implemented(),
-            0x8E => self.not_implemented(),
-            0x8F => self.not_implemented(),
-            0x90 => self.not_implemented(),
-            0x91 => self.not_implemented(),
-            0x92 => self.not_implemented(),
-            0x93 => self.not_implemented(),
-            0x94 => self.not_implemented(),
-            0x95 => self.not_implemented(),
-            0x96 => self.not_implemented(),
-            0x97 => self.not_implemented(),
-            0x98 => self.not_implemented(),
-            0x99 => self.not_implemented(),
-            0x9A => self.not_implemented(),
-            0x9B => self.not_implemented(),
-            0x9C => self.not_implemented(),
-            0x9D => self.not_implemented(),
-            0x9E => self.not_implemented(),
-            0x9F => self.not_implemented(),
-            0xA0 => self.not_implemented(),
-            0xA1 => self.not_implemented(),
-            0xA2 => self.not_implemented(),
-            0xA3 => self.not_implemented(),
-            0xA4 => self.not_implemented(),
-            0xA5 => self.not_implemented(),
-            0xA6 => self.not_implemented(),
-            0xA7 => self.not_implemented(),
-            0xA8 => self.not_implemented(),
-            0xA9 => self.not_implemented(),
-            0xAA => self.not_implemented(),
-            0xAB => self.not_implemented(),
-            0xAC => self.not_implemented(),
-            0xAD => self.not_implemented(),
-            0xAE => self.not_implemented(),
-            0xAF => self.not_implemented(),
-            0xB0 => self.not_implemented(),
-            0xB1 => self.not_implemented(),
-            0xB2 => self.not_implemented(),
-            0xB3 => self.not_implemented(),
-            0xB4 => self.not_implemented(),
-            0xB5 => self.not_implemented(),
-            0xB6 => self.not_implemented(),
-            0xB7 => self.not_implemented(),
-            0xB8 => self.not_implemented(),
-            0xB9 => self.not_implemented(),
-            0xBA => self.not_implemented(),
-            0xBB => self.not_implemented(),
-            0xBC => self.not_implemented(),
-            0xBD => self.not_implemented(),
-            0xBE => self.not_implemented(),
-            0xBF => self.not_implemented(),
-            0xC0 => self.not_implemented(),
-            0xC1 => self.not_implemented(),
-            0xC2 => self.not_implemented(),
-            0xC3 => self.not_implemented(),
-            0xC4 => self.not_implemented(),
-            0xC5 => self.not_implemented(),
-            0xC6 => self.not_implemented(),
-            0xC7 => self.not_implemented(),
-            0xC8 => self.not_implemented(),
-            0xC9 => self.not_implemented(),
-            0xCA => self.not_implemented(),
-            0xCB => self.not_implemented(),
-            0xCC => self.not_implemented(),
-            0xCD => self.not_implemented(),
-            0xCE => self.not_implemented(),
-            0xCF => self.not_implemented(),
-            0xD0 => self.not_implemented(),
-            0xD1 => self.not_implemented(),
-            0xD2 => self.not_implemented(),
-            0xD3 => self.not_implemented(),
-            0xD4 => self.not_implemented(),
-            0xD5 => self.not_implemented(),
-            0xD6 => self.not_implemented(),
-            0xD7 => self.not_implemented(),
-            0xD8 => self.not_implemented(),
-            0xD9 => self.not_implemented(),
-            0xDA => self.not_implemented(),
-            0xDB => self.not_implemented(),
-            0xDC => self.not_implemented(),
-            0xDD => self.not_implemented(),
-            0xDE => self.not_implemented(),
-            0xDF => self.not_implemented(),
-            0xE0 => self.not_implemented(),
-            0xE1 => self.not_implemented(),
-            0xE2 => self.not_implemented(),
-            0xE3 => self.not_implemented(),
-            0xE4 => self.not_implemented(),
-            0xE5 => self.not_implemented(),
-            0xE6 => self.not_implemented(),
-            0xE7 => self.not_implemented(),
-            0xE8 => self.not_implemented(),
-            0xE9 => self.not_implemented(),
-            0xEA => self.not_implemented(),
-            0xEB => self.not_implemented(),
-            0xEC => self.not_implemented(),
-            0xED => self.not_implemented(),
-            0xEE => self.not_implemented(),
-            0xEF => self.not_implemented(),
-            0xF0 => self.not_implemented(),
-            0xF1 => self.not_implemented(),
-            0xF2 => self.not_implemented(),
-            0xF3 => self.not_implemented(),
-            0xF4 => self.not_implemented(),
-            0xF5 => self.not_implemented(),
-            0xF6 => self.not_implemented(),
-            0xF7 => self.not_implemented(),
-            0xF8 => self.not_implemented(),
-            0xF9 => self.not_implemented(),
-            0xFA => self.not_implemented(),
-            0xFB => self.not_implemented(),
-            0xFC => self.not_implemented(),
-            0xFD => self.not_implemented(),
-            0xFE => self.not_implemented(),
-            0xFF => self.not_implemented(),
+    // BPS numbers are 7 bits per byte, lowest first, with the top bit marking the last byte. Each
+    // continuation also adds one so no number has two encodings.
+    fn read_number(&mut self) -> Result<usize, PatchError> {
+        let mut number: usize = 0;
+        let mut shift: usize = 1;
+        loop {
+            let byte: u8 = self.read_u8()?;
+            number += (byte & 0x7F) as usize * shift;
+            if byte & 0x80 != 0 {
+                return Ok(number);
+            }
+            shift <<= 7;
+            number += shift;
+        }
+    }
+
+    // A cursor moved by a signed offset, stored as magnitude << 1 | sign
+    fn read_offset(&mut self, cursor: usize) -> Result<usize, PatchError> {
+        let offset: usize = self.read_number()?;
+        let moved: Option<usize> = if offset & 1 != 0 {
+            cursor.checked_sub(offset >> 1)
+        } else {
+            cursor.checked_add(offset >> 1)
         };
+        moved.ok_or(PatchError::Truncated)
     }
+}
 
-    pub fn run(&mut self) {
-        loop {
-            self.exec();
+// Called with the address and value of a CPU memory access
+pub type MemoryHook = Box<dyn FnMut(u16, u8)>;
+
+// A peripheral seen by the CPU through registers in the IO page. addr is the full register address.
+pub trait IoDevice {
+    fn read(&self, addr: u16) -> u8;
+    fn write(&mut self, addr: u16, value: u8);
+}
+
+// A mapper owns the cartridge RAM and the banking registers that are written through the ROM address space
+pub trait Mapper {
+    // The ROM bank mapped at addr (0x0000-0x7FFF)
+    fn rom_bank(&self, addr: u16) -> usize;
+    fn write_register(&mut self, addr: u16, value: u8);
+    fn read_ram(&self, addr: u16) -> u8;
+    fn write_ram(&mut self, addr: u16, value: u8);
+    fn ram(&self) -> &[u8];
+    fn ram_mut(&mut self) -> &mut [u8];
+    // A copy tagged with the concrete type so serde can bring back the right mapper
+    fn tagged(&self) -> TaggedMapper;
+
+    fn read_rom(&self, rom: &Rom, addr: u16) -> u8 {
+        rom.get_banked_value(self.rom_bank(addr), addr)
+    }
+
+    // Only mappers with a clock need to track time
+    fn tick(&mut self, _cycles: u32) {}
+
+    // Whether the cartridge is currently asking for its rumble motor to run
+    fn rumble(&self) -> bool {
+        false
+    }
+
+    // Whether 0xA000-0xBFFF currently reaches the cartridge RAM
+    fn ram_enabled(&self) -> bool;
+
+    // The register value selecting what 0xA000-0xBFFF shows. On MBC3 0x08-0x0C pick a clock register.
+    fn ram_bank(&self) -> usize {
+        0
+    }
+
+    // MBC1 style banking mode, 0 on mappers that don't have one
+    fn banking_mode(&self) -> u8 {
+        0
+    }
+
+    // Battery backed save file contents. now is the wall clock time in unix seconds.
+    fn save_ram(&self, _now: u64) -> Vec<u8> {
+        self.ram().to_vec()
+    }
+
+    fn load_ram(&mut self, data: &[u8], _now: u64) {
+        let ram: &mut [u8] = self.ram_mut();
+        if data.len() != ram.len() {
+            log::warn!(
+                "Save file is {} bytes but the cartridge has {} bytes of RAM",
+                data.len(),
+                ram.len()
+            );
         }
+        let len: usize = data.len().min(ram.len());
+        ram[..len].copy_from_slice(&data[..len]);
     }
 }
 
-pub struct Wram {
-    data: HashMap<u16, u8>,
+// The mapper's banking registers as the debugger shows them
+#[derive(Clone, Copy, PartialEq, Debug)]
+pub struct BankState {
+    // Banks mapped at 0x0000-0x3FFF and 0x4000-0x7FFF
+    pub rom_bank0: usize,
+    pub rom_bank: usize,
+    pub ram_bank: usize,
+    pub ram_enabled: bool,
+    pub mode: u8,
 }
-impl Wram {
-    pub fn new() -> Self {
-        Wram {
-            data: HashMap::new(),
+
+#[derive(Serialize, Deserialize)]
+pub enum TaggedMapper {
+    RomOnly(RomOnly),
+    Mbc1(Mbc1),
+    Mbc2(Box<Mbc2>),
+    Mmm01(Mmm01),
+    Mbc3(Mbc3),
+    Mbc5(Mbc5),
+}
+
+impl TaggedMapper {
+    fn into_mapper(self) -> Box<dyn Mapper> {
+        match self {
+            TaggedMapper::RomOnly(mapper) => Box::new(mapper),
+            TaggedMapper::Mbc1(mapper) => Box::new(mapper),
+            TaggedMapper::Mbc2(mapper) => mapper,
+            TaggedMapper::Mmm01(mapper) => Box::new(mapper),
+            TaggedMapper::Mbc3(mapper) => Box::new(mapper),
+            TaggedMapper::Mbc5(mapper) => Box::new(mapper),
         }
     }
-    pub fn set_value(&mut self, addr: u16, entry: u8) {
-        self.data.insert(addr, entry);
+}
+
+// serialize_with hands over a reference to the field itself
+#[allow(clippy::borrowed_box)]
+fn serialize_mapper<S: serde::Serializer>(
+    mapper: &Box<dyn Mapper>,
+    serializer: S,
+) -> Result<S::Ok, S::Error> {
+    mapper.tagged().serialize(serializer)
+}
+
+fn deserialize_mapper<'de, D: serde::Deserializer<'de>>(
+    deserializer: D,
+) -> Result<Box<dyn Mapper>, D::Error> {
+    Ok(TaggedMapper::deserialize(deserializer)?.into_mapper())
+}
+
+// HashMap order changes from run to run, so memory is written sorted to keep equal machines' save
+// states byte for byte equal
+fn serialize_sorted<S: serde::Serializer>(
+    data: &HashMap<u16, u8>,
+    serializer: S,
+) -> Result<S::Ok, S::Error> {
+    let sorted: std::collections::BTreeMap<&u16, &u8> = data.iter().collect();
+    sorted.serialize(serializer)
+}
+
+// Current wall clock time in unix seconds, for clocks that keep running while the emulator is closed
+fn unix_time() -> u64 {
+    #[cfg(target_arch = "wasm32")]
+    {
+        (js_sys::Date::now() / 1000.0) as u64
     }
-    pub fn get_value(&self, addr: u16) -> &u8 {
-        match &self.data.get(&addr) {
-            Some(byte) => byte,
-            None => &0x00,
+    #[cfg(not(target_arch = "wasm32"))]
+    {
+        match std::time::SystemTime::now().duration_since(std::time::UNIX_EPOCH) {
+            Ok(duration) => duration.as_secs(),
+            Err(_) => 0,
         }
     }
 }
-pub struct Vram {
-    data: HashMap<u16, u8>,
+
+#[derive(Clone, Serialize, Deserialize)]
+pub struct RomOnly {
+    ram: Vec<u8>,
 }
-impl Vram {
-    pub fn new() -> Self {
-        Vram {
-            data: HashMap::new(),
+
+impl RomOnly {
+    pub fn new(ram_size: usize) -> Self {
+        RomOnly {
+            ram: vec![0x00; ram_size],
         }
     }
-    pub fn set_value(&mut self, addr: u16, entry: u8) {
-        self.data.insert(addr, entry);
+}
+
+impl Mapper for RomOnly {
+    fn rom_bank(&self, addr: u16) -> usize {
+        (addr >> 14) as usize
+    }
+
+    // There is no enable register, RAM is always there if the cartridge has any
+    fn ram_enabled(&self) -> bool {
+        !self.ram.is_empty()
+    }
+
+    fn write_register(&mut self, _addr: u16, _value: u8) {}
+
+    fn read_ram(&self, addr: u16) -> u8 {
+        match self.ram.get((addr - 0xA000) as usize) {
+            Some(byte) => *byte,
+            None => 0xFF,
+        }
     }
-    pub fn get_value(&self, addr: u16) -> &u8 {
-        match &self.data.get(&addr) {
-            Some(byte) => byte,
-            None => &0x00,
+
+    fn write_ram(&mut self, addr: u16, value: u8) {
+        if let Some(byte) = self.ram.get_mut((addr - 0xA000) as usize) {
+            *byte = value;
         }
     }
+
+    fn ram(&self) -> &[u8] {
+        &self.ram
+    }
+
+    fn ram_mut(&mut self) -> &mut [u8] {
+        &mut self.ram
+    }
+
+    fn tagged(&self) -> TaggedMapper {
+        TaggedMapper::RomOnly(self.clone())
+    }
 }
 
-pub struct MemBus {
-    rom: Rom,
-    wram: Wram,
-    vram: Vram,
+#[derive(Clone, Serialize, Deserialize)]
+pub struct Mbc1 {
+    ram: Vec<u8>,
+    ram_enabled: bool,
+    rom_bank: u8,
+    upper_bits: u8,
+    mode: u8,
+    rom_banks: usize,
 }
 
-impl MemBus {
-    pub fn new(rom: Rom) -> Self {
-        MemBus {
-            rom: rom,
-            wram: Wram::new(),
-            vram: Vram::new(),
+impl Mbc1 {
+    pub fn new(rom_banks: usize, ram_size: usize) -> Self {
+        Mbc1 {
+            ram: vec![0x00; ram_size],
+            ram_enabled: false,
+            rom_bank: 1,
+            upper_bits: 0,
+            mode: 0,
+            rom_banks,
+        }
+    }
+
+    fn ram_offset(&self, addr: u16) -> usize {
+        (self.ram_bank() * 0x2000 + (addr - 0xA000) as usize) % self.ram.len()
+    }
+}
+
+impl Mapper for Mbc1 {
+    // In mode 1 the upper bits also select the bank mapped at 0x0000-0x3FFF
+    fn rom_bank(&self, addr: u16) -> usize {
+        let bank: usize = match addr {
+            0x0000..=0x3FFF if self.mode == 1 => (self.upper_bits as usize) << 5,
+            0x0000..=0x3FFF => 0,
+            _ => (self.upper_bits as usize) << 5 | self.rom_bank as usize,
+        };
+        bank % self.rom_banks
+    }
+
+    fn ram_enabled(&self) -> bool {
+        self.ram_enabled
+    }
+
+    // In mode 1 the upper bits also select the RAM bank
+    fn ram_bank(&self) -> usize {
+        if self.mode == 1 {
+            self.upper_bits as usize
+        } else {
+            0
         }
     }
 
-    fn access(&self, addr: u16) -> &u8 {
+    fn banking_mode(&self) -> u8 {
+        self.mode
+    }
+
+    fn write_register(&mut self, addr: u16, value: u8) {
         match addr {
-            0x0000..=0x3FFF => self.rom.get_value(addr),
-            0x4000..=0x7FFF => self.rom.get_value(addr), // This should be able to access switchable rom banks through a mapper, to be fixed later.
-            0x8000..=0x9FFF => self.vram.get_value(addr),
-            0xA000..=0xBFFF => &0x00, // should access external ram on cartridge
-            0xC000..=0xCFFF => self.wram.get_value(addr),
-            0xD000..=0xDFFF => self.wram.get_value(addr), // This should also be a switchable bank to be fixed later
-            0xE000..=0xFDFF => &0x00,                     // Echo RAM. Can be ignored.
-            0xFE00..=0xFE9F => &0x00,                     // Object attribute memory
-            0xFEA0..=0xFEFF => &0xFF,                     // Not usable, ignore.
-            0xFF00..=0xFF7F => &0x00,                     // IO registers
-            0xFF80..=0xFFFE => &0x00,                     // High RAM
-            0xFFFF => &0x00,                              // Interrupt register
+            0x0000..=0x1FFF => self.ram_enabled = value & 0x0F == 0x0A,
+            0x2000..=0x3FFF => self.rom_bank = (value & 0x1F).max(1),
+            0x4000..=0x5FFF => self.upper_bits = value & 0x03,
+            _ => self.mode = value & 0x01,
+        };
+    }
+
+    fn read_ram(&self, addr: u16) -> u8 {
+        if !self.ram_enabled || self.ram.is_empty() {
+            return 0xFF;
         }
+        self.ram[self.ram_offset(addr)]
     }
 
-    fn write(&mut self, addr: u16, entry: u8) {
+    fn write_ram(&mut self, addr: u16, value: u8) {
+        if self.ram_enabled && !self.ram.is_empty() {
+            let offset: usize = self.ram_offset(addr);
+            self.ram[offset] = value;
+        }
+    }
+
+    fn ram(&self) -> &[u8] {
+        &self.ram
+    }
+
+    fn ram_mut(&mut self) -> &mut [u8] {
+        &mut self.ram
+    }
+
+    fn tagged(&self) -> TaggedMapper {
+        TaggedMapper::Mbc1(self.clone())
+    }
+}
+
+// MBC2 has 512 half-bytes of RAM built into the chip. The header RAM size is always 0.
+#[derive(Clone, Serialize, Deserialize)]
+pub struct Mbc2 {
+    #[serde(with = "BigArray")]
+    ram: [u8; 0x200],
+    ram_enabled: bool,
+    rom_bank: u8,
+    rom_banks: usize,
+}
+
+impl Mbc2 {
+    pub fn new(rom_banks: usize) -> Self {
+        Mbc2 {
+            ram: [0x00; 0x200],
+            ram_enabled: false,
+            rom_bank: 1,
+            rom_banks,
+        }
+    }
+}
+
+impl Mapper for Mbc2 {
+    fn rom_bank(&self, addr: u16) -> usize {
+        match addr {
+            0x0000..=0x3FFF => 0,
+            _ => self.rom_bank as usize % self.rom_banks,
+        }
+    }
+
+    fn ram_enabled(&self) -> bool {
+        self.ram_enabled
+    }
+
+    // Bit 8 of the address picks the register: clear for RAM enable, set for ROM bank
+    fn write_register(&mut self, addr: u16, value: u8) {
         match addr {
-            0x0000..=0x3FFF => eprintln!("Attempted to write to ROM address {addr}"),
-            0x4000..=0x7FFF => eprintln!("Attempted to write to ROM address {addr}"),
-            0x8000..=0x9FFF => self.vram.set_value(addr, entry),
-            0xA000..=0xBFFF => (), // should access external ram on cartridge
-            0xC000..=0xCFFF => self.wram.set_value(addr, entry),
-            0xD000..=0xDFFF => self.wram.set_value(addr, entry), // This should be a switchable bank to be fixed later
-            0xE000..=0xFDFF => eprintln!("Attempted to write to echo RAM address {addr}"),
-            0xFE00..=0xFE9F => (), // Object attribute memory
-            0xFEA0..=0xFEFF => eprintln!("Attempted to write to unuasable space address {addr}"),
-            0xFF00..=0xFF7F => (), // IO registers
-            0xFF80..=0xFFFE => (), // High RAM
-            0xFFFF => (),          // Interrupt register
+            0x0000..=0x3FFF if addr & 0x0100 == 0 => self.ram_enabled = value & 0x0F == 0x0A,
+            0x0000..=0x3FFF => self.rom_bank = (value & 0x0F).max(1),
+            _ => (),
         };
     }
+
+    // Only the low nibble is stored, the upper nibble reads back as 1s. RAM repeats through 0xA000-0xBFFF.
+    fn read_ram(&self, addr: u16) -> u8 {
+        if !self.ram_enabled {
+            return 0xFF;
+        }
+        self.ram[(addr & 0x01FF) as usize] | 0xF0
+    }
+
+    fn write_ram(&mut self, addr: u16, value: u8) {
+        if self.ram_enabled {
+            self.ram[(addr & 0x01FF) as usize] = value & 0x0F;
+        }
+    }
+
+    fn ram(&self) -> &[u8] {
+        &self.ram
+    }
+
+    fn ram_mut(&mut self) -> &mut [u8] {
+        &mut self.ram
+    }
+
+    fn tagged(&self) -> TaggedMapper {
+        TaggedMapper::Mbc2(Box::new(self.clone()))
+    }
 }
 
-pub struct Gui {
-    cpu: Cpu,
+// Multicart mapper. Boots unmapped with the menu in the last 32 KiB of ROM. Once the menu sets the
+// map bit the outer bank bits it picked are locked and the selected game sees an MBC1.
+#[derive(Clone, Serialize, Deserialize)]
+pub struct Mmm01 {
+    ram: Vec<u8>,
+    ram_enabled: bool,
+    mapped: bool,
+    rom_bank_low: u8,
+    rom_bank_mid: u8,
+    rom_bank_high: u8,
+    rom_mask: u8,
+    ram_bank_low: u8,
+    ram_bank_high: u8,
+    mode: u8,
+    rom_banks: usize,
 }
 
-impl Gui {
-    pub fn new(cpu: Cpu) -> Self {
-        Gui { cpu: cpu }
+impl Mmm01 {
+    pub fn new(rom_banks: usize, ram_size: usize) -> Self {
+        Mmm01 {
+            ram: vec![0x00; ram_size],
+            ram_enabled: false,
+            mapped: false,
+            rom_bank_low: 0,
+            rom_bank_mid: 0,
+            rom_bank_high: 0,
+            rom_mask: 0,
+            ram_bank_low: 0,
+            ram_bank_high: 0,
+            mode: 0,
+            rom_banks,
+        }
+    }
+
+    fn outer_bank(&self) -> usize {
+        (self.rom_bank_high as usize) << 7 | (self.rom_bank_mid as usize) << 5
+    }
+
+    // Bits 1-4 of the low bank register that are covered by the mask stay as the menu left them
+    fn locked_low_bits(&self) -> u8 {
+        (self.rom_mask << 1) & 0x1E
+    }
+
+    fn ram_offset(&self, addr: u16) -> usize {
+        (self.ram_bank() * 0x2000 + (addr - 0xA000) as usize) % self.ram.len()
     }
 }
 
-impl App for Gui {
-    fn update(&mut self, ctx: &egui::Context, _frame: &mut eframe::Frame) {
-        egui::CentralPanel::default().show(ctx, |ui| {
-            ui.heading("rgb-emu CPU visualizer");
-            ui.label(format!("A: {:X?}", self.cpu.a));
-            ui.label(format!("B: {:X?}", self.cpu.b));
-            ui.label(format!("C: {:X?}", self.cpu.c));
-            ui.label(format!("D: {:X?}", self.cpu.d));
-            ui.label(format!("E: {:X?}", self.cpu.e));
-            ui.label(format!("F: {:X?}", self.cpu.f));
-            ui.label(format!("H: {:X?}", self.cpu.h));
-            ui.label(format!("L: {:X?}", self.cpu.l));
-            ui.label(format!("SP: {:X?}", self.cpu.sp));
-            ui.label(format!("PC: {:X?}", self.cpu.pc));
-        });
+impl Mapper for Mmm01 {
+    fn rom_bank(&self, addr: u16) -> usize {
+        let bank: usize = match (self.mapped, addr) {
+            (false, 0x0000..=0x3FFF) => self.rom_banks.saturating_sub(2),
+            (false, _) => self.rom_banks.saturating_sub(1),
+            (true, 0x0000..=0x3FFF) => {
+                self.outer_bank() | (self.rom_bank_low & self.locked_low_bits()) as usize
+            }
+            (true, _) => self.outer_bank() | self.rom_bank_low.max(1) as usize,
+        };
+        bank % self.rom_banks
+    }
+
+    fn ram_enabled(&self) -> bool {
+        self.ram_enabled
+    }
+
+    fn ram_bank(&self) -> usize {
+        let low: usize = if self.mode == 1 {
+            self.ram_bank_low as usize
+        } else {
+            0
+        };
+        (self.ram_bank_high as usize) << 2 | low
+    }
+
+    fn banking_mode(&self) -> u8 {
+        self.mode
+    }
+
+    // While unmapped each register also carries the outer bank bits for the game the menu picks
+    fn write_register(&mut self, addr: u16, value: u8) {
+        match addr {
+            0x0000..=0x1FFF => {
+                self.ram_enabled = value & 0x0F == 0x0A;
+                if !self.mapped {
+                    self.mapped = value & 0b0100_0000 != 0;
+                }
+            }
+            0x2000..=0x3FFF => {
+                let locked: u8 = if self.mapped {
+                    self.locked_low_bits()
+                } else {
+                    0
+                };
+                self.rom_bank_low = (self.rom_bank_low & locked) | (value & 0x1F & !locked);
+                if !self.mapped {
+                    self.rom_bank_mid = (value >> 5) & 0x03;
+                }
+            }
+            0x4000..=0x5FFF => {
+                self.ram_bank_low = value & 0x03;
+                if !self.mapped {
+                    self.ram_bank_high = (value >> 2) & 0x03;
+                    self.rom_bank_high = (value >> 4) & 0x03;
+                }
+            }
+            _ => {
+                self.mode = value & 0x01;
+                if !self.mapped {
+                    self.rom_mask = (value >> 2) & 0x0F;
+                }
+            }
+        };
+    }
+
+    fn read_ram(&self, addr: u16) -> u8 {
+        if !self.ram_enabled || self.ram.is_empty() {
+            return 0xFF;
+        }
+        self.ram[self.ram_offset(addr)]
+    }
+
+    fn write_ram(&mut self, addr: u16, value: u8) {
+        if self.ram_enabled && !self.ram.is_empty() {
+            let offset: usize = self.ram_offset(addr);
+            self.ram[offset] = value;
+        }
+    }
+
+    fn ram(&self) -> &[u8] {
+        &self.ram
+    }
+
+    fn ram_mut(&mut self) -> &mut [u8] {
+        &mut self.ram
+    }
+
+    fn tagged(&self) -> TaggedMapper {
+        TaggedMapper::Mmm01(self.clone())
+    }
+}
+
+#[derive(Clone, Serialize, Deserialize)]
+pub struct Mbc3 {
+    ram: Vec<u8>,
+    ram_enabled: bool,
+    rom_bank: u8,
+    ram_bank: u8,
+    rtc: [u8; 5],
+    latched_rtc: [u8; 5],
+    latch: u8,
+    rtc_cycles: u32,
+    rom_banks: usize,
+}
+
+impl Mbc3 {
+    pub fn new(rom_banks: usize, ram_size: usize) -> Self {
+        Mbc3 {
+            ram: vec![0x00; ram_size],
+            ram_enabled: false,
+            rom_bank: 1,
+            ram_bank: 0,
+            rtc: [0x00; 5],
+            latched_rtc: [0x00; 5],
+            latch: 0xFF,
+            rtc_cycles: 0,
+            rom_banks,
+        }
+    }
+
+    const CYCLES_PER_SECOND: u32 = 4194304;
+    // 5 running and 5 latched registers as u32 followed by a u64 timestamp, as written by BGB and VBA-M
+    const RTC_FOOTER_LEN: usize = 48;
+
+    fn rtc_halted(&self) -> bool {
+        self.rtc[4] & 0b0100_0000 != 0
+    }
+
+    // RTC registers are seconds, minutes, hours, the low 8 bits of the day counter and then
+    // DH holding day bit 8, halt (bit 6) and day counter carry (bit 7)
+    fn advance_rtc(&mut self, seconds: u64) {
+        if self.rtc_halted() || seconds == 0 {
+            return;
+        }
+        let days: u64 = ((self.rtc[4] as u64 & 0x01) << 8) | self.rtc[3] as u64;
+        let total: u64 = self.rtc[0] as u64
+            + self.rtc[1] as u64 * 60
+            + self.rtc[2] as u64 * 3600
+            + days * 86400
+            + seconds;
+        let days: u64 = total / 86400;
+        self.rtc[0] = (total % 60) as u8;
+        self.rtc[1] = (total / 60 % 60) as u8;
+        self.rtc[2] = (total / 3600 % 24) as u8;
+        self.rtc[3] = (days & 0xFF) as u8;
+        self.rtc[4] = (self.rtc[4] & 0b1100_0000) | ((days >> 8) & 0x01) as u8;
+        if days > 511 {
+            self.rtc[4] |= 0b1000_0000;
+        }
+    }
+}
+
+impl Mapper for Mbc3 {
+    fn rom_bank(&self, addr: u16) -> usize {
+        match addr {
+            0x0000..=0x3FFF => 0,
+            _ => self.rom_bank as usize % self.rom_banks,
+        }
+    }
+
+    fn ram_enabled(&self) -> bool {
+        self.ram_enabled
+    }
+
+    fn ram_bank(&self) -> usize {
+        self.ram_bank as usize
+    }
+
+    fn write_register(&mut self, addr: u16, value: u8) {
+        match addr {
+            0x0000..=0x1FFF => self.ram_enabled = value & 0x0F == 0x0A,
+            0x2000..=0x3FFF => self.rom_bank = (value & 0x7F).max(1),
+            0x4000..=0x5FFF => self.ram_bank = value,
+            _ => {
+                // Writing 0x00 then 0x01 copies the running clock into the readable registers
+                if self.latch == 0x00 && value == 0x01 {
+                    self.latched_rtc = self.rtc;
+                }
+                self.latch = value;
+            }
+        };
+    }
+
+    fn read_ram(&self, addr: u16) -> u8 {
+        if !self.ram_enabled {
+            return 0xFF;
+        }
+        match self.ram_bank {
+            0x00..=0x03 if !self.ram.is_empty() => {
+                let offset: usize = self.ram_bank as usize * 0x2000 + (addr - 0xA000) as usize;
+                self.ram[offset % self.ram.len()]
+            }
+            0x08..=0x0C => self.latched_rtc[(self.ram_bank - 0x08) as usize],
+            _ => 0xFF,
+        }
+    }
+
+    fn write_ram(&mut self, addr: u16, value: u8) {
+        if !self.ram_enabled {
+            return;
+        }
+        match self.ram_bank {
+            0x00..=0x03 if !self.ram.is_empty() => {
+                let offset: usize = self.ram_bank as usize * 0x2000 + (addr - 0xA000) as usize;
+                let len: usize = self.ram.len();
+                self.ram[offset % len] = value;
+            }
+            0x08..=0x0C => {
+                // Writing the seconds register also resets the sub-second counter
+                if self.ram_bank == 0x08 {
+                    self.rtc_cycles = 0;
+                }
+                self.rtc[(self.ram_bank - 0x08) as usize] = value;
+            }
+            _ => (),
+        };
+    }
+
+    fn tick(&mut self, cycles: u32) {
+        if self.rtc_halted() {
+            return;
+        }
+        self.rtc_cycles += cycles;
+        while self.rtc_cycles >= Mbc3::CYCLES_PER_SECOND {
+            self.rtc_cycles -= Mbc3::CYCLES_PER_SECOND;
+            self.advance_rtc(1);
+        }
+    }
+
+    fn save_ram(&self, now: u64) -> Vec<u8> {
+        let mut data: Vec<u8> = self.ram.clone();
+        for value in self.rtc.iter().chain(self.latched_rtc.iter()) {
+            data.extend_from_slice(&(*value as u32).to_le_bytes());
+        }
+        data.extend_from_slice(&now.to_le_bytes());
+        data
+    }
+
+    // The clock kept running while the game was closed, so it is moved forward by the time since the save
+    fn load_ram(&mut self, data: &[u8], now: u64) {
+        let len: usize = data.len().min(self.ram.len());
+        self.ram[..len].copy_from_slice(&data[..len]);
+        let footer: &[u8] = &data[len..];
+        // Some emulators write a 32-bit timestamp instead
+        if footer.len() != Mbc3::RTC_FOOTER_LEN && footer.len() != Mbc3::RTC_FOOTER_LEN - 4 {
+            if !footer.is_empty() {
+                log::warn!("Ignoring {} byte RTC footer in save file", footer.len());
+            }
+            return;
+        }
+        for i in 0..10 {
+            let value: u8 = footer[i * 4];
+            if i < 5 {
+                self.rtc[i] = value;
+            } else {
+                self.latched_rtc[i - 5] = value;
+            }
+        }
+        let mut timestamp: [u8; 8] = [0; 8];
+        timestamp[..footer.len() - 40].copy_from_slice(&footer[40..]);
+        let saved_at: u64 = u64::from_le_bytes(timestamp);
+        self.advance_rtc(now.saturating_sub(saved_at));
+    }
+
+    fn ram(&self) -> &[u8] {
+        &self.ram
+    }
+
+    fn ram_mut(&mut self) -> &mut [u8] {
+        &mut self.ram
+    }
+
+    fn tagged(&self) -> TaggedMapper {
+        TaggedMapper::Mbc3(self.clone())
+    }
+}
+
+#[derive(Clone, Serialize, Deserialize)]
+pub struct Mbc5 {
+    ram: Vec<u8>,
+    ram_enabled: bool,
+    rom_bank: u16,
+    ram_bank: u8,
+    rom_banks: usize,
+    has_rumble: bool,
+    rumble: bool,
+}
+
+impl Mbc5 {
+    pub fn new(rom_banks: usize, ram_size: usize) -> Self {
+        Mbc5 {
+            ram: vec![0x00; ram_size],
+            ram_enabled: false,
+            rom_bank: 1,
+            ram_bank: 0,
+            rom_banks,
+            has_rumble: false,
+            rumble: false,
+        }
+    }
+
+    // On rumble cartridges bit 3 of the RAM bank register drives the motor instead of selecting a bank
+    pub fn with_rumble(rom_banks: usize, ram_size: usize) -> Self {
+        let mut mbc5: Mbc5 = Mbc5::new(rom_banks, ram_size);
+        mbc5.has_rumble = true;
+        mbc5
     }
+
+    fn ram_offset(&self, addr: u16) -> usize {
+        (self.ram_bank as usize * 0x2000 + (addr - 0xA000) as usize) % self.ram.len()
+    }
+}
+
+impl Mapper for Mbc5 {
+    fn rom_bank(&self, addr: u16) -> usize {
+        match addr {
+            0x0000..=0x3FFF => 0,
+            _ => self.rom_bank as usize % self.rom_banks,
+        }
+    }
+
+    fn ram_enabled(&self) -> bool {
+        self.ram_enabled
+    }
+
+    fn ram_bank(&self) -> usize {
+        self.ram_bank as usize
+    }
+
+    fn write_register(&mut self, addr: u16, value: u8) {
+        match addr {
+            0x0000..=0x1FFF => self.ram_enabled = value & 0x0F == 0x0A,
+            0x2000..=0x2FFF => self.rom_bank = (self.rom_bank & 0x100) | value as u16,
+            0x3000..=0x3FFF => {
+                self.rom_bank = (self.rom_bank & 0xFF) | ((value as u16 & 0x01) << 8)
+            }
+            0x4000..=0x5FFF => {
+                if self.has_rumble {
+                    self.rumble = value & 0x08 != 0;
+                    self.ram_bank = value & 0x07;
+                } else {
+                    self.ram_bank = value & 0x0F;
+                }
+            }
+            _ => (),
+        };
+    }
+
+    fn rumble(&self) -> bool {
+        self.rumble
+    }
+
+    fn read_ram(&self, addr: u16) -> u8 {
+        if !self.ram_enabled || self.ram.is_empty() {
+            return 0xFF;
+        }
+        self.ram[self.ram_offset(addr)]
+    }
+
+    fn write_ram(&mut self, addr: u16, value: u8) {
+        if self.ram_enabled && !self.ram.is_empty() {
+            let offset: usize = self.ram_offset(addr);
+            self.ram[offset] = value;
+        }
+    }
+
+    fn ram(&self) -> &[u8] {
+        &self.ram
+    }
+
+    fn ram_mut(&mut self) -> &mut [u8] {
+        &mut self.ram
+    }
+
+    fn tagged(&self) -> TaggedMapper {
+        TaggedMapper::Mbc5(self.clone())
+    }
+}
+
+// Base T-cycle count of each opcode. Conditional branches list their not-taken timing.
+const OPCODE_CYCLES: [u8; 256] = [
+    4, 12, 8, 8, 4, 4, 8, 4, 20, 8, 8, 8, 4, 4, 8, 4, // 0x00
+    4, 12, 8, 8, 4, 4, 8, 4, 12, 8, 8, 8, 4, 4, 8, 4, // 0x10
+    8, 12, 8, 8, 4, 4, 8, 4, 8, 8, 8, 8, 4, 4, 8, 4, // 0x20
+    8, 12, 8, 8, 12, 12, 12, 4, 8, 8, 8, 8, 4, 4, 8, 4, // 0x30
+    4, 4, 4, 4, 4, 4, 8, 4, 4, 4, 4, 4, 4, 4, 8, 4, // 0x40
+    4, 4, 4, 4, 4, 4, 8, 4, 4, 4, 4, 4, 4, 4, 8, 4, // 0x50
+    4, 4, 4, 4, 4, 4, 8, 4, 4, 4, 4, 4, 4, 4, 8, 4, // 0x60
+    8, 8, 8, 8, 8, 8, 4, 8, 4, 4, 4, 4, 4, 4, 8, 4, // 0x70
+    4, 4, 4, 4, 4, 4, 8, 4, 4, 4, 4, 4, 4, 4, 8, 4, // 0x80
+    4, 4, 4, 4, 4, 4, 8, 4, 4, 4, 4, 4, 4, 4, 8, 4, // 0x90
+    4, 4, 4, 4, 4, 4, 8, 4, 4, 4, 4, 4, 4, 4, 8, 4, // 0xA0
+    4, 4, 4, 4, 4, 4, 8, 4, 4, 4, 4, 4, 4, 4, 8, 4, // 0xB0
+    8, 12, 12, 16, 12, 16, 8, 16, 8, 16, 12, 4, 12, 24, 8, 16, // 0xC0
+    8, 12, 12, 4, 12, 16, 8, 16, 8, 16, 12, 4, 12, 4, 8, 16, // 0xD0
+    12, 12, 8, 4, 4, 16, 8, 16, 16, 4, 16, 4, 4, 4, 8, 16, // 0xE0
+    12, 12, 8, 4, 4, 16, 8, 16, 12, 8, 16, 4, 4, 4, 8, 16, // 0xF0
+];
+
+// What the CPU does with an opcode that isn't implemented yet
+#[derive(Clone, Copy, PartialEq, Debug, Default)]
+pub enum UnimplementedPolicy {
+    // Stop with the opcode and PC so gaps are loud during development
+    #[default]
+    Panic,
+    // Warn and skip the opcode byte
+    Log,
+    // Skip the opcode byte silently, as if it were a NOP
+    Nop,
+    // Leave PC on the opcode and stop, reported as an Error by try_step and Emulator::try_run_frame
+    Stop,
+}
+
+#[derive(Serialize, Deserialize)]
+pub struct Cpu {
+    a: u8,
+    b: u8,
+    c: u8,
+    d: u8,
+    e: u8,
+    f: u8,
+    h: u8,
+    l: u8,
+    sp: u16,
+    pc: u16,
+    ime: bool,
+    ime_scheduled: bool,
+    halted: bool,
+    // Cycles on top of OPCODE_CYCLES when the current instruction's branch is taken
+    branch_cycles: u32,
+    #[serde(skip)]
+    unimplemented_policy: UnimplementedPolicy,
+    // One bit per opcode already warned about, so the log isn't flooded by a loop
+    #[serde(skip)]
+    unimplemented_seen: [u64; 4],
+    // Opcode the Stop policy halted on, until it is reported
+    #[serde(skip)]
+    stopped_at: Option<u8>,
+    membus: MemBus,
+}
+
+// Plain copy of the CPU registers so frontends and tests don't need to reach into the Cpu
+#[derive(Clone, Copy, PartialEq, Debug, Default)]
+pub struct CpuState {
+    pub a: u8,
+    pub b: u8,
+    pub c: u8,
+    pub d: u8,
+    pub e: u8,
+    pub f: u8,
+    pub h: u8,
+    pub l: u8,
+    pub sp: u16,
+    pub pc: u16,
+    pub zero: bool,
+    pub subtract: bool,
+    pub half_carry: bool,
+    pub carry: bool,
+    pub ime: bool,
+    pub halted: bool,
+}
+
+impl CpuState {
+    // Flags as "Znhc" with set flags in uppercase
+    pub fn flag_string(&self) -> String {
+        let flags: [(bool, char); 4] = [
+            (self.zero, 'z'),
+            (self.subtract, 'n'),
+            (self.half_carry, 'h'),
+            (self.carry, 'c'),
+        ];
+        flags
+            .iter()
+            .map(|(set, name)| {
+                if *set {
+                    name.to_ascii_uppercase()
+                } else {
+                    *name
+                }
+            })
+            .collect()
+    }
+}
+
+#[derive(Clone, Copy)]
+enum Register {
+    A,
+    B,
+    C,
+    D,
+    E,
+    H,
+    L,
+    SP,
+    AF,
+    BC,
+    DE,
+    HL,
+}
+
+#[derive(Clone, Copy, PartialEq, Debug)]
+pub enum Flag {
+    Z,
+    N,
+    H,
+    C,
+}
+
+impl Flag {
+    // The flag's bit in F. The low nibble is always 0.
+    fn mask(self) -> u8 {
+        match self {
+            Flag::Z => 0b1000_0000,
+            Flag::N => 0b0100_0000,
+            Flag::H => 0b0010_0000,
+            Flag::C => 0b0001_0000,
+        }
+    }
+}
+
+impl Cpu {
+    pub fn new(membus: MemBus) -> Self {
+        Cpu::new_with_model(membus, Model::Dmg)
+    }
+
+    // Starts with the registers the given model's boot ROM leaves behind
+    pub fn new_with_model(membus: MemBus, model: Model) -> Self {
+        let mut cpu: Cpu = Cpu {
+            a: 0x00,
+            b: 0x00,
+            c: 0x00,
+            d: 0x00,
+            e: 0x00,
+            f: 0x00,
+            h: 0x00,
+            l: 0x00,
+            sp: 0xFFFE,
+            pc: 0x0100,
+            ime: false,
+            ime_scheduled: false,
+            halted: false,
+            branch_cycles: 0,
+            unimplemented_policy: UnimplementedPolicy::default(),
+            unimplemented_seen: [0; 4],
+            stopped_at: None,
+            membus,
+        };
+        cpu.membus.set_model(model);
+        cpu.set_post_boot_registers(model);
+        cpu
+    }
+
+    pub fn set_unimplemented_policy(&mut self, policy: UnimplementedPolicy) {
+        self.unimplemented_policy = policy;
+    }
+
+    // A-L as the boot ROM leaves them. Games and test ROMs tell the models apart by A, and B on the DMG0.
+    fn set_post_boot_registers(&mut self, model: Model) {
+        let [a, f, b, c, d, e, h, l]: [u8; 8] = match model {
+            Model::Dmg0 => [0x01, 0x00, 0xFF, 0x13, 0x00, 0xC1, 0x84, 0x03],
+            Model::Dmg => [0x01, 0xB0, 0x00, 0x13, 0x00, 0xD8, 0x01, 0x4D],
+            Model::Mgb => [0xFF, 0xB0, 0x00, 0x13, 0x00, 0xD8, 0x01, 0x4D],
+            Model::Cgb => [0x11, 0x80, 0x00, 0x00, 0xFF, 0x56, 0x00, 0x0D],
+        };
+        self.a = a;
+        self.f = f;
+        self.b = b;
+        self.c = c;
+        self.d = d;
+        self.e = e;
+        self.h = h;
+        self.l = l;
+    }
+
+    // For homebrew and code fragments that don't start at the cartridge entry point
+    pub fn with_entry_point(membus: MemBus, pc: u16, sp: u16) -> Self {
+        let mut cpu: Cpu = Cpu::new(membus);
+        cpu.pc = pc;
+        cpu.sp = sp;
+        cpu
+    }
+
+    fn get_af(&self) -> u16 {
+        (self.a as u16) << 8 | self.f as u16
+    }
+
+    fn get_bc(&self) -> u16 {
+        (self.b as u16) << 8 | self.c as u16
+    }
+
+    fn get_de(&self) -> u16 {
+        (self.d as u16) << 8 | self.e as u16
+    }
+
+    fn get_hl(&self) -> u16 {
+        (self.h as u16) << 8 | self.l as u16
+    }
+
+    fn set_af(&mut self, value: u16) {
+        self.a = (value >> 8) as u8;
+        self.f = (value & 0xFF) as u8;
+    }
+
+    fn set_bc(&mut self, value: u16) {
+        self.b = (value >> 8) as u8;
+        self.c = (value & 0xFF) as u8;
+    }
+    fn set_de(&mut self, value: u16) {
+        self.d = (value >> 8) as u8;
+        self.e = (value & 0xFF) as u8;
+    }
+    fn set_hl(&mut self, value: u16) {
+        self.h = (value >> 8) as u8;
+        self.l = (value & 0xFF) as u8;
+    }
+
+    pub fn snapshot(&self) -> CpuState {
+        CpuState {
+            a: self.a,
+            b: self.b,
+            c: self.c,
+            d: self.d,
+            e: self.e,
+            f: self.f,
+            h: self.h,
+            l: self.l,
+            sp: self.sp,
+            pc: self.pc,
+            zero: self.get_flag(Flag::Z),
+            subtract: self.get_flag(Flag::N),
+            half_carry: self.get_flag(Flag::H),
+            carry: self.get_flag(Flag::C),
+            ime: self.ime,
+            halted: self.halted,
+        }
+    }
+
+    // Puts the registers back from a snapshot. F is taken as is, the separate flag fields are ignored.
+    fn restore(&mut self, state: &CpuState) {
+        self.a = state.a;
+        self.b = state.b;
+        self.c = state.c;
+        self.d = state.d;
+        self.e = state.e;
+        self.f = state.f;
+        self.h = state.h;
+        self.l = state.l;
+        self.sp = state.sp;
+        self.pc = state.pc;
+        self.ime = state.ime;
+        self.halted = state.halted;
+    }
+
+    // A CPU decoded from a save state picks up the settings the running one had
+    fn take_session(&mut self, old: &mut Cpu) {
+        self.unimplemented_policy = old.unimplemented_policy;
+        self.membus.take_session(&mut old.membus);
+    }
+
+    fn get_flag(&self, flag: Flag) -> bool {
+        self.f & flag.mask() != 0
+    }
+
+    // Public so tests can set up flags without knowing where they sit in F
+    pub fn flag(&self, flag: Flag) -> bool {
+        self.get_flag(flag)
+    }
+
+    pub fn set_flag(&mut self, flag: Flag, value: bool) {
+        let mask: u8 = flag.mask();
+        if value {
+            self.f |= mask;
+        } else {
+            self.f &= !mask;
+        }
+    }
+
+    // 8 bit operands are encoded in 3 bits as B, C, D, E, H, L, (HL), A
+    fn decode_r8(bits: u8) -> Register {
+        match bits & 0x07 {
+            0 => Register::B,
+            1 => Register::C,
+            2 => Register::D,
+            3 => Register::E,
+            4 => Register::H,
+            5 => Register::L,
+            6 => Register::HL,
+            _ => Register::A,
+        }
+    }
+
+    fn decode_r16(bits: u8) -> Register {
+        match bits & 0x03 {
+            0 => Register::BC,
+            1 => Register::DE,
+            2 => Register::HL,
+            _ => Register::SP,
+        }
+    }
+
+    // PUSH and POP use AF in place of SP
+    fn decode_r16_stack(bits: u8) -> Register {
+        match bits & 0x03 {
+            0 => Register::BC,
+            1 => Register::DE,
+            2 => Register::HL,
+            _ => Register::AF,
+        }
+    }
+
+    fn get_r8(&self, source: Register) -> u8 {
+        match source {
+            Register::A => self.a,
+            Register::B => self.b,
+            Register::C => self.c,
+            Register::D => self.d,
+            Register::E => self.e,
+            Register::H => self.h,
+            Register::L => self.l,
+            Register::HL => self.membus.access(self.get_hl()),
+            _ => {
+                log::error!("Invalid register");
+                0x00
+            }
+        }
+    }
+
+    fn set_r8(&mut self, dest: Register, value: u8) {
+        match dest {
+            Register::A => self.a = value,
+            Register::B => self.b = value,
+            Register::C => self.c = value,
+            Register::D => self.d = value,
+            Register::E => self.e = value,
+            Register::H => self.h = value,
+            Register::L => self.l = value,
+            Register::HL => self.membus.write(self.get_hl(), value),
+            _ => log::error!("Invalid register"),
+        }
+    }
+
+    fn get_r16(&self, source: Register) -> u16 {
+        match source {
+            Register::BC => self.get_bc(),
+            Register::DE => self.get_de(),
+            Register::HL => self.get_hl(),
+            Register::SP => self.sp,
+            _ => {
+                log::error!("Invalid register");
+                0x0000
+            }
+        }
+    }
+
+    fn set_r16(&mut self, dest: Register, value: u16) {
+        match dest {
+            Register::BC => self.set_bc(value),
+            Register::DE => self.set_de(value),
+            Register::HL => self.set_hl(value),
+            Register::SP => self.sp = value,
+            _ => log::error!("Invalid register"),
+        }
+    }
+
+    fn get_16b_value(&self) -> u16 {
+        (self.membus.access(self.pc + 1) as u16) << 8 | (self.membus.access(self.pc) as u16)
+    }
+
+    // The stack grows downward. The high byte is stored at SP-1 and the low byte at SP-2.
+    fn push_u16(&mut self, value: u16) {
+        self.membus.oam_bug(self.sp, OamBug::Write);
+        self.sp = self.sp.wrapping_sub(1);
+        self.membus.write(self.sp, (value >> 8) as u8);
+        self.membus.oam_bug(self.sp, OamBug::Write);
+        self.sp = self.sp.wrapping_sub(1);
+        self.membus.write(self.sp, (value & 0xFF) as u8);
+    }
+
+    fn pop_u16(&mut self) -> u16 {
+        self.membus.oam_bug(self.sp, OamBug::ReadIncrease);
+        let low: u8 = self.membus.access(self.sp);
+        self.sp = self.sp.wrapping_add(1);
+        self.membus.oam_bug(self.sp, OamBug::ReadIncrease);
+        let high: u8 = self.membus.access(self.sp);
+        self.sp = self.sp.wrapping_add(1);
+        (high as u16) << 8 | low as u16
+    }
+
+    fn inc_pc(&mut self) {
+        self.pc += 1;
+    }
+
+    // The opcode byte is skipped so a Log or Nop policy can't spin on the same PC
+    fn not_implemented(&mut self, op: u8) {
+        match self.unimplemented_policy {
+            UnimplementedPolicy::Panic => panic!(
+                "{}",
+                Error::UnimplementedOpcode {
+                    opcode: op,
+                    pc: self.pc
+                }
+            ),
+            UnimplementedPolicy::Log => {
+                let (word, bit): (usize, u64) = (op as usize / 64, 1 << (op % 64));
+                if self.unimplemented_seen[word] & bit == 0 {
+                    self.unimplemented_seen[word] |= bit;
+                    log::warn!(
+                        "Opcode {op:02X} at {:04X} is not implemented, skipping it. Further uses aren't logged.",
+                        self.pc
+                    );
+                }
+            }
+            UnimplementedPolicy::Nop => (),
+            UnimplementedPolicy::Stop => {
+                self.stopped_at = Some(op);
+                return;
+            }
+        };
+        self.inc_pc();
+    }
+
+    // Steps like step, but an unimplemented opcode under the Stop policy comes back as an error. The
+    // opcode isn't run, so stepping again reports it again.
+    pub fn try_step(&mut self) -> Result<u32, Error> {
+        let cycles: u32 = self.step();
+        self.take_stop().map_or(Ok(cycles), Err)
+    }
+
+    fn take_stop(&mut self) -> Option<Error> {
+        self.stopped_at
+            .take()
+            .map(|opcode| Error::UnimplementedOpcode {
+                opcode,
+                pc: self.pc,
+            })
+    }
+
+    // EI only takes effect after the next instruction. DI cancels a pending EI.
+    fn di(&mut self) {
+        self.ime = false;
+        self.ime_scheduled = false;
+        self.inc_pc();
+    }
+
+    fn ei(&mut self) {
+        self.ime_scheduled = true;
+        self.inc_pc();
+    }
+
+    // Sleeps until an interrupt is requested and enabled, even with IME clear. The HALT bug (IME clear with
+    // one already pending reads the next byte twice) isn't emulated.
+    fn halt(&mut self) {
+        self.halted = true;
+        self.inc_pc();
+    }
+
+    fn ret(&mut self) {
+        self.pc = self.pop_u16();
+    }
+
+    // Unlike EI there is no delay, so a pending interrupt is taken before the next instruction
+    fn reti(&mut self) {
+        self.pc = self.pop_u16();
+        self.ime = true;
+    }
+
+    // Jumps to the handler of the highest priority interrupt that is both requested and enabled.
+    // The lowest IF bit wins: VBlank, STAT, timer, serial, then joypad.
+    // Returns the 20 T-cycles the dispatch takes, or None if no interrupt was taken.
+    fn service_interrupt(&mut self) -> Option<u32> {
+        let pending: u8 = self.membus.interrupt_enable & self.membus.interrupt_flag & 0x1F;
+        if pending == 0 {
+            return None;
+        }
+        self.halted = false;
+        if !self.ime {
+            return None;
+        }
+        self.ime = false;
+        self.ime_scheduled = false;
+        // The interrupt is only picked after the high byte of PC is pushed. If that push overwrote IE
+        // (SP was 0x0000) and nothing is left pending, the dispatch is cancelled and jumps to 0x0000.
+        self.membus.oam_bug(self.sp, OamBug::Write);
+        self.sp = self.sp.wrapping_sub(1);
+        self.membus.write(self.sp, (self.pc >> 8) as u8);
+        let pending: u8 = self.membus.interrupt_enable & self.membus.interrupt_flag & 0x1F;
+        self.membus.oam_bug(self.sp, OamBug::Write);
+        self.sp = self.sp.wrapping_sub(1);
+        self.membus.write(self.sp, (self.pc & 0xFF) as u8);
+        if pending == 0 {
+            self.pc = 0x0000;
+        } else {
+            let bit: u8 = pending.trailing_zeros() as u8;
+            self.membus.interrupt_flag &= !(1 << bit);
+            self.pc = 0x0040 + bit as u16 * 8;
+        }
+        Some(20)
+    }
+
+    // Operations need flag logic and timing logic
+    fn nop(&mut self) {
+        self.inc_pc();
+    }
+
+    // STOP is 2 bytes long. On the CGB it performs a prepared speed switch.
+    fn stop(&mut self) {
+        self.membus.speed_switch();
+        self.pc = self.pc.wrapping_add(2);
+    }
+
+    // No LD instruction affects F. Keep flag updates out of the load helpers below.
+    fn load_r8r8(&mut self, source: Register, dest: Register) {
+        let value: &u8 = match source {
+            Register::A => &self.a,
+            Register::B => &self.b,
+            Register::C => &self.c,
+            Register::D => &self.d,
+            Register::E => &self.e,
+            Register::H => &self.h,
+            Register::L => &self.l,
+            _ => {
+                log::error!("Invalid register");
+                &0x00
+            }
+        };
+        match dest {
+            Register::A => self.a = *value,
+            Register::B => self.b = *value,
+            Register::C => self.c = *value,
+            Register::D => self.d = *value,
+            Register::E => self.e = *value,
+            Register::H => self.h = *value,
+            Register::L => self.l = *value,
+            _ => log::error!("Invalid register"),
+        };
+        self.inc_pc();
+    }
+    fn load_r8n8(&mut self, dest: Register) {
+        self.inc_pc();
+        match dest {
+            Register::A => self.a = self.membus.access(self.pc),
+            Register::B => self.b = self.membus.access(self.pc),
+            Register::C => self.c = self.membus.access(self.pc),
+            Register::D => self.d = self.membus.access(self.pc),
+            Register::E => self.e = self.membus.access(self.pc),
+            Register::H => self.h = self.membus.access(self.pc),
+            Register::L => self.l = self.membus.access(self.pc),
+            _ => log::error!("Invalid register"),
+        };
+        self.inc_pc();
+    }
+    fn load_hlr8(&mut self, source: Register) {
+        match source {
+            Register::A => self.membus.write(self.get_hl(), self.a),
+            Register::B => self.membus.write(self.get_hl(), self.b),
+            Register::C => self.membus.write(self.get_hl(), self.c),
+            Register::D => self.membus.write(self.get_hl(), self.d),
+            Register::E => self.membus.write(self.get_hl(), self.e),
+            Register::H => self.membus.write(self.get_hl(), self.h),
+            Register::L => self.membus.write(self.get_hl(), self.l),
+            _ => log::error!("Invalid register"),
+        };
+        self.inc_pc();
+    }
+    fn load_hln8(&mut self) {
+        self.inc_pc();
+        self.membus
+            .write(self.get_hl(), self.membus.access(self.pc));
+        self.inc_pc();
+    }
+    fn load_r8hl(&mut self, dest: Register) {
+        match dest {
+            Register::A => self.a = self.membus.access(self.get_hl()),
+            Register::B => self.b = self.membus.access(self.get_hl()),
+            Register::C => self.c = self.membus.access(self.get_hl()),
+            Register::D => self.d = self.membus.access(self.get_hl()),
+            Register::E => self.e = self.membus.access(self.get_hl()),
+            Register::H => self.h = self.membus.access(self.get_hl()),
+            Register::L => self.l = self.membus.access(self.get_hl()),
+            _ => log::error!("Invalid register"),
+        };
+        self.inc_pc();
+    }
+    fn load_r16n16(&mut self, dest: Register) {
+        self.inc_pc();
+        match dest {
+            Register::BC => self.set_bc(self.get_16b_value()),
+            Register::DE => self.set_de(self.get_16b_value()),
+            Register::HL => self.set_hl(self.get_16b_value()),
+            Register::SP => self.sp = self.get_16b_value(),
+            _ => log::error!("Invalid register"),
+        }
+        self.inc_pc();
+        self.inc_pc();
+    }
+    fn load_r16a(&mut self, addr: Register) {
+        match addr {
+            Register::BC => self.membus.write(self.get_bc(), self.a),
+            Register::DE => self.membus.write(self.get_de(), self.a),
+            Register::HL => self.membus.write(self.get_hl(), self.a),
+            Register::SP => self.membus.write(self.sp, self.a),
+            _ => log::error!("Invalid register"),
+        }
+        self.inc_pc();
+    }
+    fn load_ar16(&mut self, addr: Register) {
+        match addr {
+            Register::BC => self.a = self.membus.access(self.get_bc()),
+            Register::DE => self.a = self.membus.access(self.get_de()),
+            _ => log::error!("Invalid register"),
+        }
+        self.inc_pc();
+    }
+    fn inc_r16(&mut self, dest: Register) {
+        let value: u16 = self.get_r16(dest);
+        self.membus.oam_bug(value, OamBug::Write);
+        self.set_r16(dest, value.wrapping_add(1));
+        self.inc_pc();
+    }
+    fn dec_r16(&mut self, dest: Register) {
+        let value: u16 = self.get_r16(dest);
+        self.membus.oam_bug(value, OamBug::Write);
+        self.set_r16(dest, value.wrapping_sub(1));
+        self.inc_pc();
+    }
+    fn load_n16a(&mut self) {
+        self.inc_pc();
+        self.membus.write(self.get_16b_value(), self.a);
+        self.inc_pc();
+        self.inc_pc();
+    }
+
+    fn push_r16(&mut self, source: Register) {
+        let value: u16 = match source {
+            Register::AF => self.get_af(),
+            Register::BC => self.get_bc(),
+            Register::DE => self.get_de(),
+            Register::HL => self.get_hl(),
+            _ => {
+                log::error!("Invalid register");
+                0x0000
+            }
+        };
+        self.push_u16(value);
+        self.inc_pc();
+    }
+    fn pop_r16(&mut self, dest: Register) {
+        let value: u16 = self.pop_u16();
+        match dest {
+            // The low nibble of F doesn't exist in hardware, so it always pops as zero
+            Register::AF => self.set_af(value & 0xFFF0),
+            Register::BC => self.set_bc(value),
+            Register::DE => self.set_de(value),
+            Register::HL => self.set_hl(value),
+            _ => log::error!("Invalid register"),
+        };
+        self.inc_pc();
+    }
+
+    // ALU operation is selected by bits 3-5 of the opcode
+    fn alu(&mut self, op: u8, value: u8) {
+        match op & 0x07 {
+            0 => self.add_a(value, false),
+            1 => self.add_a(value, true),
+            2 => self.sub_a(value, false),
+            3 => self.sub_a(value, true),
+            4 => self.and_a(value),
+            5 => self.xor_a(value),
+            6 => self.or_a(value),
+            _ => self.cp_a(value),
+        };
+    }
+    fn add_a(&mut self, value: u8, use_carry: bool) {
+        let carry: u8 = (use_carry && self.get_flag(Flag::C)) as u8;
+        let result: u16 = self.a as u16 + value as u16 + carry as u16;
+        self.set_flag(Flag::Z, result as u8 == 0);
+        self.set_flag(Flag::N, false);
+        self.set_flag(Flag::H, (self.a & 0x0F) + (value & 0x0F) + carry > 0x0F);
+        self.set_flag(Flag::C, result > 0xFF);
+        self.a = result as u8;
+    }
+    fn sub_a(&mut self, value: u8, use_carry: bool) {
+        self.a = self.compare_a(value, use_carry);
+    }
+    fn cp_a(&mut self, value: u8) {
+        self.compare_a(value, false);
+    }
+    // Shared by SUB, SBC and CP. Sets the flags and returns the result without storing it
+    fn compare_a(&mut self, value: u8, use_carry: bool) -> u8 {
+        let carry: u8 = (use_carry && self.get_flag(Flag::C)) as u8;
+        let result: u8 = self.a.wrapping_sub(value).wrapping_sub(carry);
+        self.set_flag(Flag::Z, result == 0);
+        self.set_flag(Flag::N, true);
+        self.set_flag(Flag::H, (self.a & 0x0F) < (value & 0x0F) + carry);
+        self.set_flag(Flag::C, (self.a as u16) < value as u16 + carry as u16);
+        result
+    }
+    fn and_a(&mut self, value: u8) {
+        self.a &= value;
+        self.f = 0x00;
+        self.set_flag(Flag::Z, self.a == 0);
+        self.set_flag(Flag::H, true);
+    }
+    fn xor_a(&mut self, value: u8) {
+        self.a ^= value;
+        self.f = 0x00;
+        self.set_flag(Flag::Z, self.a == 0);
+    }
+    fn or_a(&mut self, value: u8) {
+        self.a |= value;
+        self.f = 0x00;
+        self.set_flag(Flag::Z, self.a == 0);
+    }
+
+    // INC and DEC leave C alone. On (HL) they read, modify and write back through the bus.
+    fn inc_r8(&mut self, dest: Register) {
+        let value: u8 = self.get_r8(dest);
+        let result: u8 = value.wrapping_add(1);
+        self.set_flag(Flag::Z, result == 0);
+        self.set_flag(Flag::N, false);
+        self.set_flag(Flag::H, value & 0x0F == 0x0F);
+        self.set_r8(dest, result);
+        self.inc_pc();
+    }
+    fn dec_r8(&mut self, dest: Register) {
+        let value: u8 = self.get_r8(dest);
+        let result: u8 = value.wrapping_sub(1);
+        self.set_flag(Flag::Z, result == 0);
+        self.set_flag(Flag::N, true);
+        self.set_flag(Flag::H, value & 0x0F == 0x00);
+        self.set_r8(dest, result);
+        self.inc_pc();
+    }
+
+    // ADD HL,r16 takes H from bit 11 and C from bit 15. Z is left alone.
+    fn add_hl_r16(&mut self, source: Register) {
+        let hl: u16 = self.get_hl();
+        let value: u16 = self.get_r16(source);
+        self.set_flag(Flag::N, false);
+        self.set_flag(Flag::H, (hl & 0x0FFF) + (value & 0x0FFF) > 0x0FFF);
+        self.set_flag(Flag::C, hl as u32 + value as u32 > 0xFFFF);
+        self.set_hl(hl.wrapping_add(value));
+        self.inc_pc();
+    }
+    // ADD SP,e8 and LD HL,SP+e8 take H and C from the unsigned addition of the low byte of SP,
+    // not from bits 11 and 15 like ADD HL,r16. Z and N are always cleared.
+    fn sp_plus_e8(&mut self) -> u16 {
+        self.inc_pc();
+        let offset: u8 = self.membus.access(self.pc);
+        let low: u8 = self.sp as u8;
+        self.f = 0x00;
+        self.set_flag(Flag::H, (low & 0x0F) + (offset & 0x0F) > 0x0F);
+        self.set_flag(Flag::C, low as u16 + offset as u16 > 0xFF);
+        self.inc_pc();
+        self.sp.wrapping_add(offset as i8 as u16)
+    }
+    fn add_sp_e8(&mut self) {
+        self.sp = self.sp_plus_e8();
+    }
+    fn load_hl_sp_e8(&mut self) {
+        let value: u16 = self.sp_plus_e8();
+        self.set_hl(value);
+    }
+
+    // Conditions are encoded in bits 3-4 as NZ, Z, NC, C
+    fn condition(&self, op: u8) -> bool {
+        match (op >> 3) & 0x03 {
+            0b00 => !self.get_flag(Flag::Z),
+            0b01 => self.get_flag(Flag::Z),
+            0b10 => !self.get_flag(Flag::C),
+            _ => self.get_flag(Flag::C),
+        }
+    }
+
+    fn jr(&mut self) {
+        self.inc_pc();
+        let offset: u8 = self.membus.access(self.pc);
+        self.inc_pc();
+        self.pc = self.pc.wrapping_add(offset as i8 as u16);
+    }
+
+    // Taken relative jumps take one extra M-cycle
+    fn jr_cc(&mut self, op: u8) {
+        if self.condition(op) {
+            self.jr();
+            self.branch_cycles = 4;
+        } else {
+            self.pc = self.pc.wrapping_add(2);
+        }
+    }
+
+    fn jp(&mut self) {
+        self.inc_pc();
+        self.pc = self.get_16b_value();
+    }
+
+    fn jp_cc(&mut self, op: u8) {
+        if self.condition(op) {
+            self.jp();
+            self.branch_cycles = 4;
+        } else {
+            self.pc = self.pc.wrapping_add(3);
+        }
+    }
+
+    fn jp_hl(&mut self) {
+        self.pc = self.get_hl();
+    }
+
+    fn call(&mut self) {
+        self.inc_pc();
+        let target: u16 = self.get_16b_value();
+        self.push_u16(self.pc.wrapping_add(2));
+        self.pc = target;
+    }
+
+    fn call_cc(&mut self, op: u8) {
+        if self.condition(op) {
+            self.call();
+            self.branch_cycles = 12;
+        } else {
+            self.pc = self.pc.wrapping_add(3);
+        }
+    }
+
+    fn ret_cc(&mut self, op: u8) {
+        if self.condition(op) {
+            self.ret();
+            self.branch_cycles = 12;
+        } else {
+            self.inc_pc();
+        }
+    }
+
+    // RST n calls the fixed address in bits 3-5 times 8
+    fn rst(&mut self, op: u8) {
+        self.push_u16(self.pc.wrapping_add(1));
+        self.pc = (op & 0x38) as u16;
+    }
+
+    fn exec(&mut self, op: u8) {
+        match op >> 6 {
+            0b00 => self.exec_block0(op),
+            0b01 => self.exec_load_block(op),
+            0b10 => self.exec_alu_block(op),
+            _ => self.exec_block3(op),
+        };
+    }
+
+    // Opcodes 0x00-0x3F are irregular enough that they are matched individually
+    fn exec_block0(&mut self, op: u8) {
+        match op {
+            0x00 => self.nop(),
+            0x10 => self.stop(),
+            0x18 => self.jr(),
+            0x20 | 0x28 | 0x30 | 0x38 => self.jr_cc(op),
+            0x01 | 0x11 | 0x21 | 0x31 => self.load_r16n16(Cpu::decode_r16(op >> 4)),
+            0x02 | 0x12 => self.load_r16a(Cpu::decode_r16(op >> 4)),
+            0x0A | 0x1A => self.load_ar16(Cpu::decode_r16(op >> 4)),
+            0x03 | 0x13 | 0x23 | 0x33 => self.inc_r16(Cpu::decode_r16(op >> 4)),
+            0x09 | 0x19 | 0x29 | 0x39 => self.add_hl_r16(Cpu::decode_r16(op >> 4)),
+            0x0B | 0x1B | 0x2B | 0x3B => self.dec_r16(Cpu::decode_r16(op >> 4)),
+            0x04 | 0x0C | 0x14 | 0x1C | 0x24 | 0x2C | 0x34 | 0x3C => {
+                self.inc_r8(Cpu::decode_r8(op >> 3))
+            }
+            0x05 | 0x0D | 0x15 | 0x1D | 0x25 | 0x2D | 0x35 | 0x3D => {
+                self.dec_r8(Cpu::decode_r8(op >> 3))
+            }
+            0x06 | 0x0E | 0x16 | 0x1E | 0x26 | 0x2E | 0x36 | 0x3E => {
+                match Cpu::decode_r8(op >> 3) {
+                    Register::HL => self.load_hln8(),
+                    dest => self.load_r8n8(dest),
+                }
+            }
+            _ => self.not_implemented(op),
+        };
+    }
+
+    // Opcodes 0x40-0x7F: LD dest,source where dest is bits 3-5 and source is bits 0-2
+    fn exec_load_block(&mut self, op: u8) {
+        let dest: Register = Cpu::decode_r8(op >> 3);
+        let source: Register = Cpu::decode_r8(op);
+        match (dest, source) {
+            (Register::HL, Register::HL) => self.halt(),
+            (Register::HL, source) => self.load_hlr8(source),
+            (dest, Register::HL) => self.load_r8hl(dest),
+            (dest, source) => self.load_r8r8(source, dest),
+        };
+    }
+
+    // Opcodes 0x80-0xBF: ALU operation is bits 3-5 and the operand is bits 0-2
+    fn exec_alu_block(&mut self, op: u8) {
+        let value: u8 = self.get_r8(Cpu::decode_r8(op));
+        self.alu(op >> 3, value);
+        self.inc_pc();
+    }
+
+    fn exec_block3(&mut self, op: u8) {
+        match op {
+            0xC1 | 0xD1 | 0xE1 | 0xF1 => self.pop_r16(Cpu::decode_r16_stack(op >> 4)),
+            0xC5 | 0xD5 | 0xE5 | 0xF5 => self.push_r16(Cpu::decode_r16_stack(op >> 4)),
+            0xC0 | 0xC8 | 0xD0 | 0xD8 => self.ret_cc(op),
+            0xC2 | 0xCA | 0xD2 | 0xDA => self.jp_cc(op),
+            0xC3 => self.jp(),
+            0xC4 | 0xCC | 0xD4 | 0xDC => self.call_cc(op),
+            0xC7 | 0xCF | 0xD7 | 0xDF | 0xE7 | 0xEF | 0xF7 | 0xFF => self.rst(op),
+            0xC9 => self.ret(),
+            0xCD => self.call(),
+            0xE9 => self.jp_hl(),
+            0xD9 => self.reti(),
+            0xF3 => self.di(),
+            0xFB => self.ei(),
+            0xC6 | 0xCE | 0xD6 | 0xDE | 0xE6 | 0xEE | 0xF6 | 0xFE => {
+                self.inc_pc();
+                let value: u8 = self.membus.access(self.pc);
+                self.alu(op >> 3, value);
+                self.inc_pc();
+            }
+            0xE8 => self.add_sp_e8(),
+            0xEA => self.load_n16a(),
+            0xF8 => self.load_hl_sp_e8(),
+            _ => self.not_implemented(op),
+        };
+    }
+
+    // Executes a single instruction, advances the rest of the hardware by the time it took and returns that time in T-cycles
+    pub fn step(&mut self) -> u32 {
+        if let Some(cycles) = self.service_interrupt() {
+            let cycles: u32 = self.membus.scale_cycles(cycles);
+            self.membus.tick(cycles);
+            return cycles;
+        }
+        if self.halted {
+            let cycles: u32 = self.membus.scale_cycles(4);
+            self.membus.tick(cycles);
+            return cycles;
+        }
+        let op: u8 = self.membus.access(self.pc);
+        log::trace!(
+            "PC={} OP={:02X} A={:02X} F={:02X} BC={:04X} DE={:04X} HL={:04X} SP={:04X}",
+            self.membus.banked_address(self.pc),
+            op,
+            self.a,
+            self.f,
+            self.get_bc(),
+            self.get_de(),
+            self.get_hl(),
+            self.sp
+        );
+        let enable_ime: bool = self.ime_scheduled;
+        self.branch_cycles = 0;
+        self.exec(op);
+        let cycles: u32 = OPCODE_CYCLES[op as usize] as u32 + self.branch_cycles;
+        if enable_ime && self.ime_scheduled {
+            self.ime = true;
+            self.ime_scheduled = false;
+        }
+        let cycles: u32 = self.membus.scale_cycles(cycles);
+        self.membus.tick(cycles);
+        cycles
+    }
+
+    pub fn run(&mut self) {
+        loop {
+            self.step();
+        }
+    }
+
+    // Steps the given number of times and returns the T-cycles that took. Servicing an interrupt counts as a step.
+    pub fn run_for(&mut self, instructions: u32) -> u32 {
+        (0..instructions).map(|_| self.step()).sum()
+    }
+
+    // Steps until the predicate holds, checking before every step, and returns the T-cycles that took
+    pub fn run_until(&mut self, mut predicate: impl FnMut(&Cpu) -> bool) -> u32 {
+        let mut cycles: u32 = 0;
+        while !predicate(self) {
+            cycles += self.step();
+        }
+        cycles
+    }
+}
+
+#[derive(Serialize, Deserialize)]
+pub struct Wram {
+    #[serde(serialize_with = "serialize_sorted")]
+    data: HashMap<u16, u8>,
+    bank: u8,
+}
+impl Default for Wram {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+impl Wram {
+    pub fn new() -> Self {
+        Wram {
+            data: HashMap::new(),
+            bank: 1,
+        }
+    }
+    // 0xD000-0xDFFF is switchable between banks 1-7 on the CGB. Bank n is stored at n * 0x1000.
+    fn key(&self, addr: u16) -> u16 {
+        match addr {
+            0xD000..=0xDFFF => (addr - 0xD000) + self.bank as u16 * 0x1000,
+            _ => addr - 0xC000,
+        }
+    }
+    pub fn set_value(&mut self, addr: u16, entry: u8) {
+        self.data.insert(self.key(addr), entry);
+    }
+    pub fn get_value(&self, addr: u16) -> u8 {
+        match self.data.get(&self.key(addr)) {
+            Some(byte) => *byte,
+            None => 0x00,
+        }
+    }
+    pub fn bank(&self) -> u8 {
+        self.bank
+    }
+    // Selecting bank 0 gives bank 1
+    pub fn set_bank(&mut self, bank: u8) {
+        self.bank = (bank & 0x07).max(1);
+    }
+}
+// An 8x8 tile decoded from VRAM, one color index (0-3) per pixel, indexed [y][x]
+#[derive(Clone, Copy, PartialEq, Debug)]
+pub struct Tile {
+    pub pixels: [[u8; 8]; 8],
+}
+impl Tile {
+    // 2 bytes per row, the low bit of every pixel in the first and the high bit in the second
+    pub fn from_bytes(data: &[u8; 16]) -> Tile {
+        let mut pixels: [[u8; 8]; 8] = [[0; 8]; 8];
+        for (y, row) in pixels.iter_mut().enumerate() {
+            let low: u8 = data[y * 2];
+            let high: u8 = data[y * 2 + 1];
+            for (x, pixel) in row.iter_mut().enumerate() {
+                let bit: usize = 7 - x;
+                *pixel = ((high >> bit) & 1) << 1 | ((low >> bit) & 1);
+            }
+        }
+        Tile { pixels }
+    }
+    pub fn flipped(&self, x_flip: bool, y_flip: bool) -> Tile {
+        let mut pixels: [[u8; 8]; 8] = self.pixels;
+        if y_flip {
+            pixels.reverse();
+        }
+        if x_flip {
+            for row in pixels.iter_mut() {
+                row.reverse();
+            }
+        }
+        Tile { pixels }
+    }
+}
+#[derive(Serialize, Deserialize)]
+pub struct Vram {
+    #[serde(serialize_with = "serialize_sorted")]
+    data: HashMap<u16, u8>,
+    bank: u8,
+    // Tiles written since the PPU last decoded them, numbered bank * 384 + tile
+    #[serde(skip)]
+    dirty: Vec<u16>,
+}
+impl Default for Vram {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+impl Vram {
+    pub fn new() -> Self {
+        Vram {
+            data: HashMap::new(),
+            bank: 0,
+            dirty: Vec::new(),
+        }
+    }
+    // The CGB has a second VRAM bank, stored at 0x2000 onwards
+    fn key(bank: u8, addr: u16) -> u16 {
+        (addr - 0x8000) + bank as u16 * 0x2000
+    }
+    pub fn set_value(&mut self, addr: u16, entry: u8) {
+        self.data.insert(Vram::key(self.bank, addr), entry);
+        // Tile data is 0x8000-0x97FF, the tile maps after it aren't cached
+        if addr < 0x9800 {
+            let tile: u16 = self.bank as u16 * TILES_PER_BANK + (addr - 0x8000) / 16;
+            if self.dirty.last() != Some(&tile) {
+                self.dirty.push(tile);
+            }
+        }
+    }
+    pub fn get_value(&self, addr: u16) -> u8 {
+        self.get_banked_value(self.bank, addr)
+    }
+    pub fn tile_dirty(&self, bank: u8, tile: u16) -> bool {
+        self.dirty.contains(&(bank as u16 * TILES_PER_BANK + tile))
+    }
+    fn take_dirty_tiles(&mut self) -> Vec<u16> {
+        std::mem::take(&mut self.dirty)
+    }
+    // The PPU picks the bank itself regardless of which one the CPU has selected
+    pub fn get_banked_value(&self, bank: u8, addr: u16) -> u8 {
+        match self.data.get(&Vram::key(bank, addr)) {
+            Some(byte) => *byte,
+            None => 0x00,
+        }
+    }
+    pub fn bank(&self) -> u8 {
+        self.bank
+    }
+    pub fn set_bank(&mut self, bank: u8) {
+        self.bank = bank & 0x01;
+    }
+    // Tile numbered from 0x8000 (0-383) in the given bank
+    pub fn tile(&self, bank: u8, tile: u16) -> Tile {
+        let addr: u16 = 0x8000 + tile * 16;
+        let mut data: [u8; 16] = [0; 16];
+        for (i, byte) in data.iter_mut().enumerate() {
+            *byte = self.get_banked_value(bank, addr + i as u16);
+        }
+        Tile::from_bytes(&data)
+    }
+}
+// 16-bit increments/decrements of an OAM address during mode 2 corrupt OAM on the DMG
+#[derive(Clone, Copy, PartialEq)]
+enum OamBug {
+    Write,
+    ReadIncrease,
+}
+
+// One sprite's 4 bytes of OAM with the attribute bits split out. x and y are as stored, 8 and 16 more
+// than the screen position.
+#[derive(Clone, Copy, PartialEq, Debug)]
+pub struct OamEntry {
+    pub y: u8,
+    pub x: u8,
+    pub tile: u8,
+    pub behind_bg: bool,
+    pub y_flip: bool,
+    pub x_flip: bool,
+    pub dmg_palette: u8,
+    // The VRAM bank and palette are only used on the CGB
+    pub bank: u8,
+    pub cgb_palette: u8,
+}
+impl OamEntry {
+    pub fn from_bytes(bytes: [u8; 4]) -> OamEntry {
+        let [y, x, tile, attr] = bytes;
+        OamEntry {
+            y,
+            x,
+            tile,
+            behind_bg: attr & 0x80 != 0,
+            y_flip: attr & 0x40 != 0,
+            x_flip: attr & 0x20 != 0,
+            dmg_palette: (attr >> 4) & 0x01,
+            bank: (attr >> 3) & 0x01,
+            cgb_palette: attr & 0x07,
+        }
+    }
+}
+
+#[derive(Serialize, Deserialize)]
+pub struct Oam {
+    #[serde(serialize_with = "serialize_sorted")]
+    data: HashMap<u16, u8>,
+}
+impl Default for Oam {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+impl Oam {
+    pub fn new() -> Self {
+        Oam {
+            data: HashMap::new(),
+        }
+    }
+    pub fn set_value(&mut self, addr: u16, entry: u8) {
+        self.data.insert(addr, entry);
+    }
+    pub fn get_value(&self, addr: u16) -> u8 {
+        match self.data.get(&addr) {
+            Some(byte) => *byte,
+            None => 0x00,
+        }
+    }
+    // Sprite 0-39
+    pub fn entry(&self, index: usize) -> OamEntry {
+        let addr: u16 = 0xFE00 + index as u16 * 4;
+        OamEntry::from_bytes([
+            self.get_value(addr),
+            self.get_value(addr + 1),
+            self.get_value(addr + 2),
+            self.get_value(addr + 3),
+        ])
+    }
+    pub fn entries(&self) -> Vec<OamEntry> {
+        (0..40).map(|index| self.entry(index)).collect()
+    }
+    fn get_word(&self, row: usize, word: usize) -> u16 {
+        let addr: u16 = 0xFE00 + (row * 8 + word * 2) as u16;
+        u16::from_le_bytes([self.get_value(addr), self.get_value(addr + 1)])
+    }
+    fn set_word(&mut self, row: usize, word: usize, value: u16) {
+        let addr: u16 = 0xFE00 + (row * 8 + word * 2) as u16;
+        let [low, high] = value.to_le_bytes();
+        self.set_value(addr, low);
+        self.set_value(addr + 1, high);
+    }
+    fn copy_row(&mut self, from: usize, to: usize) {
+        for word in 0..4 {
+            self.set_word(to, word, self.get_word(from, word));
+        }
+    }
+    // DMG OAM bug. Row is the 8 byte OAM row the PPU is scanning. Patterns follow Pan Docs.
+    fn corrupt(&mut self, row: usize, bug: OamBug) {
+        if row == 0 {
+            return;
+        }
+        if bug == OamBug::ReadIncrease && (4..19).contains(&row) {
+            let a: u16 = self.get_word(row - 2, 0);
+            let b: u16 = self.get_word(row - 1, 0);
+            let c: u16 = self.get_word(row, 0);
+            let d: u16 = self.get_word(row - 1, 2);
+            self.set_word(row - 1, 0, (b & (a | c | d)) | (a & c & d));
+            self.copy_row(row - 1, row);
+            self.copy_row(row - 1, row - 2);
+        }
+        let a: u16 = self.get_word(row, 0);
+        let b: u16 = self.get_word(row - 1, 0);
+        let c: u16 = self.get_word(row - 1, 2);
+        let first: u16 = match bug {
+            OamBug::Write => ((a ^ c) & (b ^ c)) ^ c,
+            OamBug::ReadIncrease => b | (a & c),
+        };
+        self.set_word(row, 0, first);
+        for word in 1..4 {
+            self.set_word(row, word, self.get_word(row - 1, word));
+        }
+    }
+}
+
+pub const SCREEN_WIDTH: usize = 160;
+pub const SCREEN_HEIGHT: usize = 144;
+
+const VBLANK_INTERRUPT: u8 = 0b0000_0001;
+const STAT_INTERRUPT: u8 = 0b0000_0010;
+const TIMER_INTERRUPT: u8 = 0b0000_0100;
+const SERIAL_INTERRUPT: u8 = 0b0000_1000;
+
+// The four RGBA colors DMG shades 0 (lightest) to 3 (darkest) are displayed as
+#[derive(Clone, Copy, PartialEq, Debug, Serialize, Deserialize)]
+pub struct DisplayPalette {
+    pub colors: [[u8; 4]; 4],
+}
+
+impl DisplayPalette {
+    pub const DMG_GREEN: DisplayPalette = DisplayPalette {
+        colors: [
+            [0x9B, 0xBC, 0x0F, 0xFF],
+            [0x8B, 0xAC, 0x0F, 0xFF],
+            [0x30, 0x62, 0x30, 0xFF],
+            [0x0F, 0x38, 0x0F, 0xFF],
+        ],
+    };
+    pub const POCKET_GRAY: DisplayPalette = DisplayPalette {
+        colors: [
+            [0xFF, 0xFF, 0xFF, 0xFF],
+            [0xA9, 0xA9, 0xA9, 0xFF],
+            [0x54, 0x54, 0x54, 0xFF],
+            [0x00, 0x00, 0x00, 0xFF],
+        ],
+    };
+
+    pub fn custom(colors: [[u8; 4]; 4]) -> Self {
+        DisplayPalette { colors }
+    }
+
+    // Four RGB555 colors as used by the CGB and SGB palettes
+    pub fn from_rgb555(colors: [u16; 4]) -> Self {
+        DisplayPalette {
+            colors: colors.map(rgb555_to_rgba),
+        }
+    }
+
+    pub fn color(&self, shade: u8) -> [u8; 4] {
+        self.colors[(shade & 0x03) as usize]
+    }
+}
+
+// Scales each 5 bit channel up to 8 bits
+fn rgb555_to_rgba(color: u16) -> [u8; 4] {
+    let channel = |shift: u16| -> u8 {
+        let value: u8 = ((color >> shift) & 0x1F) as u8;
+        (value << 3) | (value >> 2)
+    };
+    [channel(0), channel(5), channel(10), 0xFF]
+}
+
+impl Default for DisplayPalette {
+    fn default() -> Self {
+        DisplayPalette::DMG_GREEN
+    }
+}
+
+#[derive(Clone, Copy, PartialEq, Debug)]
+pub enum PalettePreset {
+    DmgGreen,
+    PocketGray,
+    Custom,
+}
+
+impl PalettePreset {
+    pub fn name(&self) -> &str {
+        match self {
+            PalettePreset::DmgGreen => "DMG green",
+            PalettePreset::PocketGray => "Pocket gray",
+            PalettePreset::Custom => "Custom",
+        }
+    }
+}
+
+// Integer keeps every Game Boy pixel the same size. Fit fills as much of the window as the aspect ratio allows.
+#[derive(Clone, Copy, PartialEq, Debug)]
+pub enum ScaleMode {
+    Integer,
+    Fit,
+}
+
+impl ScaleMode {
+    pub fn name(&self) -> &str {
+        match self {
+            ScaleMode::Integer => "Integer scale",
+            ScaleMode::Fit => "Fit window",
+        }
+    }
+}
+
+// Where the screen goes in an area of the given size as (x, y, width, height), centered with the 10:9
+// aspect ratio kept and the rest left for letterboxing. Integer never drops below 1x.
+pub fn screen_placement(width: f32, height: f32, mode: ScaleMode) -> (f32, f32, f32, f32) {
+    let fit: f32 = (width / SCREEN_WIDTH as f32).min(height / SCREEN_HEIGHT as f32);
+    let scale: f32 = match mode {
+        ScaleMode::Integer => fit.floor().max(1.0),
+        ScaleMode::Fit => fit,
+    };
+    let screen_width: f32 = SCREEN_WIDTH as f32 * scale;
+    let screen_height: f32 = SCREEN_HEIGHT as f32 * scale;
+    (
+        ((width - screen_width) / 2.0).floor(),
+        ((height - screen_height) / 2.0).floor(),
+        screen_width,
+        screen_height,
+    )
+}
+
+#[derive(Clone, Copy, PartialEq, Debug, Serialize, Deserialize)]
+pub enum PpuMode {
+    HBlank,
+    VBlank,
+    OamScan,
+    Drawing,
+}
+
+// Plain copy of the PPU timing and scroll registers for debug views, in the spirit of CpuState
+#[derive(Clone, Copy, PartialEq, Debug)]
+pub struct PpuState {
+    pub ly: u8,
+    pub mode: PpuMode,
+    pub dot: u32,
+    pub lcdc: u8,
+    pub stat: u8,
+    pub scx: u8,
+    pub scy: u8,
+    pub wx: u8,
+    pub wy: u8,
+}
+
+const TILES_PER_BANK: u16 = 384;
+
+// Every tile in both VRAM banks decoded to one color index per pixel, so the scanline renderer doesn't
+// pick bits out of the tile data for every pixel. Only the tiles VRAM marked dirty are decoded again.
+struct TileCache {
+    pixels: Box<[u8]>,
+    // Cleared for a new or deserialized PPU, which has to decode everything once
+    valid: bool,
+    rebuilt: usize,
+}
+
+impl TileCache {
+    fn update(&mut self, vram: &mut Vram) {
+        let mut tiles: Vec<u16> = vram.take_dirty_tiles();
+        if !self.valid {
+            tiles = (0..TILES_PER_BANK * 2).collect();
+            self.valid = true;
+        }
+        tiles.sort_unstable();
+        tiles.dedup();
+        for tile in tiles.iter() {
+            self.decode(vram, *tile);
+        }
+        self.rebuilt = tiles.len();
+    }
+
+    fn decode(&mut self, vram: &Vram, tile: u16) {
+        let decoded: Tile = vram.tile((tile / TILES_PER_BANK) as u8, tile % TILES_PER_BANK);
+        let start: usize = tile as usize * 64;
+        self.pixels[start..start + 64].copy_from_slice(decoded.pixels.as_flattened());
+    }
+
+    // Color index of pixel x,y (0-7) of a tile numbered from 0x8000
+    fn pixel(&self, bank: u8, tile: u16, x: u8, y: u8) -> u8 {
+        self.pixels
+            [(bank as u16 * TILES_PER_BANK + tile) as usize * 64 + y as usize * 8 + x as usize]
+    }
+}
+
+impl Default for TileCache {
+    fn default() -> Self {
+        TileCache {
+            pixels: vec![0; TILES_PER_BANK as usize * 2 * 64].into_boxed_slice(),
+            valid: false,
+            rebuilt: 0,
+        }
+    }
+}
+
+// Scanline draws a whole line at the end of mode 3. Fifo runs the background fetcher and pixel FIFOs
+// dot by dot, so mode 3 stretches with SCX, the window and sprite fetches like on hardware.
+#[derive(Clone, Copy, PartialEq, Debug, Serialize, Deserialize)]
+pub enum PpuRenderer {
+    Scanline,
+    Fifo,
+}
+
+#[derive(Clone, Copy, PartialEq, Serialize, Deserialize)]
+enum FetchStep {
+    Tile,
+    DataLow,
+    DataHigh,
+    Push,
+}
+
+// A sprite picked during OAM scan and the row of it on this line
+#[derive(Clone, Copy, Serialize, Deserialize)]
+struct LineObject {
+    index: u8,
+    x: u8,
+    tile: u8,
+    attr: u8,
+    row: u8,
+}
+
+// Background/window pixel FIFO. The fetcher only pushes a tile's 8 pixels once the FIFO is empty.
+// Each pixel is its color index in bits 0-1, its CGB palette in bits 2-4 and the CGB priority bit in bit 5.
+// The sprite FIFO next to it is lined up with the next 8 screen pixels and holds obj_line style pixels.
+#[derive(Serialize, Deserialize)]
+struct PixelFifo {
+    pixels: [u8; 8],
+    count: u8,
+    step: FetchStep,
+    step_dots: u8,
+    fetch_x: u8,
+    tile_index: u8,
+    tile_attr: u8,
+    data_low: u8,
+    data_high: u8,
+    window: bool,
+    x: u8,
+    discard: u8,
+    stall: u8,
+    // Sprites on this line that haven't been reached, left to right
+    objects: Vec<LineObject>,
+    obj_pixels: [u8; 8],
+    // OAM index of each sprite pixel, which decides overlaps on the CGB
+    obj_indices: [u8; 8],
+    // Background tile the last sprite fetch waited on. Later sprites over it don't wait again.
+    obj_tile: Option<u8>,
+}
+
+impl PixelFifo {
+    // The first tile fetch of every line is thrown away, which costs 6 dots
+    const FIRST_FETCH_DOTS: u8 = 6;
+    const OBJ_FETCH_DOTS: u8 = 6;
+
+    fn new() -> Self {
+        PixelFifo {
+            pixels: [0; 8],
+            count: 0,
+            step: FetchStep::Tile,
+            step_dots: 0,
+            fetch_x: 0,
+            tile_index: 0,
+            tile_attr: 0,
+            data_low: 0,
+            data_high: 0,
+            window: false,
+            x: 0,
+            discard: 0,
+            stall: 0,
+            objects: Vec::new(),
+            obj_pixels: [0; 8],
+            obj_indices: [0; 8],
+            obj_tile: None,
+        }
+    }
+
+    fn start_line(&mut self, scx: u8) {
+        *self = PixelFifo::new();
+        self.discard = scx % 8;
+        self.stall = PixelFifo::FIRST_FETCH_DOTS;
+    }
+
+    fn restart_fetcher(&mut self) {
+        self.count = 0;
+        self.step = FetchStep::Tile;
+        self.step_dots = 0;
+        self.fetch_x = 0;
+    }
+
+    fn pop(&mut self) -> Option<u8> {
+        if self.count == 0 {
+            return None;
+        }
+        let pixel: u8 = self.pixels[8 - self.count as usize];
+        self.count -= 1;
+        Some(pixel)
+    }
+
+    // Shifts out the sprite pixel for the screen pixel being drawn, 0 where no sprite shows
+    fn pop_object(&mut self) -> u8 {
+        let pixel: u8 = self.obj_pixels[0];
+        self.obj_pixels.rotate_left(1);
+        self.obj_pixels[7] = 0;
+        self.obj_indices.rotate_left(1);
+        pixel
+    }
+}
+
+#[derive(Serialize, Deserialize)]
+pub struct Ppu {
+    mode: PpuMode,
+    dot: u32,
+    lcdc: u8,
+    stat: u8,
+    scy: u8,
+    scx: u8,
+    ly: u8,
+    lyc: u8,
+    bgp: u8,
+    obp0: u8,
+    obp1: u8,
+    wy: u8,
+    wx: u8,
+    stat_line: bool,
+    window_line: u8,
+    // Boxed so the PPU stays small enough to move around on the stack
+    framebuffer: Box<[u8]>,
+    cgb: bool,
+    #[serde(with = "BigArray")]
+    bg_palettes: [u8; 64],
+    #[serde(with = "BigArray")]
+    obj_palettes: [u8; 64],
+    bcps: u8,
+    ocps: u8,
+    color_framebuffer: Box<[u16]>,
+    renderer: PpuRenderer,
+    fifo: PixelFifo,
+    frame_skip: u32,
+    frame_count: u32,
+    // Sprite pixels of the current line, drawn when mode 3 starts. Each is its color index in bits 0-1
+    // (0 where no sprite shows), its palette in bits 2-4 and the behind background flag in bit 7.
+    #[serde(with = "BigArray")]
+    obj_line: [u8; SCREEN_WIDTH],
+    #[serde(skip)]
+    tiles: TileCache,
+}
+
+impl Default for Ppu {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+impl Ppu {
+    const OAM_SCAN_DOTS: u32 = 80;
+    const DRAWING_DOTS: u32 = 172;
+    const LINE_DOTS: u32 = 456;
+    const VBLANK_START: u8 = 144;
+    const LINES: u8 = 154;
+    const OBJS_PER_LINE: usize = 10;
+
+    pub fn new() -> Self {
+        Ppu {
+            mode: PpuMode::HBlank,
+            dot: 0,
+            lcdc: 0x00,
+            stat: 0x00,
+            scy: 0x00,
+            scx: 0x00,
+            ly: 0x00,
+            lyc: 0x00,
+            bgp: 0x00,
+            obp0: 0x00,
+            obp1: 0x00,
+            wy: 0x00,
+            wx: 0x00,
+            stat_line: false,
+            window_line: 0,
+            framebuffer: vec![0; SCREEN_WIDTH * SCREEN_HEIGHT].into_boxed_slice(),
+            cgb: false,
+            bg_palettes: [0xFF; 64],
+            obj_palettes: [0xFF; 64],
+            bcps: 0x00,
+            ocps: 0x00,
+            color_framebuffer: vec![0x7FFF; SCREEN_WIDTH * SCREEN_HEIGHT].into_boxed_slice(),
+            renderer: PpuRenderer::Scanline,
+            fifo: PixelFifo::new(),
+            frame_skip: 0,
+            frame_count: 0,
+            obj_line: [0; SCREEN_WIDTH],
+            tiles: TileCache::default(),
+        }
+    }
+
+    // Only 1 in every frames + 1 frames is drawn into the framebuffer. Timing and interrupts are unaffected.
+    pub fn set_frame_skip(&mut self, frames: u32) {
+        self.frame_skip = frames;
+        self.frame_count = 0;
+    }
+
+    fn rendering(&self) -> bool {
+        self.frame_count == 0
+    }
+
+    pub fn set_renderer(&mut self, renderer: PpuRenderer) {
+        self.renderer = renderer;
+    }
+
+    pub fn set_cgb(&mut self, cgb: bool) {
+        self.cgb = cgb;
+    }
+
+    // RGB555 color of every pixel of the last rendered frame. Only drawn in CGB mode.
+    pub fn color_framebuffer(&self) -> &[u16] {
+        &self.color_framebuffer
+    }
+
+    // 160x144 RGBA pixels, row by row. DMG shades go through the display palette, CGB colors are
+    // scaled from 5 to 8 bits per channel.
+    pub fn framebuffer_rgba(&self, palette: &DisplayPalette) -> Vec<u8> {
+        let mut rgba: Vec<u8> = Vec::with_capacity(SCREEN_WIDTH * SCREEN_HEIGHT * 4);
+        if self.cgb {
+            for color in self.color_framebuffer.iter() {
+                rgba.extend_from_slice(&rgb555_to_rgba(*color));
+            }
+        } else {
+            for shade in self.framebuffer.iter() {
+                rgba.extend_from_slice(&palette.color(*shade));
+            }
+        }
+        rgba
+    }
+
+    // Shade (0-3) of every pixel of the last rendered frame after BGP has been applied
+    pub fn framebuffer(&self) -> &[u8] {
+        &self.framebuffer
+    }
+
+    pub fn mode(&self) -> PpuMode {
+        self.mode
+    }
+
+    pub fn ly(&self) -> u8 {
+        self.ly
+    }
+
+    // STAT is as the CPU would read it, with the mode and LYC bits filled in
+    pub fn snapshot(&self) -> PpuState {
+        PpuState {
+            ly: self.ly,
+            mode: self.mode,
+            dot: self.dot,
+            lcdc: self.lcdc,
+            stat: self.read_stat(),
+            scx: self.scx,
+            scy: self.scy,
+            wx: self.wx,
+            wy: self.wy,
+        }
+    }
+
+    // How many tiles the last step had to decode again
+    pub fn rebuilt_tiles(&self) -> usize {
+        self.tiles.rebuilt
+    }
+
+    // The CPU can't see VRAM while the PPU is drawing, or OAM while it is scanning or drawing (DMG behaviour)
+    pub fn vram_accessible(&self) -> bool {
+        self.mode != PpuMode::Drawing
+    }
+
+    pub fn oam_accessible(&self) -> bool {
+        self.mode != PpuMode::OamScan && self.mode != PpuMode::Drawing
+    }
+
+    // The OAM row the PPU is reading during OAM scan. Each 8 byte row takes 4 dots.
+    fn oam_row(&self) -> Option<usize> {
+        match self.mode {
+            PpuMode::OamScan if self.lcd_enabled() && !self.cgb => Some((self.dot / 4) as usize),
+            _ => None,
+        }
+    }
+
+    fn lcd_enabled(&self) -> bool {
+        self.lcdc & 0b1000_0000 != 0
+    }
+
+    // Advances the PPU by the given number of dots (T-cycles) and returns any interrupts it requested as IF bits
+    pub fn step(&mut self, cycles: u32, vram: &mut Vram, oam: &Oam) -> u8 {
+        self.tiles.update(vram);
+        let mut interrupts: u8 = 0;
+        if !self.lcd_enabled() {
+            return interrupts;
+        }
+        for _ in 0..cycles {
+            self.dot += 1;
+            match self.mode {
+                PpuMode::OamScan => {
+                    if self.dot == Ppu::OAM_SCAN_DOTS {
+                        self.mode = PpuMode::Drawing;
+                        self.fifo.start_line(self.scx);
+                        match self.renderer {
+                            PpuRenderer::Scanline => self.render_objects(oam),
+                            PpuRenderer::Fifo => self.fifo.objects = self.fifo_objects(oam),
+                        };
+                    }
+                }
+                PpuMode::Drawing => match self.renderer {
+                    PpuRenderer::Scanline => {
+                        if self.dot == Ppu::OAM_SCAN_DOTS + Ppu::DRAWING_DOTS {
+                            self.render_line(vram);
+                            self.mode = PpuMode::HBlank;
+                        }
+                    }
+                    PpuRenderer::Fifo => {
+                        self.fifo_dot(vram);
+                        if self.fifo.x as usize >= SCREEN_WIDTH {
+                            if self.fifo.window {
+                                self.window_line += 1;
+                            }
+                            self.mode = PpuMode::HBlank;
+                        }
+                    }
+                },
+                PpuMode::HBlank => {
+                    if self.dot == Ppu::LINE_DOTS {
+                        self.dot = 0;
+                        self.ly += 1;
+                        if self.ly == Ppu::VBLANK_START {
+                            self.mode = PpuMode::VBlank;
+                            interrupts |= VBLANK_INTERRUPT;
+                        } else {
+                            self.mode = PpuMode::OamScan;
+                        }
+                    }
+                }
+                PpuMode::VBlank => {
+                    if self.dot == Ppu::LINE_DOTS {
+                        self.dot = 0;
+                        self.ly += 1;
+                        if self.ly == Ppu::LINES {
+                            self.ly = 0;
+                            self.window_line = 0;
+                            self.frame_count = if self.frame_count >= self.frame_skip {
+                                0
+                            } else {
+                                self.frame_count + 1
+                            };
+                            self.mode = PpuMode::OamScan;
+                        }
+                    }
+                }
+            };
+            interrupts |= self.update_stat_line();
+        }
+        interrupts
+    }
+
+    fn render_line(&mut self, vram: &Vram) {
+        let bg_enabled: bool = self.bg_enabled();
+        let window_visible: bool =
+            bg_enabled && self.lcdc & 0b0010_0000 != 0 && self.ly >= self.wy && self.wx < 167;
+        let bg_map: u16 = if self.lcdc & 0b0000_1000 != 0 {
+            0x9C00
+        } else {
+            0x9800
+        };
+        let window_map: u16 = if self.lcdc & 0b0100_0000 != 0 {
+            0x9C00
+        } else {
+            0x9800
+        };
+        for x in 0..SCREEN_WIDTH as u8 {
+            if !self.rendering() {
+                break;
+            }
+            let pixel: u8 = if window_visible && x + 7 >= self.wx {
+                self.tile_pixel(vram, window_map, x + 7 - self.wx, self.window_line)
+            } else if bg_enabled {
+                self.tile_pixel(
+                    vram,
+                    bg_map,
+                    x.wrapping_add(self.scx),
+                    self.ly.wrapping_add(self.scy),
+                )
+            } else {
+                0
+            };
+            self.put_pixel(x, pixel, self.obj_line[x as usize]);
+        }
+        if window_visible {
+            self.window_line += 1;
+        }
+    }
+
+    // 8x16 sprites when LCDC bit 2 is set
+    fn obj_height(&self) -> u8 {
+        if self.lcdc & 0b0000_0100 != 0 { 16 } else { 8 }
+    }
+
+    // The first 10 sprites in OAM covering this line. Sprites off the left or right edge still use up a slot.
+    fn select_objects(&self, oam: &Oam) -> Vec<LineObject> {
+        let height: u8 = self.obj_height();
+        let mut selected: Vec<LineObject> = Vec::with_capacity(Ppu::OBJS_PER_LINE);
+        for sprite in 0..40 {
+            let addr: u16 = 0xFE00 + sprite * 4;
+            // OAM holds Y + 16 and X + 8 so sprites can sit partly off the top and left edges
+            let row: u8 = self.ly.wrapping_add(16).wrapping_sub(oam.get_value(addr));
+            if row < height {
+                selected.push(LineObject {
+                    index: sprite as u8,
+                    x: oam.get_value(addr + 1),
+                    tile: oam.get_value(addr + 2),
+                    attr: oam.get_value(addr + 3),
+                    row,
+                });
+                if selected.len() == Ppu::OBJS_PER_LINE {
+                    break;
+                }
+            }
+        }
+        // The DMG draws the sprite with the smaller X on top, OAM order breaking ties. The CGB only uses OAM order.
+        if !self.cgb {
+            selected.sort_by_key(|object| object.x);
+        }
+        selected
+    }
+
+    // The sprites in the order the FIFO renderer reaches them. None are fetched with sprites off.
+    fn fifo_objects(&self, oam: &Oam) -> Vec<LineObject> {
+        if self.lcdc & 0b0000_0010 == 0 {
+            return Vec::new();
+        }
+        let mut objects: Vec<LineObject> = self.select_objects(oam);
+        objects.sort_by_key(|object| object.x);
+        objects
+    }
+
+    // One line of a sprite from left to right, each pixel encoded as in obj_line
+    fn object_pixels(&self, object: &LineObject) -> [u8; 8] {
+        let height: u8 = self.obj_height();
+        let attr: u8 = object.attr;
+        // A tall sprite is an even/odd tile pair read as one 16 row tile, so flipping it also swaps the pair
+        let row: u8 = if attr & 0x40 != 0 {
+            height - 1 - object.row
+        } else {
+            object.row
+        };
+        let tile: u8 = if height == 16 {
+            object.tile & 0xFE
+        } else {
+            object.tile
+        };
+        let bank: u8 = if self.cgb { (attr >> 3) & 0x01 } else { 0 };
+        let palette: u8 = if self.cgb {
+            attr & 0x07
+        } else {
+            (attr >> 4) & 0x01
+        };
+        let mut pixels: [u8; 8] = [0; 8];
+        for (i, pixel) in pixels.iter_mut().enumerate() {
+            let tile_x: u8 = if attr & 0x20 != 0 {
+                7 - i as u8
+            } else {
+                i as u8
+            };
+            let color: u8 = self
+                .tiles
+                .pixel(bank, tile as u16 + row as u16 / 8, tile_x, row % 8);
+            if color != 0 {
+                *pixel = color | palette << 2 | (attr & 0x80);
+            }
+        }
+        pixels
+    }
+
+    // Draws the sprites covering this line into obj_line, highest priority first
+    fn render_objects(&mut self, oam: &Oam) {
+        self.obj_line = [0; SCREEN_WIDTH];
+        if self.lcdc & 0b0000_0010 == 0 || !self.rendering() {
+            return;
+        }
+        for object in self.select_objects(oam) {
+            for (i, pixel) in self.object_pixels(&object).iter().enumerate() {
+                let screen_x: usize = match (object.x as usize + i).checked_sub(8) {
+                    Some(screen_x) if screen_x < SCREEN_WIDTH => screen_x,
+                    _ => continue,
+                };
+                if *pixel != 0 && self.obj_line[screen_x] == 0 {
+                    self.obj_line[screen_x] = *pixel;
+                }
+            }
+        }
+    }
+
+    // A sprite pixel hides background color 0 always, and colors 1-3 unless the sprite or the CGB tile
+    // attributes put the background first. CGB LCDC bit 0 clear puts every sprite on top.
+    fn obj_over_bg(&self, bg: u8, obj: u8) -> bool {
+        bg & 0x03 == 0
+            || (self.cgb && self.lcdc & 0b0000_0001 == 0)
+            || (obj & 0x80 == 0 && bg & 0x20 == 0)
+    }
+
+    // One dot of mode 3 for the FIFO renderer. A pixel is shifted out before the fetcher advances.
+    fn fifo_dot(&mut self, vram: &Vram) {
+        if self.fifo.stall > 0 {
+            self.fifo.stall -= 1;
+            return;
+        }
+        let window_visible: bool = self.bg_enabled()
+            && self.lcdc & 0b0010_0000 != 0
+            && self.ly >= self.wy
+            && self.wx < 167;
+        // Reaching WX throws away the queued background pixels and restarts the fetcher on the window
+        if window_visible && !self.fifo.window && self.fifo.x + 7 >= self.wx {
+            self.fifo.restart_fetcher();
+            self.fifo.window = true;
+            self.fifo.discard = 0;
+        }
+        // Reaching a sprite's left edge holds the pixel output while its line is fetched
+        if let Some(object) = self.fifo.objects.first().copied()
+            && object.x <= self.fifo.x + 8
+        {
+            self.fifo.objects.remove(0);
+            self.fetch_object(object);
+            return;
+        }
+        if let Some(pixel) = self.fifo.pop() {
+            if self.fifo.discard > 0 {
+                self.fifo.discard -= 1;
+            } else {
+                let obj: u8 = self.fifo.pop_object();
+                if self.rendering() {
+                    self.put_pixel(self.fifo.x, pixel, obj);
+                }
+                self.fifo.x += 1;
+            }
+        }
+        self.fetcher_dot(vram);
+    }
+
+    // Merges a sprite into the sprite FIFO and stalls for the fetch: 6 dots, plus up to 5 while the first
+    // sprite over a background tile waits for that tile's fetch, and 11 in all for a sprite at X 0.
+    // This dot is the first of them.
+    fn fetch_object(&mut self, object: LineObject) {
+        // Pixels left of the screen are already gone
+        let skip: usize = (self.fifo.x + 8 - object.x) as usize;
+        for (slot, pixel) in self.object_pixels(&object).iter().skip(skip).enumerate() {
+            let current: u8 = self.fifo.obj_pixels[slot];
+            // The sprite fetched first, the one with the smaller X, stays on top. On the CGB OAM order decides.
+            let replace: bool =
+                current == 0 || (self.cgb && object.index < self.fifo.obj_indices[slot]);
+            if *pixel != 0 && replace {
+                self.fifo.obj_pixels[slot] = *pixel;
+                self.fifo.obj_indices[slot] = object.index;
+            }
+        }
+        let wait: u8 = if object.x == 0 {
+            5
+        } else {
+            let (offset, window): (u8, u8) = if self.fifo.window {
+                (255 - self.wx, 1)
+            } else {
+                (self.scx, 0)
+            };
+            let pixel: u8 = object.x.wrapping_add(offset);
+            let tile: u8 = (window << 5) | (pixel / 8);
+            if self.fifo.obj_tile == Some(tile) {
+                0
+            } else {
+                self.fifo.obj_tile = Some(tile);
+                5 - (pixel % 8).min(5)
+            }
+        };
+        self.fifo.stall = PixelFifo::OBJ_FETCH_DOTS + wait - 1;
+    }
+
+    // Tile, low and high data fetches take 2 dots each. Pushing waits for the FIFO to empty.
+    fn fetcher_dot(&mut self, vram: &Vram) {
+        if self.fifo.step != FetchStep::Push {
+            self.fifo.step_dots += 1;
+            if self.fifo.step_dots < 2 {
+                return;
+            }
+            self.fifo.step_dots = 0;
+        }
+        let (map, x, y): (u16, u8, u8) = if self.fifo.window {
+            let map: u16 = if self.lcdc & 0b0100_0000 != 0 {
+                0x9C00
+            } else {
+                0x9800
+            };
+            (map, self.fifo.fetch_x * 8, self.window_line)
+        } else {
+            let map: u16 = if self.lcdc & 0b0000_1000 != 0 {
+                0x9C00
+            } else {
+                0x9800
+            };
+            let x: u8 = (self.scx / 8).wrapping_add(self.fifo.fetch_x) & 0x1F;
+            (map, x * 8, self.ly.wrapping_add(self.scy))
+        };
+        match self.fifo.step {
+            FetchStep::Tile => {
+                let tile: u16 = map + (y as u16 / 8) * 32 + (x as u16 / 8) % 32;
+                self.fifo.tile_index = vram.get_banked_value(0, tile);
+                self.fifo.tile_attr = if self.cgb {
+                    vram.get_banked_value(1, tile)
+                } else {
+                    0x00
+                };
+                self.fifo.step = FetchStep::DataLow;
+            }
+            FetchStep::DataLow => {
+                let (bank, row) = self.attr_tile_row(self.fifo.tile_index, self.fifo.tile_attr, y);
+                self.fifo.data_low = vram.get_banked_value(bank, row);
+                self.fifo.step = FetchStep::DataHigh;
+            }
+            FetchStep::DataHigh => {
+                let (bank, row) = self.attr_tile_row(self.fifo.tile_index, self.fifo.tile_attr, y);
+                self.fifo.data_high = vram.get_banked_value(bank, row + 1);
+                self.fifo.step = FetchStep::Push;
+                self.fetcher_push();
+            }
+            FetchStep::Push => self.fetcher_push(),
+        };
+    }
+
+    fn fetcher_push(&mut self) {
+        if self.fifo.count != 0 {
+            return;
+        }
+        let bg_enabled: bool = self.bg_enabled();
+        let palette: u8 = (self.fifo.tile_attr & 0x07) << 2 | (self.fifo.tile_attr & 0x80) >> 2;
+        for i in 0..8 {
+            let bit: u8 = if self.fifo.tile_attr & 0x20 != 0 {
+                i as u8
+            } else {
+                7 - i as u8
+            };
+            self.fifo.pixels[i] = if bg_enabled {
+                ((self.fifo.data_high >> bit) & 1) << 1
+                    | ((self.fifo.data_low >> bit) & 1)
+                    | palette
+            } else {
+                0
+            };
+        }
+        self.fifo.count = 8;
+        self.fifo.fetch_x = self.fifo.fetch_x.wrapping_add(1);
+        self.fifo.step = FetchStep::Tile;
+    }
+
+    // Writes a background pixel, or the sprite pixel over it, to the framebuffers. pixel is a FIFO pixel
+    // and obj a sprite pixel as in obj_line.
+    fn put_pixel(&mut self, x: u8, pixel: u8, obj: u8) {
+        let index: usize = self.ly as usize * SCREEN_WIDTH + x as usize;
+        if obj & 0x03 != 0 && self.obj_over_bg(pixel, obj) {
+            let color: u8 = obj & 0x03;
+            if self.cgb {
+                let entry: usize = (((obj >> 2) & 0x07) as usize * 4 + color as usize) * 2;
+                self.color_framebuffer[index] =
+                    u16::from_le_bytes([self.obj_palettes[entry], self.obj_palettes[entry + 1]]);
+                self.framebuffer[index] = color;
+            } else {
+                let palette: u8 = if obj & 0b0000_0100 != 0 {
+                    self.obp1
+                } else {
+                    self.obp0
+                };
+                self.framebuffer[index] = (palette >> (color * 2)) & 0x03;
+            }
+            return;
+        }
+        let color: u8 = pixel & 0x03;
+        if self.cgb {
+            let entry: usize = (((pixel >> 2) & 0x07) as usize * 4 + color as usize) * 2;
+            self.color_framebuffer[index] =
+                u16::from_le_bytes([self.bg_palettes[entry], self.bg_palettes[entry + 1]]);
+            self.framebuffer[index] = color;
+        } else {
+            self.framebuffer[index] = (self.bgp >> (color * 2)) & 0x03;
+        }
+    }
+
+    // In CGB mode LCDC bit 0 only changes priority, the background is always drawn
+    fn bg_enabled(&self) -> bool {
+        self.cgb || self.lcdc & 0b0000_0001 != 0
+    }
+
+    // Address of the two bytes holding row y % 8 of a tile, following the LCDC addressing mode
+    fn tile_row(&self, index: u8, y: u8) -> u16 {
+        0x8000 + self.tile_number(index) * 16 + (y as u16 % 8) * 2
+    }
+
+    // Tiles counted from 0x8000. With LCDC bit 4 clear the index is signed and based at 0x9000.
+    fn tile_number(&self, index: u8) -> u16 {
+        if self.lcdc & 0b0001_0000 != 0 {
+            index as u16
+        } else {
+            (256 + index as i8 as i16) as u16
+        }
+    }
+
+    // A sprite's tiles from top to bottom as it is drawn, flipped and from its CGB bank. Tall sprites
+    // have two.
+    pub fn object_tiles(&self, vram: &Vram, entry: &OamEntry) -> Vec<Tile> {
+        let bank: u8 = if self.cgb { entry.bank } else { 0 };
+        let mut tiles: Vec<Tile> = if self.obj_height() == 16 {
+            let tile: u16 = (entry.tile & 0xFE) as u16;
+            vec![vram.tile(bank, tile), vram.tile(bank, tile + 1)]
+        } else {
+            vec![vram.tile(bank, entry.tile as u16)]
+        };
+        if entry.y_flip {
+            tiles.reverse();
+        }
+        tiles
+            .iter()
+            .map(|tile| tile.flipped(entry.x_flip, entry.y_flip))
+            .collect()
+    }
+
+    // The tile a background/window map entry at addr (0x9800-0x9FFF) shows, with the addressing mode
+    // from LCDC and the bank and flips from its CGB attributes
+    pub fn map_tile(&self, vram: &Vram, addr: u16) -> Tile {
+        let index: u8 = vram.get_banked_value(0, addr);
+        let attr: u8 = if self.cgb {
+            vram.get_banked_value(1, addr)
+        } else {
+            0x00
+        };
+        vram.tile((attr >> 3) & 0x01, self.tile_number(index))
+            .flipped(attr & 0x20 != 0, attr & 0x40 != 0)
+    }
+
+    // VRAM bank and row address of a tile after applying its CGB attributes (bank bit 3, y flip bit 6)
+    fn attr_tile_row(&self, index: u8, attr: u8, y: u8) -> (u8, u16) {
+        let y: u8 = if attr & 0x40 != 0 { 7 - y % 8 } else { y };
+        ((attr >> 3) & 0x01, self.tile_row(index, y))
+    }
+
+    // Color index (0-3) of the pixel at x,y within the 256x256 pixel tilemap starting at map, with the
+    // CGB palette from the tile attributes in bits 2-4 and their priority bit in bit 5
+    fn tile_pixel(&self, vram: &Vram, map: u16, x: u8, y: u8) -> u8 {
+        let tile: u16 = map + (y as u16 / 8) * 32 + x as u16 / 8;
+        let index: u8 = vram.get_banked_value(0, tile);
+        let attr: u8 = if self.cgb {
+            vram.get_banked_value(1, tile)
+        } else {
+            0x00
+        };
+        let tile_x: u8 = if attr & 0x20 != 0 { 7 - x % 8 } else { x % 8 };
+        let tile_y: u8 = if attr & 0x40 != 0 { 7 - y % 8 } else { y % 8 };
+        let color: u8 =
+            self.tiles
+                .pixel((attr >> 3) & 0x01, self.tile_number(index), tile_x, tile_y);
+        color | (attr & 0x07) << 2 | (attr & 0x80) >> 2
+    }
+
+    // The STAT interrupt fires on the rising edge of the OR of all enabled STAT sources
+    fn update_stat_line(&mut self) -> u8 {
+        let line: bool = match self.mode {
+            PpuMode::HBlank => self.stat & 0b0000_1000 != 0,
+            PpuMode::VBlank => self.stat & 0b0001_0000 != 0,
+            PpuMode::OamScan => self.stat & 0b0010_0000 != 0,
+            PpuMode::Drawing => false,
+        } || (self.ly == self.lyc && self.stat & 0b0100_0000 != 0);
+        let rising: bool = line && !self.stat_line;
+        self.stat_line = line;
+        if rising { STAT_INTERRUPT } else { 0 }
+    }
+
+    fn mode_number(&self) -> u8 {
+        match self.mode {
+            PpuMode::HBlank => 0,
+            PpuMode::VBlank => 1,
+            PpuMode::OamScan => 2,
+            PpuMode::Drawing => 3,
+        }
+    }
+
+    fn read_stat(&self) -> u8 {
+        let mode: u8 = self.mode_number();
+        let coincidence: u8 = if self.ly == self.lyc { 0b0000_0100 } else { 0 };
+        0b1000_0000 | (self.stat & 0b0111_1000) | coincidence | mode
+    }
+
+    fn write_lcdc(&mut self, value: u8) {
+        let was_enabled: bool = self.lcd_enabled();
+        self.lcdc = value;
+        if was_enabled && !self.lcd_enabled() {
+            self.ly = 0;
+            self.dot = 0;
+            self.mode = PpuMode::HBlank;
+        } else if !was_enabled && self.lcd_enabled() {
+            self.mode = PpuMode::OamScan;
+        }
+    }
+
+    // BCPS/OCPS bit 7 makes the index move on after every data write
+    fn increment_palette_index(spec: u8) -> u8 {
+        if spec & 0x80 != 0 {
+            0x80 | ((spec + 1) & 0x3F)
+        } else {
+            spec
+        }
+    }
+}
+
+impl IoDevice for Ppu {
+    fn read(&self, addr: u16) -> u8 {
+        match addr {
+            0xFF40 => self.lcdc,
+            0xFF41 => self.read_stat(),
+            0xFF42 => self.scy,
+            0xFF43 => self.scx,
+            0xFF44 => self.ly,
+            0xFF45 => self.lyc,
+            0xFF47 => self.bgp,
+            0xFF48 => self.obp0,
+            0xFF49 => self.obp1,
+            0xFF4A => self.wy,
+            0xFF4B => self.wx,
+            0xFF68 if self.cgb => self.bcps,
+            0xFF69 if self.cgb => self.bg_palettes[(self.bcps & 0x3F) as usize],
+            0xFF6A if self.cgb => self.ocps,
+            0xFF6B if self.cgb => self.obj_palettes[(self.ocps & 0x3F) as usize],
+            _ => 0xFF,
+        }
+    }
+
+    fn write(&mut self, addr: u16, value: u8) {
+        match addr {
+            0xFF40 => self.write_lcdc(value),
+            0xFF41 => self.stat = value & 0b0111_1000,
+            0xFF42 => self.scy = value,
+            0xFF43 => self.scx = value,
+            0xFF44 => (), // LY is read only
+            0xFF45 => self.lyc = value,
+            0xFF47 => self.bgp = value,
+            0xFF48 => self.obp0 = value,
+            0xFF49 => self.obp1 = value,
+            0xFF4A => self.wy = value,
+            0xFF4B => self.wx = value,
+            0xFF68 if self.cgb => self.bcps = value & 0xBF,
+            0xFF69 if self.cgb => {
+                self.bg_palettes[(self.bcps & 0x3F) as usize] = value;
+                self.bcps = Ppu::increment_palette_index(self.bcps);
+            }
+            0xFF6A if self.cgb => self.ocps = value & 0xBF,
+            0xFF6B if self.cgb => {
+                self.obj_palettes[(self.ocps & 0x3F) as usize] = value;
+                self.ocps = Ppu::increment_palette_index(self.ocps);
+            }
+            _ => (),
+        };
+    }
+}
+
+#[derive(Serialize, Deserialize)]
+pub struct Timer {
+    divider: u16,
+    tima: u8,
+    tma: u8,
+    tac: u8,
+    interrupts: u8,
+}
+
+impl Default for Timer {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+impl Timer {
+    pub fn new() -> Self {
+        Timer {
+            divider: 0x0000,
+            tima: 0x00,
+            tma: 0x00,
+            tac: 0x00,
+            interrupts: 0x00,
+        }
+    }
+
+    // TIMA is clocked by the falling edge of one bit of the internal divider ANDed with the enable bit.
+    // Because DIV writes and TAC changes also move this signal, they can cause an extra increment.
+    fn timer_signal(&self) -> bool {
+        let bit: u8 = match self.tac & 0x03 {
+            0 => 9,
+            1 => 3,
+            2 => 5,
+            _ => 7,
+        };
+        self.tac & 0b0000_0100 != 0 && (self.divider >> bit) & 1 != 0
+    }
+
+    fn increment_tima(&mut self) {
+        if self.tima == 0xFF {
+            self.tima = self.tma;
+            self.interrupts |= TIMER_INTERRUPT;
+        } else {
+            self.tima += 1;
+        }
+    }
+
+    // Increments TIMA if the timer signal went from high to low
+    fn check_falling_edge(&mut self, before: bool) {
+        if before && !self.timer_signal() {
+            self.increment_tima();
+        }
+    }
+
+    // Advances the timer by the given number of T-cycles and returns any interrupts it requested as IF bits
+    pub fn step(&mut self, cycles: u32) -> u8 {
+        for _ in 0..cycles {
+            let before: bool = self.timer_signal();
+            self.divider = self.divider.wrapping_add(1);
+            self.check_falling_edge(before);
+        }
+        let interrupts: u8 = self.interrupts;
+        self.interrupts = 0x00;
+        interrupts
+    }
+}
+
+impl IoDevice for Timer {
+    fn read(&self, addr: u16) -> u8 {
+        match addr {
+            0xFF04 => (self.divider >> 8) as u8,
+            0xFF05 => self.tima,
+            0xFF06 => self.tma,
+            0xFF07 => self.tac,
+            _ => 0xFF,
+        }
+    }
+
+    fn write(&mut self, addr: u16, value: u8) {
+        match addr {
+            0xFF04 => {
+                let before: bool = self.timer_signal();
+                self.divider = 0x0000;
+                self.check_falling_edge(before);
+            }
+            0xFF05 => self.tima = value,
+            0xFF06 => self.tma = value,
+            0xFF07 => {
+                let before: bool = self.timer_signal();
+                self.tac = value;
+                self.check_falling_edge(before);
+            }
+            _ => (),
+        };
+    }
+}
+
+// The other Game Boy on the link cable
+pub trait LinkPartner {
+    // Internal clock: sends our byte and waits for the partner's
+    fn exchange(&mut self, byte: u8) -> io::Result<u8>;
+    // External clock: if the partner started a transfer, answers it with reply and returns the partner's byte
+    fn poll(&mut self, reply: u8) -> io::Result<Option<u8>>;
+}
+
+// A link cable over TCP. Each transfer is one byte each way, the side with the internal clock sends
+// first and blocks until the answer arrives, which keeps both ends in step a byte at a time.
+pub struct TcpLink {
+    stream: TcpStream,
+}
+
+impl TcpLink {
+    // Long enough to ride out a hiccup, short enough that a paused partner doesn't hang the game for good
+    const TIMEOUT: Duration = Duration::from_secs(2);
+
+    // Waits for one partner to connect
+    pub fn listen(addr: impl ToSocketAddrs) -> io::Result<Self> {
+        let listener: TcpListener = TcpListener::bind(addr)?;
+        let (stream, _) = listener.accept()?;
+        Self::from_stream(stream)
+    }
+
+    pub fn connect(addr: impl ToSocketAddrs) -> io::Result<Self> {
+        Self::from_stream(TcpStream::connect(addr)?)
+    }
+
+    pub fn from_stream(stream: TcpStream) -> io::Result<Self> {
+        stream.set_nodelay(true)?;
+        stream.set_read_timeout(Some(TcpLink::TIMEOUT))?;
+        Ok(TcpLink { stream })
+    }
+
+    fn read_byte(&mut self) -> io::Result<u8> {
+        let mut byte: [u8; 1] = [0];
+        self.stream.read_exact(&mut byte)?;
+        Ok(byte[0])
+    }
+}
+
+impl LinkPartner for TcpLink {
+    fn exchange(&mut self, byte: u8) -> io::Result<u8> {
+        self.stream.write_all(&[byte])?;
+        self.read_byte()
+    }
+
+    fn poll(&mut self, reply: u8) -> io::Result<Option<u8>> {
+        self.stream.set_nonblocking(true)?;
+        let mut byte: [u8; 1] = [0];
+        let received: io::Result<usize> = self.stream.read(&mut byte);
+        self.stream.set_nonblocking(false)?;
+        match received {
+            Ok(0) => Err(io::Error::from(io::ErrorKind::UnexpectedEof)),
+            Ok(_) => {
+                self.stream.write_all(&[reply])?;
+                Ok(Some(byte[0]))
+            }
+            Err(e) if e.kind() == io::ErrorKind::WouldBlock => Ok(None),
+            Err(e) => Err(e),
+        }
+    }
+}
+
+// Serial port. Bytes shifted out are kept so test ROMs can report results. With no link partner
+// plugged in every byte shifted in is all 1s.
+#[derive(Serialize, Deserialize)]
+pub struct Serial {
+    data: u8,
+    control: u8,
+    transfer_cycles: u32,
+    output: Vec<u8>,
+    #[serde(skip)]
+    link: Option<Box<dyn LinkPartner>>,
+    #[serde(skip)]
+    poll_cycles: u32,
+}
+
+impl Default for Serial {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+impl Serial {
+    // 8 bits at 8192 Hz with the internal clock
+    const TRANSFER_CYCLES: u32 = 4096;
+    // How often to check whether the partner started a transfer, an eighth of the fastest transfer
+    const POLL_CYCLES: u32 = 512;
+
+    pub fn new() -> Self {
+        Serial {
+            data: 0x00,
+            control: 0x00,
+            transfer_cycles: 0,
+            output: Vec::new(),
+            link: None,
+            poll_cycles: 0,
+        }
+    }
+
+    pub fn output(&self) -> &[u8] {
+        &self.output
+    }
+
+    pub fn set_link(&mut self, link: Option<Box<dyn LinkPartner>>) {
+        self.link = link;
+        self.poll_cycles = 0;
+    }
+
+    pub fn link_connected(&self) -> bool {
+        self.link.is_some()
+    }
+
+    pub fn step(&mut self, cycles: u32) -> u8 {
+        if self.transfer_cycles == 0 {
+            return self.poll_link(cycles);
+        }
+        if cycles < self.transfer_cycles {
+            self.transfer_cycles -= cycles;
+            return 0x00;
+        }
+        self.transfer_cycles = 0;
+        self.output.push(self.data);
+        self.data = match &mut self.link {
+            Some(link) => match link.exchange(self.data) {
+                Ok(byte) => byte,
+                Err(e) => {
+                    self.disconnect(e);
+                    0xFF
+                }
+            },
+            None => 0xFF,
+        };
+        self.control &= 0b0111_1111;
+        SERIAL_INTERRUPT
+    }
+
+    // With the external clock the partner drives the transfer. A transfer that isn't armed still
+    // gets an answer so the partner doesn't wait, but shifts nothing.
+    fn poll_link(&mut self, cycles: u32) -> u8 {
+        let Some(link) = &mut self.link else {
+            return 0x00;
+        };
+        self.poll_cycles += cycles;
+        if self.poll_cycles < Serial::POLL_CYCLES {
+            return 0x00;
+        }
+        self.poll_cycles = 0;
+        let armed: bool = self.control == 0b1000_0000;
+        match link.poll(if armed { self.data } else { 0xFF }) {
+            Ok(Some(byte)) if armed => {
+                self.output.push(self.data);
+                self.data = byte;
+                self.control &= 0b0111_1111;
+                SERIAL_INTERRUPT
+            }
+            Ok(_) => 0x00,
+            Err(e) => {
+                self.disconnect(e);
+                0x00
+            }
+        }
+    }
+
+    // Falls back to an unplugged cable so the game sees its partner leave rather than hanging
+    fn disconnect(&mut self, e: io::Error) {
+        log::warn!("Link cable disconnected: {e}");
+        self.link = None;
+    }
+}
+
+impl IoDevice for Serial {
+    fn read(&self, addr: u16) -> u8 {
+        match addr {
+            0xFF01 => self.data,
+            _ => self.control,
+        }
+    }
+
+    // Only transfers using the internal clock ever complete without a partner
+    fn write(&mut self, addr: u16, value: u8) {
+        match addr {
+            0xFF01 => self.data = value,
+            _ => {
+                self.control = value & 0b1000_0001;
+                if self.control == 0b1000_0001 {
+                    self.transfer_cycles = Serial::TRANSFER_CYCLES;
+                }
+            }
+        };
+    }
+}
+
+// Sound registers 0xFF10-0xFF3F. Tracks power and which channels are running, no audio is produced yet.
+#[derive(Serialize, Deserialize)]
+pub struct Apu {
+    #[serde(with = "BigArray")]
+    registers: [u8; 0x30],
+    powered: bool,
+    channels: [bool; 4],
+    lengths: [u16; 4],
+    sequencer_cycles: u32,
+    sequencer_step: u8,
+}
+
+impl Default for Apu {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+impl Apu {
+    // Frame sequencer runs at 512 Hz
+    const SEQUENCER_CYCLES: u32 = 8192;
+
+    pub fn new() -> Self {
+        Apu {
+            registers: [0x00; 0x30],
+            powered: true,
+            channels: [false; 4],
+            lengths: [0; 4],
+            sequencer_cycles: 0,
+            sequencer_step: 0,
+        }
+    }
+
+    fn get_register(&self, addr: u16) -> u8 {
+        self.registers[(addr - 0xFF10) as usize]
+    }
+
+    // Channel 3 has an 8-bit length counter, the others are 6-bit
+    fn max_length(channel: usize) -> u16 {
+        if channel == 2 { 256 } else { 64 }
+    }
+
+    // A channel's DAC is off when its volume/envelope register (NR30 bit 7 for channel 3) is all zero
+    fn dac_enabled(&self, channel: usize) -> bool {
+        match channel {
+            0 => self.get_register(0xFF12) & 0xF8 != 0,
+            1 => self.get_register(0xFF17) & 0xF8 != 0,
+            2 => self.get_register(0xFF1A) & 0x80 != 0,
+            _ => self.get_register(0xFF21) & 0xF8 != 0,
+        }
+    }
+
+    fn length_enabled(&self, channel: usize) -> bool {
+        let nrx4: u16 = 0xFF14 + channel as u16 * 5;
+        self.get_register(nrx4) & 0b0100_0000 != 0
+    }
+
+    pub fn step(&mut self, cycles: u32) {
+        if !self.powered {
+            return;
+        }
+        self.sequencer_cycles += cycles;
+        while self.sequencer_cycles >= Apu::SEQUENCER_CYCLES {
+            self.sequencer_cycles -= Apu::SEQUENCER_CYCLES;
+            // Length counters are clocked on every other step
+            if self.sequencer_step & 0x01 == 0 {
+                for channel in 0..4 {
+                    if self.length_enabled(channel) && self.lengths[channel] > 0 {
+                        self.lengths[channel] -= 1;
+                        if self.lengths[channel] == 0 {
+                            self.channels[channel] = false;
+                        }
+                    }
+                }
+            }
+            self.sequencer_step = (self.sequencer_step + 1) % 8;
+        }
+    }
+
+    fn load_length(&mut self, channel: usize, value: u8) {
+        self.lengths[channel] = Apu::max_length(channel) - value as u16;
+    }
+
+    fn trigger(&mut self, channel: usize) {
+        if self.lengths[channel] == 0 {
+            self.lengths[channel] = Apu::max_length(channel);
+        }
+        self.channels[channel] = self.dac_enabled(channel);
+    }
+}
+
+impl IoDevice for Apu {
+    fn read(&self, addr: u16) -> u8 {
+        match addr {
+            0xFF26 => {
+                let mut status: u8 = (self.powered as u8) << 7;
+                for (channel, active) in self.channels.iter().enumerate() {
+                    if *active {
+                        status |= 1 << channel;
+                    }
+                }
+                status
+            }
+            _ => self.get_register(addr),
+        }
+    }
+
+    fn write(&mut self, addr: u16, value: u8) {
+        match addr {
+            0xFF26 => {
+                let powered: bool = value & 0b1000_0000 != 0;
+                if self.powered && !powered {
+                    // Powering off clears every sound register except wave RAM
+                    for register in self.registers[0x00..0x16].iter_mut() {
+                        *register = 0x00;
+                    }
+                    self.channels = [false; 4];
+                } else if !self.powered && powered {
+                    self.sequencer_step = 0;
+                }
+                self.powered = powered;
+            }
+            0xFF30..=0xFF3F => self.registers[(addr - 0xFF10) as usize] = value,
+            // The DMG still lets the length counters be loaded while the APU is off
+            0xFF11 | 0xFF16 | 0xFF20 if !self.powered => {
+                self.load_length(((addr - 0xFF11) / 5) as usize, value & 0x3F)
+            }
+            0xFF1B if !self.powered => self.load_length(2, value),
+            _ if !self.powered => (),
+            _ => {
+                self.registers[(addr - 0xFF10) as usize] = value;
+                match addr {
+                    0xFF11 | 0xFF16 | 0xFF20 => {
+                        self.load_length(((addr - 0xFF11) / 5) as usize, value & 0x3F)
+                    }
+                    0xFF1B => self.load_length(2, value),
+                    0xFF12 | 0xFF17 | 0xFF1A | 0xFF21 => {
+                        let channel: usize = match addr {
+                            0xFF12 => 0,
+                            0xFF17 => 1,
+                            0xFF1A => 2,
+                            _ => 3,
+                        };
+                        if !self.dac_enabled(channel) {
+                            self.channels[channel] = false;
+                        }
+                    }
+                    0xFF14 | 0xFF19 | 0xFF1E | 0xFF23 if value & 0b1000_0000 != 0 => {
+                        self.trigger(((addr - 0xFF14) / 5) as usize)
+                    }
+                    _ => (),
+                };
+            }
+        };
+    }
+}
+
+// What the audio callback hears when the emulator hasn't produced enough samples
+#[derive(Clone, Copy, PartialEq, Debug, Default)]
+pub enum UnderrunPolicy {
+    #[default]
+    Silence,
+    // Holding the last sample avoids the click a sudden drop to 0 makes
+    RepeatLast,
+}
+
+// Samples waiting between the emulator and the host's audio callback. Bigger queues survive a slow
+// frame without underrunning but add latency. A full queue drops its oldest sample, so a producer
+// running fast can't build up delay.
+pub struct AudioQueue {
+    samples: std::collections::VecDeque<f32>,
+    capacity: usize,
+    underrun_policy: UnderrunPolicy,
+    last: f32,
+    underruns: u64,
+    overruns: u64,
+}
+
+impl AudioQueue {
+    // The rate the queue is sized for, what most hosts run their output at
+    pub const SAMPLE_RATE: u32 = 48000;
+    // About 43 ms at 48 kHz
+    pub const DEFAULT_CAPACITY: usize = 2048;
+    pub const MIN_CAPACITY: usize = 256;
+    pub const MAX_CAPACITY: usize = 32768;
+
+    // capacity is clamped to MIN_CAPACITY..=MAX_CAPACITY
+    pub fn new(capacity: usize) -> Self {
+        let capacity: usize = capacity.clamp(AudioQueue::MIN_CAPACITY, AudioQueue::MAX_CAPACITY);
+        AudioQueue {
+            samples: std::collections::VecDeque::with_capacity(capacity),
+            capacity,
+            underrun_policy: UnderrunPolicy::default(),
+            last: 0.0,
+            underruns: 0,
+            overruns: 0,
+        }
+    }
+
+    // Sizes the queue for a target latency in milliseconds
+    pub fn with_latency(milliseconds: u32, sample_rate: u32) -> Self {
+        AudioQueue::new((milliseconds as u64 * sample_rate as u64 / 1000) as usize)
+    }
+
+    pub fn set_underrun_policy(&mut self, policy: UnderrunPolicy) {
+        self.underrun_policy = policy;
+    }
+
+    pub fn push(&mut self, sample: f32) {
+        if self.samples.len() == self.capacity {
+            self.samples.pop_front();
+            self.overruns += 1;
+        }
+        self.samples.push_back(sample);
+    }
+
+    pub fn pop(&mut self) -> f32 {
+        match self.samples.pop_front() {
+            Some(sample) => {
+                self.last = sample;
+                sample
+            }
+            None => {
+                self.underruns += 1;
+                match self.underrun_policy {
+                    UnderrunPolicy::Silence => 0.0,
+                    UnderrunPolicy::RepeatLast => self.last,
+                }
+            }
+        }
+    }
+
+    // Fills the callback's buffer, padding by the underrun policy once the queue runs dry
+    pub fn fill(&mut self, out: &mut [f32]) {
+        for sample in out.iter_mut() {
+            *sample = self.pop();
+        }
+    }
+
+    pub fn len(&self) -> usize {
+        self.samples.len()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.samples.is_empty()
+    }
+
+    pub fn capacity(&self) -> usize {
+        self.capacity
+    }
+
+    // Samples made up because the queue was empty
+    pub fn underruns(&self) -> u64 {
+        self.underruns
+    }
+
+    // Samples dropped because the queue was full
+    pub fn overruns(&self) -> u64 {
+        self.overruns
+    }
+}
+
+impl Default for AudioQueue {
+    fn default() -> Self {
+        AudioQueue::new(AudioQueue::DEFAULT_CAPACITY)
+    }
+}
+
+#[derive(Clone, Copy, PartialEq, Debug)]
+pub enum Button {
+    Right,
+    Left,
+    Up,
+    Down,
+    A,
+    B,
+    Select,
+    Start,
+}
+
+const JOYPAD_INTERRUPT: u8 = 0b0001_0000;
+
+// Controller buttons by position, so the right face button is A like on the Game Boy
+#[cfg(feature = "gamepad")]
+pub fn gamepad_button(button: gilrs::Button) -> Option<Button> {
+    match button {
+        gilrs::Button::DPadRight => Some(Button::Right),
+        gilrs::Button::DPadLeft => Some(Button::Left),
+        gilrs::Button::DPadUp => Some(Button::Up),
+        gilrs::Button::DPadDown => Some(Button::Down),
+        gilrs::Button::East => Some(Button::A),
+        gilrs::Button::South => Some(Button::B),
+        gilrs::Button::Select => Some(Button::Select),
+        gilrs::Button::Start => Some(Button::Start),
+        _ => None,
+    }
+}
+
+// OAM DMA copies 160 bytes from source * 0x100 into OAM, one byte every 4 T-cycles
+#[derive(Serialize, Deserialize)]
+struct Dma {
+    source: u8,
+    active: bool,
+    index: u8,
+    cycles: u32,
+    byte: u8,
+}
+
+impl Dma {
+    const LENGTH: u8 = 0xA0;
+
+    fn new() -> Self {
+        Dma {
+            source: 0xFF,
+            active: false,
+            index: 0,
+            cycles: 0,
+            byte: 0xFF,
+        }
+    }
+
+    fn start(&mut self, source: u8) {
+        self.source = source;
+        self.active = true;
+        self.index = 0;
+        self.cycles = 0;
+    }
+
+    // Sources from 0xE000 up read the WRAM that echo RAM mirrors
+    fn source_addr(&self) -> u16 {
+        let addr: u16 = (self.source as u16) << 8 | self.index as u16;
+        if addr >= 0xE000 { addr - 0x2000 } else { addr }
+    }
+}
+
+// P1 register. Bits 4-5 select the d-pad and/or button row, pressed inputs read back as 0
+#[derive(Serialize, Deserialize)]
+struct Joypad {
+    select: u8,
+    pressed: [bool; 8],
+    interrupts: u8,
+}
+
+impl Joypad {
+    fn new() -> Self {
+        Joypad {
+            select: 0b0011_0000,
+            pressed: [false; 8],
+            interrupts: 0x00,
+        }
+    }
+
+    fn set_button(&mut self, button: Button, pressed: bool) {
+        let index: usize = button as usize;
+        if pressed && !self.pressed[index] {
+            self.interrupts |= JOYPAD_INTERRUPT;
+        }
+        self.pressed[index] = pressed;
+    }
+
+    // One bit per button, in Button order
+    fn buttons(&self) -> u8 {
+        self.pressed
+            .iter()
+            .enumerate()
+            .fold(0x00, |buttons, (bit, pressed)| {
+                buttons | (*pressed as u8) << bit
+            })
+    }
+
+    fn set_buttons(&mut self, buttons: u8) {
+        for index in 0..8 {
+            let pressed: bool = buttons & (1 << index) != 0;
+            if pressed && !self.pressed[index] {
+                self.interrupts |= JOYPAD_INTERRUPT;
+            }
+            self.pressed[index] = pressed;
+        }
+    }
+
+    fn take_interrupts(&mut self) -> u8 {
+        let interrupts: u8 = self.interrupts;
+        self.interrupts = 0x00;
+        interrupts
+    }
+}
+
+impl IoDevice for Joypad {
+    fn read(&self, _addr: u16) -> u8 {
+        let mut lines: u8 = 0x0F;
+        if self.select & 0b0001_0000 == 0 {
+            for (bit, pressed) in self.pressed[0..4].iter().enumerate() {
+                if *pressed {
+                    lines &= !(1 << bit);
+                }
+            }
+        }
+        if self.select & 0b0010_0000 == 0 {
+            for (bit, pressed) in self.pressed[4..8].iter().enumerate() {
+                if *pressed {
+                    lines &= !(1 << bit);
+                }
+            }
+        }
+        self.select | lines
+    }
+
+    fn write(&mut self, _addr: u16, entry: u8) {
+        self.select = entry & 0b0011_0000;
+    }
+}
+
+impl Default for Joypad {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+// Super Game Boy command packets, sent by pulsing P14/P15. Writing both low resets the transfer,
+// P14 low sends a 0 bit, P15 low sends a 1 bit and both high separates the bits. Each packet is
+// 16 bytes LSB first followed by a 0 stop bit. Only the palette commands are acted on.
+#[derive(Serialize, Deserialize)]
+struct Sgb {
+    enabled: bool,
+    receiving: bool,
+    bit_ready: bool,
+    bit: u8,
+    packet: [u8; 16],
+    command: Vec<u8>,
+    palettes: [[u16; 4]; 4],
+    palettes_set: bool,
+}
+
+impl Sgb {
+    const PACKET_BITS: u8 = 128;
+
+    fn new() -> Self {
+        Sgb {
+            enabled: false,
+            receiving: false,
+            bit_ready: false,
+            bit: 0,
+            packet: [0; 16],
+            command: Vec::new(),
+            palettes: [[0x7FFF; 4]; 4],
+            palettes_set: false,
+        }
+    }
+
+    fn write_register(&mut self, entry: u8) {
+        if !self.enabled {
+            return;
+        }
+        match entry & 0b0011_0000 {
+            0b0000_0000 => {
+                self.receiving = true;
+                self.bit_ready = false;
+                self.bit = 0;
+                self.packet = [0; 16];
+            }
+            0b0011_0000 => self.bit_ready = self.receiving,
+            lines => {
+                if !self.bit_ready {
+                    return;
+                }
+                self.bit_ready = false;
+                if self.bit == Sgb::PACKET_BITS {
+                    self.receiving = false;
+                    self.finish_packet();
+                } else {
+                    if lines == 0b0010_0000 {
+                        self.packet[(self.bit / 8) as usize] &= !(1 << (self.bit % 8));
+                    } else {
+                        self.packet[(self.bit / 8) as usize] |= 1 << (self.bit % 8);
+                    }
+                    self.bit += 1;
+                }
+            }
+        };
+    }
+
+    // The low 3 bits of the first byte give the number of packets in the command
+    fn finish_packet(&mut self) {
+        self.command.extend_from_slice(&self.packet);
+        let packets: usize = (self.command[0] & 0x07).max(1) as usize;
+        if self.command.len() >= packets * 16 {
+            let command: Vec<u8> = std::mem::take(&mut self.command);
+            self.run_command(&command);
+        }
+    }
+
+    fn run_command(&mut self, command: &[u8]) {
+        let (first, second): (usize, usize) = match command[0] >> 3 {
+            0x00 => (0, 1), // PAL01
+            0x01 => (2, 3), // PAL23
+            0x02 => (0, 3), // PAL03
+            0x03 => (1, 2), // PAL12
+            _ => return,
+        };
+        let color = |index: usize| -> u16 {
+            u16::from_le_bytes([command[1 + index * 2], command[2 + index * 2]]) & 0x7FFF
+        };
+        // Color 0 is shared by every palette
+        for palette in self.palettes.iter_mut() {
+            palette[0] = color(0);
+        }
+        for i in 1..4 {
+            self.palettes[first][i] = color(i);
+            self.palettes[second][i] = color(i + 3);
+        }
+        self.palettes_set = true;
+    }
+
+    // Without attribute commands the whole screen uses palette 0
+    fn display_palette(&self) -> Option<DisplayPalette> {
+        if self.enabled && self.palettes_set {
+            Some(DisplayPalette::from_rgb555(self.palettes[0]))
+        } else {
+            None
+        }
+    }
+}
+
+#[derive(Debug)]
+pub enum CheatError {
+    InvalidFormat(String),
+    InvalidAddress(u16),
+}
+
+impl fmt::Display for CheatError {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match self {
+            CheatError::InvalidFormat(code) => write!(f, "Malformed cheat code {code}"),
+            CheatError::InvalidAddress(addr) => {
+                write!(f, "Cheat code targets invalid address {addr:#06X}")
+            }
+        }
+    }
+}
+
+impl std::error::Error for CheatError {}
+
+#[derive(Clone, Debug)]
+enum CheatKind {
+    // RAM write applied once per frame
+    GameShark {
+        addr: u16,
+        value: u8,
+    },
+    // ROM patch applied on reads. Only replaces the byte if it currently matches compare.
+    GameGenie {
+        addr: u16,
+        value: u8,
+        compare: Option<u8>,
+    },
+}
+
+#[derive(Clone, Debug)]
+struct Cheat {
+    code: String,
+    kind: CheatKind,
+}
+
+impl Cheat {
+    // GameShark: 01DDAAAA with the address little-endian. Game Genie: ABC-DEF or ABC-DEF-GHI.
+    fn parse(code: &str) -> Result<Self, CheatError> {
+        let normalized: String = code.trim().to_ascii_uppercase();
+        let digits: String = normalized.replace('-', "");
+        if digits.is_empty() || !digits.chars().all(|c| c.is_ascii_hexdigit()) {
+            return Err(CheatError::InvalidFormat(code.to_string()));
+        }
+        let nibbles: Vec<u8> = digits
+            .chars()
+            .map(|c| c.to_digit(16).unwrap() as u8)
+            .collect();
+        let kind: CheatKind = match (nibbles.len(), normalized.contains('-')) {
+            (8, false) => {
+                let value: u8 = (nibbles[2] << 4) | nibbles[3];
+                let addr: u16 = ((nibbles[6] as u16) << 12)
+                    | ((nibbles[7] as u16) << 8)
+                    | ((nibbles[4] as u16) << 4)
+                    | nibbles[5] as u16;
+                if addr < 0x8000 {
+                    return Err(CheatError::InvalidAddress(addr));
+                }
+                CheatKind::GameShark { addr, value }
+            }
+            (6, _) | (9, _) => {
+                let value: u8 = (nibbles[0] << 4) | nibbles[1];
+                let addr: u16 = (((nibbles[5] ^ 0xF) as u16) << 12)
+                    | ((nibbles[2] as u16) << 8)
+                    | ((nibbles[3] as u16) << 4)
+                    | nibbles[4] as u16;
+                if addr >= 0x8000 {
+                    return Err(CheatError::InvalidAddress(addr));
+                }
+                let compare: Option<u8> = match nibbles.len() {
+                    9 => Some(((nibbles[6] << 4) | nibbles[8]).rotate_right(2) ^ 0xBA),
+                    _ => None,
+                };
+                CheatKind::GameGenie {
+                    addr,
+                    value,
+                    compare,
+                }
+            }
+            _ => return Err(CheatError::InvalidFormat(code.to_string())),
+        };
+        Ok(Cheat {
+            code: normalized,
+            kind,
+        })
+    }
+}
+
+#[derive(Clone, Copy, PartialEq, Debug)]
+pub enum WatchKind {
+    Read,
+    Write,
+    // Either a read or a write
+    Access,
+}
+
+#[derive(Clone, Copy, PartialEq, Debug)]
+struct Watchpoint {
+    start: u16,
+    end: u16,
+    kind: WatchKind,
+}
+
+// The access that stopped execution. kind is Read or Write, old and new are equal for reads.
+#[derive(Clone, Copy, PartialEq, Debug)]
+pub struct WatchHit {
+    pub pc: u16,
+    pub addr: u16,
+    pub kind: WatchKind,
+    pub old: u8,
+    pub new: u8,
+}
+
+// How save files are named: after the cartridge title or after the ROM file
+#[derive(Clone, Copy, PartialEq, Debug, Default)]
+pub enum SaveNaming {
+    #[default]
+    Title,
+    RomFile,
+}
+
+// Where battery saves and other per-game files go. Without a directory they sit next to the ROM.
+#[derive(Clone, PartialEq, Debug, Default)]
+pub struct SaveConfig {
+    pub directory: Option<PathBuf>,
+    pub naming: SaveNaming,
+}
+
+impl SaveConfig {
+    // The file for this game with the given extension, e.g. "sav". A title that sanitizes to nothing falls
+    // back to the ROM's file name.
+    pub fn path(&self, rom_path: &Path, title: &str, extension: &str) -> PathBuf {
+        let rom_name: String = rom_path
+            .file_stem()
+            .map(|stem| stem.to_string_lossy().to_string())
+            .unwrap_or_default();
+        let name: String = match self.naming {
+            SaveNaming::Title => sanitize_file_name(title),
+            SaveNaming::RomFile => String::new(),
+        };
+        let name: String = if name.is_empty() {
+            sanitize_file_name(&rom_name)
+        } else {
+            name
+        };
+        let directory: &Path = match &self.directory {
+            Some(directory) => directory,
+            None => rom_path.parent().unwrap_or(Path::new("")),
+        };
+        directory.join(name).with_extension(extension)
+    }
+}
+
+// Keeps letters, digits, spaces, dashes and underscores. Anything else becomes an underscore, so titles
+// can't reach outside the save directory or trip up a filesystem.
+pub fn sanitize_file_name(name: &str) -> String {
+    name.chars()
+        .map(|c| {
+            if c.is_ascii_alphanumeric() || c == ' ' || c == '-' || c == '_' {
+                c
+            } else {
+                '_'
+            }
+        })
+        .collect::<String>()
+        .trim()
+        .to_string()
+}
+
+// Labels from an RGBDS style .sym file: one "BB:AAAA Label" per line, with ; starting a comment
+#[derive(Default)]
+pub struct SymbolTable {
+    labels: HashMap<(u16, u16), String>,
+}
+
+impl SymbolTable {
+    pub fn parse(text: &str) -> Self {
+        let mut labels: HashMap<(u16, u16), String> = HashMap::new();
+        for line in text.lines() {
+            let line: &str = line.split(';').next().unwrap_or("").trim();
+            if line.is_empty() {
+                continue;
+            }
+            let parsed: Option<(u16, u16, &str)> =
+                line.split_once(char::is_whitespace)
+                    .and_then(|(location, name)| {
+                        let (bank, addr) = location.split_once(':')?;
+                        let bank: u16 = u16::from_str_radix(bank, 16).ok()?;
+                        let addr: u16 = u16::from_str_radix(addr, 16).ok()?;
+                        Some((bank, addr, name.trim()))
+                    });
+            match parsed {
+                // The first label at an address wins, which is the global one before any locals
+                Some((bank, addr, name)) => {
+                    labels
+                        .entry((bank, addr))
+                        .or_insert_with(|| name.to_string());
+                }
+                None => log::warn!("Skipping malformed symbol line: {line}"),
+            };
+        }
+        SymbolTable { labels }
+    }
+
+    pub fn load(path: &String) -> Result<Self, std::io::Error> {
+        Ok(SymbolTable::parse(&fs::read_to_string(path)?))
+    }
+
+    pub fn lookup(&self, bank: u16, addr: u16) -> Option<&str> {
+        self.labels.get(&(bank, addr)).map(|name| name.as_str())
+    }
+
+    pub fn len(&self) -> usize {
+        self.labels.len()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.labels.is_empty()
+    }
+}
+
+// The parts of the address space the access counters tell apart
+#[derive(Clone, Copy, PartialEq, Debug)]
+pub enum MemoryRegion {
+    Rom,
+    Vram,
+    CartRam,
+    Wram,
+    Oam,
+    Io,
+    Hram,
+}
+
+impl MemoryRegion {
+    pub const ALL: [MemoryRegion; 7] = [
+        MemoryRegion::Rom,
+        MemoryRegion::Vram,
+        MemoryRegion::CartRam,
+        MemoryRegion::Wram,
+        MemoryRegion::Oam,
+        MemoryRegion::Io,
+        MemoryRegion::Hram,
+    ];
+
+    // Echo RAM counts as WRAM, the unusable block as OAM and IE as IO.
+    // Writes to the ROM region are mapper register writes, so they show how often banks are switched.
+    pub fn of(addr: u16) -> Self {
+        match addr {
+            0x0000..=0x7FFF => MemoryRegion::Rom,
+            0x8000..=0x9FFF => MemoryRegion::Vram,
+            0xA000..=0xBFFF => MemoryRegion::CartRam,
+            0xC000..=0xFDFF => MemoryRegion::Wram,
+            0xFE00..=0xFEFF => MemoryRegion::Oam,
+            0xFF80..=0xFFFE => MemoryRegion::Hram,
+            _ => MemoryRegion::Io,
+        }
+    }
+
+    pub fn name(&self) -> &str {
+        match self {
+            MemoryRegion::Rom => "ROM",
+            MemoryRegion::Vram => "VRAM",
+            MemoryRegion::CartRam => "Cartridge RAM",
+            MemoryRegion::Wram => "WRAM",
+            MemoryRegion::Oam => "OAM",
+            MemoryRegion::Io => "IO",
+            MemoryRegion::Hram => "HRAM",
+        }
+    }
+}
+
+// CPU reads and writes per region. Accesses made by tools through read/write_byte aren't counted.
+#[derive(Clone, Copy, PartialEq, Debug, Default)]
+pub struct AccessCounts {
+    reads: [u32; 7],
+    writes: [u32; 7],
+}
+
+impl AccessCounts {
+    pub fn reads(&self, region: MemoryRegion) -> u32 {
+        self.reads[region as usize]
+    }
+
+    pub fn writes(&self, region: MemoryRegion) -> u32 {
+        self.writes[region as usize]
+    }
+}
+
+// How RAM that the game hasn't written yet is filled at power on
+#[derive(Clone, Copy, PartialEq, Debug, Default)]
+pub enum MemoryInit {
+    #[default]
+    Zeros,
+    // Real hardware powers on with noise. The same seed always gives the same contents.
+    Seeded(u64),
+}
+
+// Which hardware the game is run on. The CGB adds color palettes, VRAM/WRAM banking and double speed.
+// DMG0 (the earliest DMG boot ROM) and MGB (Game Boy Pocket) only differ from the DMG in their
+// post-boot registers.
+#[derive(Clone, Copy, PartialEq, Debug, Default, Serialize, Deserialize)]
+pub enum Model {
+    Dmg0,
+    #[default]
+    Dmg,
+    Mgb,
+    Cgb,
+}
+
+impl Model {
+    // CGB-only and CGB-enhanced games both run in color
+    pub fn for_rom(rom: &Rom) -> Self {
+        if rom.supports_cgb() {
+            Model::Cgb
+        } else {
+            Model::Dmg
+        }
+    }
+}
+
+// xorshift64*, only used for reproducible power-on noise
+struct Rng {
+    state: u64,
+}
+
+impl Rng {
+    fn new(seed: u64) -> Self {
+        // xorshift gets stuck at 0
+        let state: u64 = if seed == 0 {
+            0x9E37_79B9_7F4A_7C15
+        } else {
+            seed
+        };
+        Rng { state }
+    }
+
+    fn next_u8(&mut self) -> u8 {
+        self.state ^= self.state >> 12;
+        self.state ^= self.state << 25;
+        self.state ^= self.state >> 27;
+        (self.state.wrapping_mul(0x2545_F491_4F6C_DD1D) >> 56) as u8
+    }
+}
+
+#[derive(Serialize, Deserialize)]
+pub struct MemBus {
+    // Save states leave the cartridge out. load_state moves the loaded one back in.
+    #[serde(skip, default = "Rom::placeholder")]
+    rom: Rom,
+    #[serde(
+        serialize_with = "serialize_mapper",
+        deserialize_with = "deserialize_mapper"
+    )]
+    mapper: Box<dyn Mapper>,
+    wram: Wram,
+    vram: Vram,
+    oam: Oam,
+    #[serde(with = "BigArray")]
+    hram: [u8; 0x7F],
+    ppu: Ppu,
+    timer: Timer,
+    serial: Serial,
+    apu: Apu,
+    joypad: Joypad,
+    sgb: Sgb,
+    dma: Dma,
+    interrupt_flag: u8,
+    interrupt_enable: u8,
+    // Debugger state from here to cheats belongs to the session, not the machine
+    #[serde(skip)]
+    watchpoints: Vec<Watchpoint>,
+    #[serde(skip)]
+    watch_hit: std::cell::Cell<Option<WatchHit>>,
+    // Address and previous value of every write while an undoable instruction runs
+    #[serde(skip)]
+    write_log: Option<Vec<(u16, u8)>>,
+    #[serde(skip)]
+    io_devices: Vec<(std::ops::RangeInclusive<u16>, Box<dyn IoDevice>)>,
+    // None unless profiling, so the counters cost nothing when off
+    #[serde(skip)]
+    access_counts: Option<std::cell::Cell<AccessCounts>>,
+    // Reads only borrow the bus, so the read hook sits in a RefCell
+    #[serde(skip)]
+    on_write: Option<MemoryHook>,
+    #[serde(skip)]
+    on_read: Option<std::cell::RefCell<MemoryHook>>,
+    // Debug CPU clock in percent of nominal, None at 100. The remainder carries the time lost to rounding.
+    #[serde(skip)]
+    cpu_speed: Option<u32>,
+    #[serde(skip)]
+    clock_remainder: u32,
+    #[serde(skip)]
+    cheats: Vec<Cheat>,
+    boot_rom: Option<Vec<u8>>,
+    model: Model,
+    key1_prepare: bool,
+    double_speed: bool,
+}
+
+impl MemBus {
+    // Bits of each IO register that always read back as 1 because they are unused or write only. Registers
+    // nothing answers read 0xFF anyway, so they are left at 0 for added devices.
+    const IO_READ_MASKS: [u8; 0x80] = [
+        0xC0, 0x00, 0x7E, 0x00, 0x00, 0x00, 0x00, 0xF8, 0x00, 0x00, 0x00, 0x00, 0x00, 0x00, 0x00,
+        0xE0, 0x80, 0x3F, 0x00, 0xFF, 0xBF, 0xFF, 0x3F, 0x00, 0xFF, 0xBF, 0x7F, 0xFF, 0x9F, 0xFF,
+        0xBF, 0xFF, 0xFF, 0x00, 0x00, 0xBF, 0x00, 0x00, 0x70, 0xFF, 0xFF, 0xFF, 0xFF, 0xFF, 0xFF,
+        0xFF, 0xFF, 0xFF, 0x00, 0x00, 0x00, 0x00, 0x00, 0x00, 0x00, 0x00, 0x00, 0x00, 0x00, 0x00,
+        0x00, 0x00, 0x00, 0x00, 0x00, 0x80, 0x00, 0x00, 0x00, 0x00, 0x00, 0x00, 0x00, 0x00, 0x00,
+        0x00, 0x00, 0x7E, 0x00, 0xFE, 0xFE, 0x00, 0x00, 0x00, 0x00, 0x00, 0x00, 0x00, 0x00, 0x00,
+        0x00, 0x00, 0x00, 0x00, 0x00, 0x00, 0x00, 0x00, 0x00, 0x00, 0x00, 0x00, 0x00, 0x00, 0x40,
+        0x00, 0x40, 0x00, 0x00, 0x00, 0x00, 0x00, 0xF8, 0x00, 0x00, 0x00, 0x00, 0x00, 0x00, 0x00,
+        0x00, 0x00, 0x00, 0x00, 0x00, 0x00, 0x00, 0x00,
+    ];
+    // Bits of each IO register the CPU can change, the rest of a write is dropped before any device sees it.
+    // LY is read only and any write to DIV resets it.
+    const IO_WRITE_MASKS: [u8; 0x80] = [
+        0x30, 0xFF, 0x81, 0xFF, 0xFF, 0xFF, 0xFF, 0x07, 0xFF, 0xFF, 0xFF, 0xFF, 0xFF, 0xFF, 0xFF,
+        0x1F, 0x7F, 0xFF, 0xFF, 0xFF, 0xC7, 0xFF, 0xFF, 0xFF, 0xFF, 0xC7, 0x80, 0xFF, 0x60, 0xFF,
+        0xC7, 0xFF, 0x3F, 0xFF, 0xFF, 0xC0, 0xFF, 0xFF, 0x80, 0xFF, 0xFF, 0xFF, 0xFF, 0xFF, 0xFF,
+        0xFF, 0xFF, 0xFF, 0xFF, 0xFF, 0xFF, 0xFF, 0xFF, 0xFF, 0xFF, 0xFF, 0xFF, 0xFF, 0xFF, 0xFF,
+        0xFF, 0xFF, 0xFF, 0xFF, 0xFF, 0x78, 0xFF, 0xFF, 0x00, 0xFF, 0xFF, 0xFF, 0xFF, 0xFF, 0xFF,
+        0xFF, 0xFF, 0x01, 0xFF, 0x01, 0xFF, 0xFF, 0xFF, 0xFF, 0xFF, 0xFF, 0xFF, 0xFF, 0xFF, 0xFF,
+        0xFF, 0xFF, 0xFF, 0xFF, 0xFF, 0xFF, 0xFF, 0xFF, 0xFF, 0xFF, 0xFF, 0xFF, 0xFF, 0xFF, 0xBF,
+        0xFF, 0xBF, 0xFF, 0xFF, 0xFF, 0xFF, 0xFF, 0x07, 0xFF, 0xFF, 0xFF, 0xFF, 0xFF, 0xFF, 0xFF,
+        0xFF, 0xFF, 0xFF, 0xFF, 0xFF, 0xFF, 0xFF, 0xFF,
+    ];
+
+    pub fn new(rom: Rom) -> Result<Self, RomError> {
+        let mapper: Box<dyn Mapper> = rom.create_mapper()?;
+        Ok(MemBus {
+            rom,
+            mapper,
+            wram: Wram::new(),
+            vram: Vram::new(),
+            oam: Oam::new(),
+            hram: [0x00; 0x7F],
+            ppu: Ppu::new(),
+            timer: Timer::new(),
+            serial: Serial::new(),
+            apu: Apu::new(),
+            joypad: Joypad::new(),
+            sgb: Sgb::new(),
+            dma: Dma::new(),
+            interrupt_flag: 0x00,
+            interrupt_enable: 0x00,
+            write_log: None,
+            watchpoints: Vec::new(),
+            watch_hit: std::cell::Cell::new(None),
+            io_devices: Vec::new(),
+            access_counts: None,
+            on_write: None,
+            on_read: None,
+            cpu_speed: None,
+            clock_remainder: 0,
+            cheats: Vec::new(),
+            boot_rom: None,
+            model: Model::Dmg,
+            key1_prepare: false,
+            double_speed: false,
+        })
+    }
+
+    pub fn with_memory_init(rom: Rom, init: MemoryInit) -> Result<Self, RomError> {
+        let mut membus: MemBus = MemBus::new(rom)?;
+        if let MemoryInit::Seeded(seed) = init {
+            let mut rng: Rng = Rng::new(seed);
+            for addr in 0xC000..=0xCFFF {
+                membus.wram.set_value(addr, rng.next_u8());
+            }
+            for bank in 1..=7 {
+                membus.wram.set_bank(bank);
+                for addr in 0xD000..=0xDFFF {
+                    membus.wram.set_value(addr, rng.next_u8());
+                }
+            }
+            membus.wram.set_bank(1);
+            for bank in 0..=1 {
+                membus.vram.set_bank(bank);
+                for addr in 0x8000..=0x9FFF {
+                    membus.vram.set_value(addr, rng.next_u8());
+                }
+            }
+            membus.vram.set_bank(0);
+            for byte in membus.hram.iter_mut() {
+                *byte = rng.next_u8();
+            }
+        }
+        Ok(membus)
+    }
+
+    // Leaves the IO registers and VRAM the way the DMG boot ROM does on its way out, for when it is skipped
+    pub fn post_boot(&mut self) {
+        // Sound registers go in address order so only channel 1, whose DAC is on, ends up triggered
+        let registers: [(u16, u8); 30] = [
+            (0xFF00, 0xCF),
+            (0xFF02, 0x7E),
+            (0xFF07, 0xF8),
+            (0xFF0F, 0xE1),
+            (0xFF10, 0x80),
+            (0xFF11, 0xBF),
+            (0xFF12, 0xF3),
+            (0xFF13, 0xFF),
+            (0xFF14, 0xBF),
+            (0xFF16, 0x3F),
+            (0xFF17, 0x00),
+            (0xFF18, 0xFF),
+            (0xFF19, 0xBF),
+            (0xFF1A, 0x7F),
+            (0xFF1B, 0xFF),
+            (0xFF1C, 0x9F),
+            (0xFF1D, 0xFF),
+            (0xFF1E, 0xBF),
+            (0xFF20, 0xFF),
+            (0xFF21, 0x00),
+            (0xFF22, 0x00),
+            (0xFF23, 0xBF),
+            (0xFF24, 0x77),
+            (0xFF25, 0xF3),
+            (0xFF26, 0xF1),
+            (0xFF40, 0x91),
+            (0xFF41, 0x85),
+            (0xFF47, 0xFC),
+            (0xFF48, 0xFF),
+            (0xFF49, 0xFF),
+        ];
+        for (addr, value) in registers {
+            self.write_io(addr, value);
+        }
+        self.timer.divider = 0xABCC;
+        // The logo is copied from the cartridge header with every bit and every row doubled,
+        // so each nibble becomes two rows of a tile
+        for i in 0..0x30u16 {
+            let byte: u8 = self.rom.get_value(0x0104 + i);
+            for (half, nibble) in [byte >> 4, byte & 0x0F].iter().enumerate() {
+                let mut row: u8 = 0;
+                for bit in 0..4 {
+                    if nibble & (1 << bit) != 0 {
+                        row |= 0b11 << (bit * 2);
+                    }
+                }
+                let addr: u16 = 0x8010 + i * 8 + half as u16 * 4;
+                self.vram.set_value(addr, row);
+                self.vram.set_value(addr + 2, row);
+            }
+        }
+        let trademark: [u8; 8] = [0x3C, 0x42, 0xB9, 0xA5, 0xB9, 0xA5, 0x42, 0x3C];
+        for (i, row) in trademark.iter().enumerate() {
+            self.vram.set_value(0x8190 + i as u16 * 2, *row);
+        }
+        // Tiles 1-12 and 13-24 are the two halves of the logo, 0x19 is the trademark after the top half
+        for i in 0..12u8 {
+            self.vram.set_value(0x9904 + i as u16, i + 1);
+            self.vram.set_value(0x9924 + i as u16, i + 13);
+        }
+        self.vram.set_value(0x9910, 0x19);
+    }
+
+    fn cgb(&self) -> bool {
+        self.model == Model::Cgb
+    }
+
+    fn set_model(&mut self, model: Model) {
+        self.model = model;
+        self.ppu.set_cgb(model == Model::Cgb);
+        // SGB enhancements are only seen when a DMG game runs in a Super Game Boy
+        self.sgb.enabled = model == Model::Dmg && self.rom.supports_sgb();
+    }
+
+    // Called by STOP. Toggles double speed if it was requested through KEY1.
+    fn speed_switch(&mut self) {
+        if self.cgb() && self.key1_prepare {
+            self.double_speed = !self.double_speed;
+            self.key1_prepare = false;
+        }
+    }
+
+    // Converts CPU cycles to the time the rest of the machine sees. A faster CPU fits more instructions in a
+    // frame, a slower one fewer.
+    fn scale_cycles(&mut self, cycles: u32) -> u32 {
+        let Some(percent) = self.cpu_speed else {
+            return cycles;
+        };
+        let scaled: u32 = cycles * 100 + self.clock_remainder;
+        self.clock_remainder = scaled % percent;
+        scaled / percent
+    }
+
+    // Advances every component on the bus by the given number of T-cycles. In double speed the CPU,
+    // timer and serial run twice as fast while the PPU, APU and cartridge keep their normal rate.
+    pub fn tick(&mut self, cycles: u32) {
+        let normal_cycles: u32 = if self.double_speed {
+            cycles / 2
+        } else {
+            cycles
+        };
+        self.interrupt_flag |= self.ppu.step(normal_cycles, &mut self.vram, &self.oam);
+        self.interrupt_flag |= self.timer.step(cycles);
+        self.interrupt_flag |= self.serial.step(cycles);
+        self.apu.step(normal_cycles);
+        self.mapper.tick(normal_cycles);
+        self.tick_dma(cycles);
+        self.interrupt_flag |= self.joypad.take_interrupts();
+    }
+
+    fn tick_dma(&mut self, cycles: u32) {
+        if !self.dma.active {
+            return;
+        }
+        self.dma.cycles += cycles;
+        while self.dma.cycles >= 4 && self.dma.active {
+            self.dma.cycles -= 4;
+            self.dma.byte = self.peek(self.dma.source_addr());
+            self.oam
+                .set_value(0xFE00 + self.dma.index as u16, self.dma.byte);
+            self.dma.index += 1;
+            if self.dma.index == Dma::LENGTH {
+                self.dma.active = false;
+            }
+        }
+    }
+
+    // While OAM DMA runs the CPU only has its own bus: IO registers, HRAM and IE
+    fn dma_blocks(&self, addr: u16) -> bool {
+        self.dma.active && addr < 0xFF00
+    }
+
+    fn read_rom(&self, addr: u16) -> u8 {
+        // The boot ROM sits over the cartridge header area until it is unmapped through 0xFF50
+        if addr < 0x0100
+            && let Some(boot_rom) = &self.boot_rom
+        {
+            return boot_rom.get(addr as usize).copied().unwrap_or(0xFF);
+        }
+        let value: u8 = self.mapper.read_rom(&self.rom, addr);
+        for cheat in self.cheats.iter() {
+            if let CheatKind::GameGenie {
+                addr: patch_addr,
+                value: patch_value,
+                compare,
+            } = cheat.kind
+                && patch_addr == addr
+                && compare.is_none_or(|compare| compare == value)
+            {
+                return patch_value;
+            }
+        }
+        value
+    }
+
+    // The CGB fixed the OAM bug
+    fn oam_bug(&mut self, addr: u16, bug: OamBug) {
+        if self.cgb() {
+            return;
+        }
+        if (0xFE00..=0xFEFF).contains(&addr)
+            && let Some(row) = self.ppu.oam_row()
+        {
+            self.oam.corrupt(row, bug);
+        }
+    }
+
+    fn add_cheat(&mut self, code: &str) -> Result<(), CheatError> {
+        let cheat: Cheat = Cheat::parse(code)?;
+        self.cheats.push(cheat);
+        Ok(())
+    }
+
+    fn remove_cheat(&mut self, code: &str) -> bool {
+        let normalized: String = code.trim().to_ascii_uppercase();
+        let count: usize = self.cheats.len();
+        self.cheats.retain(|cheat| cheat.code != normalized);
+        self.cheats.len() != count
+    }
+
+    fn apply_cheats(&mut self) {
+        for i in 0..self.cheats.len() {
+            if let CheatKind::GameShark { addr, value } = self.cheats[i].kind {
+                self.store(addr, value);
+            }
+        }
+    }
+
+    // Routes each IO register to the component that owns it. Registers nothing implements read as
+    // open bus (0xFF) and ignore writes.
+    fn read_io(&self, addr: u16) -> u8 {
+        let value: u8 = match addr {
+            0xFF0F => self.interrupt_flag,
+            0xFF46 => self.dma.source,
+            0xFF4D if self.cgb() => (self.double_speed as u8) << 7 | self.key1_prepare as u8,
+            0xFF4F if self.cgb() => self.vram.bank(),
+            0xFF50 => self.boot_rom.is_none() as u8,
+            0xFF70 if self.cgb() => self.wram.bank(),
+            _ => match self.io_device(addr) {
+                Some(device) => device.read(addr),
+                None => 0xFF,
+            },
+        };
+        value | MemBus::IO_READ_MASKS[(addr - 0xFF00) as usize]
+    }
+
+    fn write_io(&mut self, addr: u16, entry: u8) {
+        let entry: u8 = entry & MemBus::IO_WRITE_MASKS[(addr - 0xFF00) as usize];
+        match addr {
+            // The SGB listens in on the joypad register for command packets
+            0xFF00 => {
+                self.joypad.write(addr, entry);
+                self.sgb.write_register(entry);
+            }
+            0xFF0F => self.interrupt_flag = entry,
+            0xFF46 => self.dma.start(entry),
+            0xFF4D if self.cgb() => self.key1_prepare = entry != 0,
+            0xFF4F if self.cgb() => self.vram.set_bank(entry),
+            // Any nonzero write unmaps the boot ROM. It is a latch: the boot ROM is dropped, so nothing written
+            // here afterwards can map it back.
+            0xFF50 if entry != 0 => self.boot_rom = None,
+            0xFF70 if self.cgb() => self.wram.set_bank(entry),
+            _ => {
+                if let Some(device) = self.io_device_mut(addr) {
+                    device.write(addr, entry);
+                }
+            }
+        };
+    }
+
+    // The peripheral behind an IO register. Addresses no built in device claims go to the added devices.
+    fn io_device(&self, addr: u16) -> Option<&dyn IoDevice> {
+        match addr {
+            0xFF00 => Some(&self.joypad),
+            0xFF01..=0xFF02 => Some(&self.serial),
+            0xFF04..=0xFF07 => Some(&self.timer),
+            0xFF10..=0xFF3F => Some(&self.apu),
+            0xFF40..=0xFF45 | 0xFF47..=0xFF4B | 0xFF68..=0xFF6B => Some(&self.ppu),
+            _ => self
+                .io_devices
+                .iter()
+                .find(|(range, _)| range.contains(&addr))
+                .map(|(_, device)| device.as_ref()),
+        }
+    }
+
+    fn io_device_mut(&mut self, addr: u16) -> Option<&mut dyn IoDevice> {
+        match addr {
+            0xFF00 => Some(&mut self.joypad),
+            0xFF01..=0xFF02 => Some(&mut self.serial),
+            0xFF04..=0xFF07 => Some(&mut self.timer),
+            0xFF10..=0xFF3F => Some(&mut self.apu),
+            0xFF40..=0xFF45 | 0xFF47..=0xFF4B | 0xFF68..=0xFF6B => Some(&mut self.ppu),
+            _ => self
+                .io_devices
+                .iter_mut()
+                .find(|(range, _)| range.contains(&addr))
+                .map(|(_, device)| device.as_mut() as &mut dyn IoDevice),
+        }
+    }
+
+    // Moves over what a save state leaves out: the cartridge ROM, cheats and the debugger and tool hooks
+    fn take_session(&mut self, old: &mut MemBus) {
+        std::mem::swap(&mut self.rom, &mut old.rom);
+        self.watchpoints = std::mem::take(&mut old.watchpoints);
+        self.io_devices = std::mem::take(&mut old.io_devices);
+        self.access_counts = old.access_counts.take();
+        self.on_write = old.on_write.take();
+        self.on_read = old.on_read.take();
+        self.cpu_speed = old.cpu_speed;
+        self.cheats = std::mem::take(&mut old.cheats);
+    }
+
+    // Maps an extra device over start..=end in the IO page (0xFF00-0xFF7F)
+    pub fn add_io_device(&mut self, start: u16, end: u16, device: Box<dyn IoDevice>) {
+        self.io_devices.push((start..=end, device));
+    }
+
+    // None removes the hook
+    pub fn set_on_write(&mut self, hook: Option<MemoryHook>) {
+        self.on_write = hook;
+    }
+
+    pub fn set_on_read(&mut self, hook: Option<MemoryHook>) {
+        self.on_read = hook.map(std::cell::RefCell::new);
+    }
+
+    // The bank a .sym file lists addr under. Cartridge RAM banks aren't tracked, so SRAM is always bank 0.
+    fn bank_at(&self, addr: u16) -> u16 {
+        match addr {
+            0x0000..=0x7FFF => self.mapper.rom_bank(addr) as u16,
+            0x8000..=0x9FFF => self.vram.bank() as u16,
+            0xD000..=0xDFFF => self.wram.bank() as u16,
+            _ => 0,
+        }
+    }
+
+    // addr with its bank in front the way .sym files write it, e.g. 01:4123
+    fn banked_address(&self, addr: u16) -> String {
+        format!("{:02X}:{addr:04X}", self.bank_at(addr))
+    }
+
+    // A CPU read. Tools use peek so they don't trip read watchpoints.
+    fn access(&self, addr: u16) -> u8 {
+        // Blocked reads see the byte the DMA is moving, OAM itself reads as 0xFF
+        let value: u8 = match addr {
+            0xFE00..=0xFEFF if self.dma_blocks(addr) => 0xFF,
+            _ if self.dma_blocks(addr) => self.dma.byte,
+            _ => self.peek(addr),
+        };
+        if !self.watchpoints.is_empty() {
+            self.check_watchpoints(addr, WatchKind::Read, value, value);
+        }
+        if let Some(counts) = &self.access_counts {
+            let mut updated: AccessCounts = counts.get();
+            updated.reads[MemoryRegion::of(addr) as usize] += 1;
+            counts.set(updated);
+        }
+        if let Some(hook) = &self.on_read {
+            (hook.borrow_mut())(addr, value);
+        }
+        value
+    }
+
+    fn check_watchpoints(&self, addr: u16, kind: WatchKind, old: u8, new: u8) {
+        let watched: bool = self.watchpoints.iter().any(|watchpoint| {
+            (watchpoint.start..=watchpoint.end).contains(&addr)
+                && (watchpoint.kind == kind || watchpoint.kind == WatchKind::Access)
+        });
+        if watched && self.watch_hit.get().is_none() {
+            // The PC is filled in by the emulator once the instruction finishes
+            self.watch_hit.set(Some(WatchHit {
+                pc: 0x0000,
+                addr,
+                kind,
+                old,
+                new,
+            }));
+        }
+    }
+
+    fn peek(&self, addr: u16) -> u8 {
+        match addr {
+            0x0000..=0x7FFF => self.read_rom(addr),
+            0x8000..=0x9FFF => {
+                if self.ppu.vram_accessible() {
+                    self.vram.get_value(addr)
+                } else {
+                    0xFF
+                }
+            }
+            0xA000..=0xBFFF => self.mapper.read_ram(addr),
+            0xC000..=0xDFFF => self.wram.get_value(addr),
+            0xE000..=0xFDFF => 0x00, // Echo RAM. Can be ignored.
+            0xFE00..=0xFE9F => {
+                if self.ppu.oam_accessible() {
+                    self.oam.get_value(addr)
+                } else {
+                    0xFF
+                }
+            }
+            0xFEA0..=0xFEFF => 0xFF, // Not usable, ignore.
+            0xFF00..=0xFF7F => self.read_io(addr),
+            0xFF80..=0xFFFE => self.hram[(addr - 0xFF80) as usize], // High RAM
+            0xFFFF => self.interrupt_enable,                        // Interrupt enable
+        }
+    }
+
+    // A CPU write. Recorded for undo and checked against watchpoints before it is stored.
+    fn write(&mut self, addr: u16, entry: u8) {
+        // Mapper registers can't be read back, so writes to them are not undoable
+        if addr >= 0x8000 && self.write_log.is_some() {
+            let old: u8 = self.peek(addr);
+            if let Some(log) = &mut self.write_log {
+                log.push((addr, old));
+            }
+        }
+        if !self.watchpoints.is_empty() {
+            self.check_watchpoints(addr, WatchKind::Write, self.peek(addr), entry);
+        }
+        if let Some(counts) = &self.access_counts {
+            let mut updated: AccessCounts = counts.get();
+            updated.writes[MemoryRegion::of(addr) as usize] += 1;
+            counts.set(updated);
+        }
+        if let Some(hook) = &mut self.on_write {
+            hook(addr, entry);
+        }
+        if !self.dma_blocks(addr) {
+            self.store(addr, entry);
+        }
+    }
+
+    fn store(&mut self, addr: u16, entry: u8) {
+        match addr {
+            0x0000..=0x7FFF => self.mapper.write_register(addr, entry),
+            0x8000..=0x9FFF => {
+                if self.ppu.vram_accessible() {
+                    self.vram.set_value(addr, entry)
+                }
+            }
+            0xA000..=0xBFFF => self.mapper.write_ram(addr, entry),
+            0xC000..=0xDFFF => self.wram.set_value(addr, entry),
+            0xE000..=0xFDFF => log::warn!("Attempted to write to echo RAM address {addr}"),
+            0xFE00..=0xFE9F => {
+                if self.ppu.oam_accessible() {
+                    self.oam.set_value(addr, entry)
+                } else {
+                    self.oam_bug(addr, OamBug::Write)
+                }
+            }
+            0xFEA0..=0xFEFF => log::warn!("Attempted to write to unuasable space address {addr}"),
+            0xFF00..=0xFF7F => self.write_io(addr, entry),
+            0xFF80..=0xFFFE => self.hram[(addr - 0xFF80) as usize] = entry, // High RAM
+            0xFFFF => self.interrupt_enable = entry,                        // Interrupt enable
+        };
+    }
+
+    // Public wrappers so tests and tools can seed or inspect memory. The CPU uses access/write directly.
+    pub fn read(&self, addr: u16) -> u8 {
+        self.peek(addr)
+    }
+
+    pub fn write_byte(&mut self, addr: u16, entry: u8) {
+        self.store(addr, entry);
+    }
+}
+
+const CYCLES_PER_FRAME: u32 = 70224;
+// Bump the version when a serialized struct changes shape
+const STATE_MAGIC: &[u8; 4] = b"RGBS";
+const STATE_VERSION: u8 = 4;
+// Movies are a save state followed by the buttons held during each frame after it, one byte per frame
+const MOVIE_MAGIC: &[u8; 4] = b"RGBM";
+const MOVIE_VERSION: u8 = 1;
+// Battery RAM is flushed to the save file this often, about every 5 seconds, so a crash loses little
+const SAVE_INTERVAL_FRAMES: u32 = 300;
+
+// What an instruction changed, so the debugger can step backwards without a full save state.
+// Timers, the PPU and other hardware keep running forward.
+struct UndoRecord {
+    cpu: CpuState,
+    ime_scheduled: bool,
+    writes: Vec<(u16, u8)>,
+}
+
+// Owns the whole machine so frontends don't have to wire Rom, MemBus and Cpu together themselves
+pub struct Emulator {
+    cpu: Cpu,
+    frame_cycles: u32,
+    display_palette: DisplayPalette,
+    undo_history: std::collections::VecDeque<UndoRecord>,
+    undo_limit: usize,
+    watch_hit: Option<WatchHit>,
+    symbols: SymbolTable,
+    frame_access_counts: AccessCounts,
+    recording: Option<(fs::File, Vec<u8>)>,
+    // The inputs of the movie being played and the frame they are up to
+    movie: Option<(Vec<u8>, usize)>,
+    // The .sav file battery RAM is kept in and what was last written there
+    save_path: Option<PathBuf>,
+    saved_ram: Vec<u8>,
+    frames_since_save: u32,
+}
+
+impl Emulator {
+    // Everywhere a game keeps its variables: cartridge RAM, WRAM, OAM and HRAM
+    const SEARCH_AREAS: [(u16, u16); 4] = [
+        (0xA000, 0xBFFF),
+        (0xC000, 0xDFFF),
+        (0xFE00, 0xFE9F),
+        (0xFF80, 0xFFFE),
+    ];
+
+    pub fn new(rom: Vec<u8>) -> Result<Self, Error> {
+        Self::from_rom(Rom::from_bytes(rom)?)
+    }
+
+    pub fn from_rom(rom: Rom) -> Result<Self, Error> {
+        Self::with_memory_init(rom, MemoryInit::Zeros)
+    }
+
+    pub fn with_memory_init(rom: Rom, init: MemoryInit) -> Result<Self, Error> {
+        let model: Model = Model::for_rom(&rom);
+        let membus: MemBus = MemBus::with_memory_init(rom, init)?;
+        let mut emulator: Emulator = Emulator {
+            cpu: Cpu::new_with_model(membus, model),
+            frame_cycles: 0,
+            display_palette: DisplayPalette::default(),
+            undo_history: std::collections::VecDeque::new(),
+            undo_limit: 0,
+            watch_hit: None,
+            symbols: SymbolTable::default(),
+            frame_access_counts: AccessCounts::default(),
+            recording: None,
+            movie: None,
+            save_path: None,
+            saved_ram: Vec::new(),
+            frames_since_save: 0,
+        };
+        emulator.set_skip_boot_rom(true);
+        Ok(emulator)
+    }
+
+    pub fn model(&self) -> Model {
+        self.cpu.membus.model
+    }
+
+    // Overrides the model picked from the cartridge header. Games tell them apart by A at startup.
+    pub fn set_model(&mut self, model: Model) {
+        self.cpu.membus.set_model(model);
+        self.cpu.set_post_boot_registers(model);
+    }
+
+    pub fn cpu(&self) -> &Cpu {
+        &self.cpu
+    }
+
+    pub fn ppu(&self) -> &Ppu {
+        &self.cpu.membus.ppu
+    }
+
+    pub fn title(&self) -> &String {
+        self.cpu.membus.rom.get_title()
+    }
+
+    pub fn step(&mut self) -> u32 {
+        let pc: u16 = self.cpu.pc;
+        let cycles: u32 = if self.undo_limit > 0 {
+            self.step_undoable()
+        } else {
+            self.cpu.step()
+        };
+        // A frame is measured in PPU time, which runs at half the CPU rate in double speed
+        self.frame_cycles += if self.cpu.membus.double_speed {
+            cycles / 2
+        } else {
+            cycles
+        };
+        if let Some(mut hit) = self.cpu.membus.watch_hit.take()
+            && self.watch_hit.is_none()
+        {
+            hit.pc = pc;
+            self.watch_hit = Some(hit);
+        }
+        cycles
+    }
+
+    // Steps to the start of the next scanline and returns the dots (PPU T-cycles) that took
+    pub fn step_scanline(&mut self) -> u32 {
+        self.step_until_ppu(|before, after| before.0 != after.0)
+    }
+
+    // Steps until the PPU enters VBlank, passing the current one first if it is in it
+    pub fn step_to_vblank(&mut self) -> u32 {
+        self.step_until_ppu(|before, after| {
+            before.1 != PpuMode::VBlank && after.1 == PpuMode::VBlank
+        })
+    }
+
+    // Steps to the start of the next frame, when LY wraps back to 0
+    pub fn step_frame(&mut self) -> u32 {
+        self.step_until_ppu(|before, after| before.0 != 0 && after.0 == 0)
+    }
+
+    // Steps until the boundary check passes for the PPU's (LY, mode) before and after a step. Lands on
+    // the first instruction past the boundary, so the count overshoots it by part of an instruction.
+    // With the LCD off nothing moves, so it gives up after a frame's worth of dots.
+    fn step_until_ppu(&mut self, boundary: impl Fn((u8, PpuMode), (u8, PpuMode)) -> bool) -> u32 {
+        let mut dots: u32 = 0;
+        loop {
+            if self.cpu.stopped_at.is_some()
+                || (!self.ppu().lcd_enabled() && dots >= CYCLES_PER_FRAME)
+            {
+                return dots;
+            }
+            let before: (u8, PpuMode) = (self.ppu().ly(), self.ppu().mode());
+            let cycles: u32 = self.step();
+            dots += if self.cpu.membus.double_speed {
+                cycles / 2
+            } else {
+                cycles
+            };
+            if boundary(before, (self.ppu().ly(), self.ppu().mode())) {
+                return dots;
+            }
+        }
+    }
+
+    fn step_undoable(&mut self) -> u32 {
+        let cpu: CpuState = self.cpu.snapshot();
+        let ime_scheduled: bool = self.cpu.ime_scheduled;
+        self.cpu.membus.write_log = Some(Vec::new());
+        let cycles: u32 = self.cpu.step();
+        let writes: Vec<(u16, u8)> = self.cpu.membus.write_log.take().unwrap_or_default();
+        if self.undo_history.len() >= self.undo_limit {
+            self.undo_history.pop_front();
+        }
+        self.undo_history.push_back(UndoRecord {
+            cpu,
+            ime_scheduled,
+            writes,
+        });
+        cycles
+    }
+
+    // Keeps the last limit instructions undoable. 0 turns recording off.
+    pub fn set_undo_limit(&mut self, limit: usize) {
+        self.undo_limit = limit;
+        while self.undo_history.len() > limit {
+            self.undo_history.pop_front();
+        }
+    }
+
+    // Reverts the registers and memory writes of the last recorded instruction. False if there is none.
+    pub fn undo(&mut self) -> bool {
+        let Some(record) = self.undo_history.pop_back() else {
+            return false;
+        };
+        for (addr, old) in record.writes.iter().rev() {
+            self.cpu.membus.store(*addr, *old);
+        }
+        self.cpu.restore(&record.cpu);
+        self.cpu.ime_scheduled = record.ime_scheduled;
+        true
+    }
+
+    pub fn add_watchpoint(&mut self, addr: u16, kind: WatchKind) {
+        self.add_watch_range(addr, addr, kind);
+    }
+
+    pub fn add_watch_range(&mut self, start: u16, end: u16, kind: WatchKind) {
+        self.cpu
+            .membus
+            .watchpoints
+            .push(Watchpoint { start, end, kind });
+    }
+
+    pub fn clear_watchpoints(&mut self) {
+        self.cpu.membus.watchpoints.clear();
+    }
+
+    // Set once a watched address is accessed. run_frame does nothing until resume is called.
+    pub fn watch_hit(&self) -> Option<WatchHit> {
+        self.watch_hit
+    }
+
+    pub fn resume(&mut self) {
+        self.watch_hit = None;
+    }
+
+    // Writes value to every address from start to end, through the bus like the CPU would
+    pub fn fill_memory(&mut self, start: u16, end: u16, value: u8) {
+        for addr in start..=end {
+            self.write_byte(addr, value);
+        }
+    }
+
+    // Addresses in cartridge RAM, WRAM, OAM and HRAM holding value, in the banks currently mapped. Words
+    // are matched little-endian like the CPU stores them and never span two areas.
+    pub fn search_memory(&self, value: SearchValue) -> Vec<u16> {
+        let mut found: Vec<u16> = Vec::new();
+        for (start, end) in Emulator::SEARCH_AREAS {
+            for addr in start..=end {
+                let matched: bool = match value {
+                    SearchValue::Byte(byte) => self.read(addr) == byte,
+                    SearchValue::Word(word) => {
+                        addr < end
+                            && u16::from_le_bytes([self.read(addr), self.read(addr + 1)]) == word
+                    }
+                };
+                if matched {
+                    found.push(addr);
+                }
+            }
+        }
+        found
+    }
+
+    pub fn set_unimplemented_policy(&mut self, policy: UnimplementedPolicy) {
+        self.cpu.set_unimplemented_policy(policy);
+    }
+
+    pub fn add_io_device(&mut self, start: u16, end: u16, device: Box<dyn IoDevice>) {
+        self.cpu.membus.add_io_device(start, end, device);
+    }
+
+    // The hooks see every CPU access, opcode fetches included. read and write_byte don't trigger them.
+    pub fn set_on_write(&mut self, hook: Option<MemoryHook>) {
+        self.cpu.membus.set_on_write(hook);
+    }
+
+    pub fn set_on_read(&mut self, hook: Option<MemoryHook>) {
+        self.cpu.membus.set_on_read(hook);
+    }
+
+    pub fn set_symbols(&mut self, symbols: SymbolTable) {
+        self.symbols = symbols;
+    }
+
+    // The label at addr in whichever bank is mapped there right now
+    pub fn label(&self, addr: u16) -> Option<&str> {
+        self.symbols.lookup(self.cpu.membus.bank_at(addr), addr)
+    }
+
+    // addr as bank:address for whatever is mapped there right now, so code in high banks isn't ambiguous
+    pub fn banked_address(&self, addr: u16) -> String {
+        self.cpu.membus.banked_address(addr)
+    }
+
+    // Runs until a full frame's worth of cycles has passed. Overshoot carries into the next frame.
+    // Stops early, mid frame, when a watchpoint is hit.
+    pub fn run_frame(&mut self) {
+        while self.frame_cycles < CYCLES_PER_FRAME {
+            if self.watch_hit.is_some() || self.cpu.stopped_at.is_some() {
+                return;
+            }
+            self.step();
+        }
+        self.frame_cycles -= CYCLES_PER_FRAME;
+        if let Some((_, movie)) = &mut self.recording {
+            movie.push(self.cpu.membus.joypad.buttons());
+        }
+        self.next_movie_frame();
+        self.frames_since_save += 1;
+        if self.frames_since_save >= SAVE_INTERVAL_FRAMES
+            && let Err(e) = self.flush_save()
+        {
+            log::warn!("Could not write the save file: {e}");
+        }
+        if let Some(counts) = &self.cpu.membus.access_counts {
+            self.frame_access_counts = counts.take();
+        }
+        self.cpu.membus.apply_cheats();
+    }
+
+    // Like run_frame, but reports an unimplemented opcode the Stop policy halted on. The frame is left
+    // unfinished and the CPU stays on the opcode.
+    pub fn try_run_frame(&mut self) -> Result<(), Error> {
+        self.run_frame();
+        self.cpu.take_stop().map_or(Ok(()), Err)
+    }
+
+    // Starts or stops counting memory accesses per region
+    pub fn set_access_counting(&mut self, enabled: bool) {
+        self.cpu.membus.access_counts =
+            enabled.then(|| std::cell::Cell::new(AccessCounts::default()));
+        self.frame_access_counts = AccessCounts::default();
+    }
+
+    // Counts for the last completed frame while counting is on
+    pub fn access_counts(&self) -> Option<AccessCounts> {
+        self.cpu
+            .membus
+            .access_counts
+            .as_ref()
+            .map(|_| self.frame_access_counts)
+    }
+
+    // Runs frames back to back with no pacing. Stops at a watchpoint hit like run_frame does.
+    pub fn run_frames(&mut self, frames: u32) {
+        for _ in 0..frames {
+            if self.watch_hit.is_some() {
+                return;
+            }
+            self.run_frame();
+        }
+    }
+
+    pub fn add_cheat(&mut self, code: &str) -> Result<(), Error> {
+        Ok(self.cpu.membus.add_cheat(code)?)
+    }
+
+    // Returns false if no cheat with that code was active
+    pub fn remove_cheat(&mut self, code: &str) -> bool {
+        self.cpu.membus.remove_cheat(code)
+    }
+
+    pub fn read(&self, addr: u16) -> u8 {
+        self.cpu.membus.read(addr)
+    }
+
+    pub fn write_byte(&mut self, addr: u16, entry: u8) {
+        self.cpu.membus.write_byte(addr, entry);
+    }
+
+    // Starts execution in the given boot ROM at 0x0000 instead of at the cartridge entry point
+    pub fn set_boot_rom(&mut self, boot_rom: Vec<u8>) {
+        self.cpu.membus.boot_rom = Some(boot_rom);
+        self.set_skip_boot_rom(false);
+    }
+
+    // Skipping starts the machine exactly as the boot ROM leaves it: logo in VRAM, LCD on, registers set
+    // and PC at the cartridge entry point. Skipping drops the boot ROM, and without one it is always skipped.
+    pub fn set_skip_boot_rom(&mut self, skip: bool) {
+        if skip || self.cpu.membus.boot_rom.is_none() {
+            self.cpu.membus.boot_rom = None;
+            self.cpu.membus.post_boot();
+            self.cpu.set_post_boot_registers(self.cpu.membus.model);
+            self.cpu.pc = 0x0100;
+            self.cpu.sp = 0xFFFE;
+        } else {
+            // The boot ROM starts with the LCD off so it can clear VRAM
+            self.cpu.membus.write_io(0xFF40, 0x00);
+            self.cpu.pc = 0x0000;
+        }
+    }
+
+    pub fn skip_boot_rom(&self) -> bool {
+        self.cpu.membus.boot_rom.is_none()
+    }
+
+    pub fn set_entry_point(&mut self, pc: u16, sp: u16) {
+        self.cpu.pc = pc;
+        self.cpu.sp = sp;
+    }
+
+    pub fn framebuffer(&self) -> &[u8] {
+        self.cpu.membus.ppu.framebuffer()
+    }
+
+    pub fn color_framebuffer(&self) -> &[u16] {
+        self.cpu.membus.ppu.color_framebuffer()
+    }
+
+    // The colors DMG shades are shown with in framebuffer_rgba
+    pub fn set_display_palette(&mut self, palette: DisplayPalette) {
+        self.display_palette = palette;
+    }
+
+    // SGB games that have sent a palette are shown in its colors instead of the display palette
+    pub fn framebuffer_rgba(&self) -> Vec<u8> {
+        let palette: DisplayPalette = self
+            .cpu
+            .membus
+            .sgb
+            .display_palette()
+            .unwrap_or(self.display_palette);
+        self.cpu.membus.ppu.framebuffer_rgba(&palette)
+    }
+
+    pub fn serial_output(&self) -> &[u8] {
+        self.cpu.membus.serial.output()
+    }
+
+    // None unplugs the cable
+    pub fn set_link(&mut self, link: Option<Box<dyn LinkPartner>>) {
+        self.cpu.membus.serial.set_link(link);
+    }
+
+    pub fn link_connected(&self) -> bool {
+        self.cpu.membus.serial.link_connected()
+    }
+
+    // Checks the conventional ways test ROMs report a result. None while the test is still running.
+    fn test_result(&self) -> Option<TestResult> {
+        if let Some(result) = mooneye_result(&self.cpu.snapshot()) {
+            return Some(result);
+        }
+        // Blargg: text over serial ending in the verdict, "Passed" or "Passed all tests" on success and
+        // "Failed" or "Failed #n" otherwise. Only the last line counts so a test name doesn't match.
+        let serial: String = String::from_utf8_lossy(self.serial_output()).to_string();
+        let verdict: &str = serial.trim_end().lines().last().unwrap_or("");
+        if verdict.starts_with("Passed") {
+            return Some(TestResult::Pass);
+        }
+        if verdict.starts_with("Failed") {
+            return Some(TestResult::Fail(serial));
+        }
+        // Blargg: status at 0xA000 once the signature at 0xA001-0xA003 is written, text from 0xA004
+        let signature: [u8; 3] = [self.read(0xA001), self.read(0xA002), self.read(0xA003)];
+        let status: u8 = self.read(0xA000);
+        if signature == [0xDE, 0xB0, 0x61] && status != 0x80 {
+            if status == 0x00 {
+                return Some(TestResult::Pass);
+            }
+            let mut text: String = String::new();
+            let mut addr: u16 = 0xA004;
+            while addr < 0xC000 && self.read(addr) != 0x00 {
+                text.push(self.read(addr) as char);
+                addr += 1;
+            }
+            return Some(TestResult::Fail(text));
+        }
+        None
+    }
+
+    pub fn set_renderer(&mut self, renderer: PpuRenderer) {
+        self.cpu.membus.ppu.set_renderer(renderer);
+    }
+
+    pub fn set_frame_skip(&mut self, frames: u32) {
+        self.cpu.membus.ppu.set_frame_skip(frames);
+    }
+
+    // Runs the CPU faster or slower than the PPU, timer and APU, for telling CPU timing bugs from PPU ones.
+    // 100 is real hardware.
+    pub fn set_cpu_speed(&mut self, percent: u32) {
+        self.cpu.membus.cpu_speed = (percent != 100).then_some(percent.max(1));
+        self.cpu.membus.clock_remainder = 0;
+    }
+
+    pub fn cpu_speed(&self) -> u32 {
+        self.cpu.membus.cpu_speed.unwrap_or(100)
+    }
+
+    // Ignored while a movie is playing, so the movie's inputs aren't overridden
+    pub fn set_button(&mut self, button: Button, pressed: bool) {
+        if self.movie.is_none() {
+            self.cpu.membus.joypad.set_button(button, pressed);
+        }
+    }
+
+    // Records the current state and every frame's buttons from here on. The file is written by
+    // stop_recording.
+    pub fn start_recording(&mut self, path: &Path) -> Result<(), Error> {
+        let file: fs::File = fs::File::create(path)?;
+        let state: Vec<u8> = self.save_state()?;
+        let mut movie: Vec<u8> = Vec::new();
+        movie.extend_from_slice(MOVIE_MAGIC);
+        movie.push(MOVIE_VERSION);
+        movie.extend_from_slice(&(state.len() as u32).to_le_bytes());
+        movie.extend_from_slice(&state);
+        self.recording = Some((file, movie));
+        Ok(())
+    }
+
+    pub fn stop_recording(&mut self) -> Result<(), Error> {
+        match self.recording.take() {
+            Some((mut file, movie)) => Ok(file.write_all(&movie)?),
+            None => Ok(()),
+        }
+    }
+
+    pub fn recording(&self) -> bool {
+        self.recording.is_some()
+    }
+
+    // Loads the movie's state and feeds its inputs to the following frames. Playback ends with the
+    // last recorded frame and the buttons are left as they were then.
+    pub fn play_movie(&mut self, path: &Path) -> Result<(), Error> {
+        let data: Vec<u8> = fs::read(path)?;
+        if data.len() < 9 || &data[0..4] != MOVIE_MAGIC || data[4] != MOVIE_VERSION {
+            return Err(Error::InvalidMovie);
+        }
+        let state_len: usize = u32::from_le_bytes([data[5], data[6], data[7], data[8]]) as usize;
+        let Some(state) = data.get(9..9 + state_len) else {
+            return Err(Error::InvalidMovie);
+        };
+        self.load_state(state)?;
+        self.movie = Some((data[9 + state_len..].to_vec(), 0));
+        self.next_movie_frame();
+        Ok(())
+    }
+
+    pub fn movie_playing(&self) -> bool {
+        self.movie.is_some()
+    }
+
+    fn next_movie_frame(&mut self) {
+        let Some((inputs, frame)) = &mut self.movie else {
+            return;
+        };
+        match inputs.get(*frame) {
+            Some(buttons) => {
+                self.cpu.membus.joypad.set_buttons(*buttons);
+                *frame += 1;
+            }
+            None => self.movie = None,
+        }
+    }
+
+    // Whether the cartridge keeps its RAM (and clock) when switched off, so save_ram is worth persisting
+    pub fn has_battery(&self) -> bool {
+        self.cpu.membus.rom.cart_type.has_battery()
+    }
+
+    // Tile numbered from 0x8000 (0-383) in a VRAM bank, regardless of the bank the CPU has selected
+    pub fn tile(&self, bank: u8, tile: u16) -> Tile {
+        self.cpu.membus.vram.tile(bank, tile)
+    }
+
+    pub fn sprites(&self) -> Vec<OamEntry> {
+        self.cpu.membus.oam.entries()
+    }
+
+    pub fn sprite_tiles(&self, entry: &OamEntry) -> Vec<Tile> {
+        self.ppu().object_tiles(&self.cpu.membus.vram, entry)
+    }
+
+    pub fn map_tile(&self, addr: u16) -> Tile {
+        self.ppu().map_tile(&self.cpu.membus.vram, addr)
+    }
+
+    pub fn bank_state(&self) -> BankState {
+        let mapper: &dyn Mapper = self.cpu.membus.mapper.as_ref();
+        BankState {
+            rom_bank0: mapper.rom_bank(0x0000),
+            rom_bank: mapper.rom_bank(0x4000),
+            ram_bank: mapper.ram_bank(),
+            ram_enabled: mapper.ram_enabled(),
+            mode: mapper.banking_mode(),
+        }
+    }
+
+    // Frontends poll this after each frame to drive a controller's vibration
+    pub fn rumble(&self) -> bool {
+        self.cpu.membus.mapper.rumble()
+    }
+
+    pub fn save_ram(&self) -> Vec<u8> {
+        self.cpu.membus.mapper.save_ram(unix_time())
+    }
+
+    pub fn load_ram(&mut self, data: &[u8]) {
+        self.cpu.membus.mapper.load_ram(data, unix_time());
+    }
+
+    // Keeps battery RAM in a save file: loads it now if it exists, then writes RAM back every few seconds
+    // and when the emulator is dropped. Does nothing for cartridges without a battery.
+    pub fn set_save_path(&mut self, path: &Path) -> Result<(), Error> {
+        if !self.has_battery() {
+            return Ok(());
+        }
+        if path.exists() {
+            self.load_ram(&fs::read(path)?);
+        }
+        self.saved_ram = self.save_ram();
+        self.save_path = Some(path.to_path_buf());
+        Ok(())
+    }
+
+    // Writes battery RAM to the save file if it changed since the last write
+    pub fn flush_save(&mut self) -> Result<(), Error> {
+        self.frames_since_save = 0;
+        let Some(path) = &self.save_path else {
+            return Ok(());
+        };
+        let ram: Vec<u8> = self.save_ram();
+        if ram != self.saved_ram {
+            fs::write(path, &ram)?;
+            self.saved_ram = ram;
+        }
+        Ok(())
+    }
+
+    // The machine without the cartridge ROM, which the state is loaded back over. The header names the
+    // cartridge by a hash of its ROM so a state from another game is turned away.
+    pub fn save_state(&self) -> Result<Vec<u8>, Error> {
+        let mut state: Vec<u8> = Vec::new();
+        state.extend_from_slice(STATE_MAGIC);
+        state.push(STATE_VERSION);
+        state.extend_from_slice(&self.cpu.membus.rom.fingerprint());
+        let body: Vec<u8> = bincode::serde::encode_to_vec(
+            (self.frame_cycles, &self.cpu),
+            bincode::config::standard(),
+        )
+        .map_err(StateError::Encode)?;
+        state.extend_from_slice(&body);
+        Ok(state)
+    }
+
+    // Nothing is overwritten unless the whole state decodes
+    pub fn load_state(&mut self, state: &[u8]) -> Result<(), Error> {
+        if state.len() < 9 || &state[0..4] != STATE_MAGIC || state[4] != STATE_VERSION {
+            return Err(StateError::InvalidHeader.into());
+        }
+        if state[5..9] != self.cpu.membus.rom.fingerprint() {
+            return Err(StateError::WrongRom.into());
+        }
+        let ((frame_cycles, mut cpu), _): ((u32, Cpu), usize) =
+            bincode::serde::decode_from_slice(&state[9..], bincode::config::standard())
+                .map_err(StateError::Decode)?;
+        cpu.take_session(&mut self.cpu);
+        self.cpu = cpu;
+        self.frame_cycles = frame_cycles;
+        Ok(())
+    }
+}
+
+impl Drop for Emulator {
+    fn drop(&mut self) {
+        if let Err(e) = self.flush_save() {
+            log::error!("Could not write the save file: {e}");
+        }
+    }
+}
+
+#[derive(Clone, Copy, PartialEq, Debug)]
+pub enum SearchValue {
+    Byte(u8),
+    Word(u16),
+}
+
+// The DMG draws a frame every 70224 cycles of its 4.194304 MHz clock
+pub const FRAMES_PER_SECOND: f64 = 4194304.0 / CYCLES_PER_FRAME as f64;
+
+#[derive(Clone, Copy, PartialEq, Debug, Default)]
+pub struct SpeedStats {
+    pub fps: f64,
+    // Percentage of real hardware speed
+    pub speed: f64,
+}
+
+impl SpeedStats {
+    // Timestamps in seconds at which each emulated frame finished, oldest first
+    pub fn from_timestamps(timestamps: &[f64]) -> Self {
+        let (first, last): (f64, f64) = match (timestamps.first(), timestamps.last()) {
+            (Some(first), Some(last)) if last > first => (*first, *last),
+            _ => return SpeedStats::default(),
+        };
+        let fps: f64 = (timestamps.len() - 1) as f64 / (last - first);
+        SpeedStats {
+            fps,
+            speed: fps / FRAMES_PER_SECOND * 100.0,
+        }
+    }
+}
+
+// Timestamps in seconds of the last few frames, oldest first, for the speed overlay and frame time graph
+pub struct FrameTimes {
+    timestamps: std::collections::VecDeque<f64>,
+    capacity: usize,
+}
+
+impl FrameTimes {
+    pub fn new(capacity: usize) -> Self {
+        FrameTimes {
+            timestamps: std::collections::VecDeque::with_capacity(capacity),
+            capacity,
+        }
+    }
+
+    // Drops the oldest timestamp once the buffer is full
+    pub fn push(&mut self, timestamp: f64) {
+        if self.timestamps.len() == self.capacity {
+            self.timestamps.pop_front();
+        }
+        self.timestamps.push_back(timestamp);
+    }
+
+    pub fn len(&self) -> usize {
+        self.timestamps.len()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.timestamps.is_empty()
+    }
+
+    // Seconds each frame took, one fewer than there are timestamps
+    pub fn durations(&self) -> Vec<f64> {
+        self.timestamps
+            .iter()
+            .zip(self.timestamps.iter().skip(1))
+            .map(|(previous, next)| next - previous)
+            .collect()
+    }
+
+    pub fn stats(&self) -> SpeedStats {
+        let timestamps: Vec<f64> = self.timestamps.iter().copied().collect();
+        SpeedStats::from_timestamps(&timestamps)
+    }
+}
+
+#[derive(Debug, PartialEq)]
+pub enum TestResult {
+    Pass,
+    Fail(String),
+}
+
+// Mooneye tests leave Fibonacci numbers in B, C, D, E, H and L on a pass and 0x42 in all of them on a
+// failure. None for any other register state.
+pub fn mooneye_result(cpu: &CpuState) -> Option<TestResult> {
+    match [cpu.b, cpu.c, cpu.d, cpu.e, cpu.h, cpu.l] {
+        [3, 5, 8, 13, 21, 34] => Some(TestResult::Pass),
+        [0x42, 0x42, 0x42, 0x42, 0x42, 0x42] => {
+            Some(TestResult::Fail(String::from("Mooneye failure signature")))
+        }
+        _ => None,
+    }
+}
+
+// Two minutes of emulated time is enough for the longest Blargg suites
+const TEST_ROM_FRAME_LIMIT: u32 = 60 * 120;
+
+// Runs a test ROM headless until it reports a result through serial, cartridge RAM or registers
+pub fn run_test_rom(rom: Vec<u8>) -> TestResult {
+    let mut emulator: Emulator = match Emulator::new(rom) {
+        Ok(emulator) => emulator,
+        Err(e) => return TestResult::Fail(e.to_string()),
+    };
+    emulator.set_unimplemented_policy(UnimplementedPolicy::Stop);
+    for _ in 0..TEST_ROM_FRAME_LIMIT {
+        if let Err(e) = emulator.try_run_frame() {
+            return TestResult::Fail(e.to_string());
+        }
+        if let Some(result) = emulator.test_result() {
+            return result;
+        }
+    }
+    TestResult::Fail(format!(
+        "Timed out. Serial output: {}",
+        String::from_utf8_lossy(emulator.serial_output())
+    ))
+}
+
+#[cfg(feature = "gui")]
+pub struct Gui {
+    emulator: Emulator,
+    palette_preset: PalettePreset,
+    custom_palette: DisplayPalette,
+    screen: Option<egui::TextureHandle>,
+    frame_skip: u32,
+    show_overlay: bool,
+    show_frame_graph: bool,
+    frame_times: FrameTimes,
+    scale_mode: ScaleMode,
+    watch_input: String,
+    watch_kind: WatchKind,
+    search_input: String,
+    search_results: Vec<u16>,
+    cpu_speed: u32,
+    audio: AudioQueue,
+    // Set when emulation stopped on an error, which stays on screen
+    error: Option<String>,
+    #[cfg(feature = "gamepad")]
+    gamepads: Option<gilrs::Gilrs>,
+}
+
+#[cfg(feature = "gui")]
+impl Gui {
+    // Instructions that can be stepped back after stopping at a watchpoint
+    const UNDO_LIMIT: usize = 64;
+    // The side panel keeps a fixed width so the screen area is exactly what's left of the window
+    pub const CONTROLS_WIDTH: f32 = 300.0;
+    // About two seconds of frames for the speed overlay and the frame time graph
+    const GRAPH_FRAMES: usize = 120;
+
+    pub fn new(emulator: Emulator) -> Self {
+        Gui {
+            emulator,
+            palette_preset: PalettePreset::DmgGreen,
+            custom_palette: DisplayPalette::default(),
+            screen: None,
+            frame_skip: 0,
+            show_overlay: true,
+            show_frame_graph: false,
+            frame_times: FrameTimes::new(Gui::GRAPH_FRAMES),
+            scale_mode: ScaleMode::Integer,
+            watch_input: String::new(),
+            watch_kind: WatchKind::Write,
+            search_input: String::new(),
+            search_results: Vec::new(),
+            cpu_speed: 100,
+            audio: AudioQueue::default(),
+            error: None,
+            #[cfg(feature = "gamepad")]
+            gamepads: match gilrs::Gilrs::new() {
+                Ok(gilrs) => Some(gilrs),
+                Err(e) => {
+                    log::warn!("Gamepad support unavailable: {e}");
+                    None
+                }
+            },
+        }
+    }
+
+    pub fn set_scale_mode(&mut self, mode: ScaleMode) {
+        self.scale_mode = mode;
+    }
+
+    // Hosts that underrun need a longer buffer, at the cost of sound lagging the picture
+    pub fn set_audio_latency(&mut self, milliseconds: u32) {
+        self.audio = AudioQueue::with_latency(milliseconds, AudioQueue::SAMPLE_RATE);
+    }
+
+    fn palette(&self) -> DisplayPalette {
+        match self.palette_preset {
+            PalettePreset::DmgGreen => DisplayPalette::DMG_GREEN,
+            PalettePreset::PocketGray => DisplayPalette::POCKET_GRAY,
+            PalettePreset::Custom => self.custom_palette,
+        }
+    }
+
+    fn screen_image(&mut self) -> egui::ColorImage {
+        self.emulator.set_display_palette(self.palette());
+        egui::ColorImage::from_rgba_unmultiplied(
+            [SCREEN_WIDTH, SCREEN_HEIGHT],
+            &self.emulator.framebuffer_rgba(),
+        )
+    }
+
+    fn ppu_ui(&self, ui: &mut egui::Ui) {
+        let ppu: PpuState = self.emulator.ppu().snapshot();
+        ui.heading("PPU");
+        ui.label(format!("LY: {} dot {} ({:?})", ppu.ly, ppu.dot, ppu.mode));
+        ui.label(format!("LCDC: {:08b}", ppu.lcdc));
+        ui.label(format!("STAT: {:08b}", ppu.stat));
+        ui.label(format!("SCX/SCY: {}, {}", ppu.scx, ppu.scy));
+        ui.label(format!("WX/WY: {}, {}", ppu.wx, ppu.wy));
+    }
+
+    fn audio_ui(&self, ui: &mut egui::Ui) {
+        ui.heading("Audio");
+        ui.label(format!(
+            "Buffer: {} samples ({} ms)",
+            self.audio.capacity(),
+            self.audio.capacity() as u32 * 1000 / AudioQueue::SAMPLE_RATE
+        ));
+        ui.label(format!(
+            "Queued: {}, underruns: {}, overruns: {}",
+            self.audio.len(),
+            self.audio.underruns(),
+            self.audio.overruns()
+        ));
+    }
+
+    fn access_counts_ui(&mut self, ui: &mut egui::Ui) {
+        ui.heading("Memory access");
+        let mut enabled: bool = self.emulator.access_counts().is_some();
+        if ui.checkbox(&mut enabled, "Count per frame").changed() {
+            self.emulator.set_access_counting(enabled);
+        }
+        let Some(counts) = self.emulator.access_counts() else {
+            return;
+        };
+        egui::Grid::new("access_counts").show(ui, |ui| {
+            ui.label("Region");
+            ui.label("Reads");
+            ui.label("Writes");
+            ui.end_row();
+            for region in MemoryRegion::ALL {
+                ui.label(region.name());
+                ui.label(counts.reads(region).to_string());
+                ui.label(counts.writes(region).to_string());
+                ui.end_row();
+            }
+        });
+    }
+
+    // Frame times as a line over a band marking a full speed frame. Spikes above the band are stutters.
+    fn frame_graph_ui(&self, ui: &mut egui::Ui) {
+        let durations: Vec<f64> = self.frame_times.durations();
+        let (rect, _): (egui::Rect, egui::Response) =
+            ui.allocate_exact_size(egui::vec2(240.0, 60.0), egui::Sense::hover());
+        let painter: &egui::Painter = ui.painter();
+        painter.rect_filled(rect, 0.0, egui::Color32::from_black_alpha(160));
+        // Twice a full speed frame fills the graph, anything slower is clipped to the top
+        let target: f64 = 1.0 / FRAMES_PER_SECOND;
+        let y = |duration: f64| -> f32 {
+            rect.bottom() - (duration / (target * 2.0)).min(1.0) as f32 * rect.height()
+        };
+        painter.hline(
+            rect.x_range(),
+            y(target),
+            egui::Stroke::new(1.0, egui::Color32::DARK_GREEN),
+        );
+        let step: f32 = rect.width() / (Gui::GRAPH_FRAMES - 1) as f32;
+        let points: Vec<egui::Pos2> = durations
+            .iter()
+            .enumerate()
+            .map(|(i, duration)| egui::pos2(rect.left() + i as f32 * step, y(*duration)))
+            .collect();
+        painter.add(egui::Shape::line(
+            points,
+            egui::Stroke::new(1.0, egui::Color32::WHITE),
+        ));
+        let worst: f64 = durations.iter().copied().fold(0.0, f64::max);
+        ui.colored_label(
+            egui::Color32::WHITE,
+            format!("Slowest frame {:.1} ms", worst * 1000.0),
+        );
+    }
+
+    // Labels from the symbol file where there is one, hex otherwise
+    fn address_name(&self, addr: u16) -> String {
+        match self.emulator.label(addr) {
+            Some(label) => format!("{label} ({})", self.emulator.banked_address(addr)),
+            None => self.emulator.banked_address(addr),
+        }
+    }
+
+    fn debugger_ui(&mut self, ui: &mut egui::Ui) {
+        ui.heading("Debugger");
+        let cpu_speed: egui::Response =
+            ui.add(egui::Slider::new(&mut self.cpu_speed, 25..=400).text("CPU clock %"));
+        if cpu_speed.changed() {
+            self.emulator.set_cpu_speed(self.cpu_speed);
+        }
+        ui.horizontal(|ui| {
+            ui.label("Watch address");
+            ui.text_edit_singleline(&mut self.watch_input);
+            egui::ComboBox::from_id_salt("watch_kind")
+                .selected_text(format!("{:?}", self.watch_kind))
+                .show_ui(ui, |ui| {
+                    for kind in [WatchKind::Read, WatchKind::Write, WatchKind::Access] {
+                        ui.selectable_value(&mut self.watch_kind, kind, format!("{kind:?}"));
+                    }
+                });
+            if ui.button("Add").clicked() {
+                let input: &str = self.watch_input.trim().trim_start_matches("0x");
+                match u16::from_str_radix(input, 16) {
+                    Ok(addr) => self.emulator.add_watchpoint(addr, self.watch_kind),
+                    Err(_) => log::warn!("Invalid watch address {}", self.watch_input),
+                };
+            }
+            if ui.button("Clear").clicked() {
+                self.emulator.clear_watchpoints();
+            }
+        });
+        self.search_ui(ui);
+        let Some(hit) = self.emulator.watch_hit() else {
+            return;
+        };
+        ui.label(format!(
+            "{:?} of {} at PC {} ({:02X} -> {:02X})",
+            hit.kind,
+            self.address_name(hit.addr),
+            self.address_name(hit.pc),
+            hit.old,
+            hit.new
+        ));
+        ui.horizontal(|ui| {
+            if ui.button("Continue").clicked() {
+                self.emulator.set_undo_limit(0);
+                self.emulator.resume();
+            }
+            if ui.button("Step").clicked() {
+                self.emulator.set_undo_limit(Gui::UNDO_LIMIT);
+                self.emulator.step();
+            }
+            if ui.button("Step line").clicked() {
+                self.emulator.set_undo_limit(0);
+                self.emulator.step_scanline();
+            }
+            if ui.button("Step to VBlank").clicked() {
+                self.emulator.set_undo_limit(0);
+                self.emulator.step_to_vblank();
+            }
+            if ui.button("Step frame").clicked() {
+                self.emulator.set_undo_limit(0);
+                self.emulator.step_frame();
+            }
+            if ui.button("Step back").clicked() {
+                self.emulator.undo();
+            }
+        });
+    }
+
+    // Up to 2 hex digits searches for a byte, 3 or 4 for a word
+    fn search_ui(&mut self, ui: &mut egui::Ui) {
+        ui.horizontal(|ui| {
+            ui.label("Search value");
+            ui.text_edit_singleline(&mut self.search_input);
+            if ui.button("Search").clicked() {
+                let input: &str = self.search_input.trim().trim_start_matches("0x");
+                let value: Option<SearchValue> = match (input.len(), u16::from_str_radix(input, 16))
+                {
+                    (1..=2, Ok(value)) => Some(SearchValue::Byte(value as u8)),
+                    (3..=4, Ok(value)) => Some(SearchValue::Word(value)),
+                    _ => None,
+                };
+                match value {
+                    Some(value) => self.search_results = self.emulator.search_memory(value),
+                    None => log::warn!("Invalid search value {}", self.search_input),
+                };
+            }
+        });
+        if self.search_results.is_empty() {
+            return;
+        }
+        ui.label(format!("{} matches", self.search_results.len()));
+        for addr in self.search_results.iter().take(16) {
+            ui.label(format!(
+                "{} = {:02X}",
+                self.address_name(*addr),
+                self.emulator.read(*addr)
+            ));
+        }
+    }
+
+    fn update_buttons(&mut self, ctx: &egui::Context) {
+        let keys: [(egui::Key, Button); 8] = [
+            (egui::Key::ArrowRight, Button::Right),
+            (egui::Key::ArrowLeft, Button::Left),
+            (egui::Key::ArrowUp, Button::Up),
+            (egui::Key::ArrowDown, Button::Down),
+            (egui::Key::Z, Button::A),
+            (egui::Key::X, Button::B),
+            (egui::Key::Backspace, Button::Select),
+            (egui::Key::Enter, Button::Start),
+        ];
+        let pad_buttons: Vec<Button> = self.gamepad_buttons();
+        for (key, button) in keys {
+            let pressed: bool =
+                ctx.input(|input| input.key_down(key)) || pad_buttons.contains(&button);
+            self.emulator.set_button(button, pressed);
+        }
+    }
+
+    // Buttons held on any connected controller. Draining the events keeps gilrs' state current
+    // and picks up controllers plugged in or removed while running.
+    #[cfg(feature = "gamepad")]
+    fn gamepad_buttons(&mut self) -> Vec<Button> {
+        let mut buttons: Vec<Button> = Vec::new();
+        let Some(gilrs) = &mut self.gamepads else {
+            return buttons;
+        };
+        while let Some(event) = gilrs.next_event() {
+            match event.event {
+                gilrs::EventType::Connected => {
+                    log::info!("Gamepad connected: {}", gilrs.gamepad(event.id).name())
+                }
+                gilrs::EventType::Disconnected => log::info!("Gamepad disconnected"),
+                _ => (),
+            };
+        }
+        for (_, gamepad) in gilrs.gamepads() {
+            for pad_button in [
+                gilrs::Button::DPadRight,
+                gilrs::Button::DPadLeft,
+                gilrs::Button::DPadUp,
+                gilrs::Button::DPadDown,
+                gilrs::Button::East,
+                gilrs::Button::South,
+                gilrs::Button::Select,
+                gilrs::Button::Start,
+            ] {
+                if gamepad.is_pressed(pad_button)
+                    && let Some(button) = gamepad_button(pad_button)
+                {
+                    buttons.push(button);
+                }
+            }
+        }
+        buttons
+    }
+
+    #[cfg(not(feature = "gamepad"))]
+    fn gamepad_buttons(&mut self) -> Vec<Button> {
+        Vec::new()
+    }
+
+    fn controls_ui(&mut self, ui: &mut egui::Ui) {
+        ui.horizontal(|ui| {
+            for mode in [ScaleMode::Integer, ScaleMode::Fit] {
+                ui.radio_value(&mut self.scale_mode, mode, mode.name());
+            }
+        });
+        egui::ComboBox::from_label("Palette")
+            .selected_text(self.palette_preset.name())
+            .show_ui(ui, |ui| {
+                for preset in [
+                    PalettePreset::DmgGreen,
+                    PalettePreset::PocketGray,
+                    PalettePreset::Custom,
+                ] {
+                    ui.selectable_value(&mut self.palette_preset, preset, preset.name());
+                }
+            });
+        if self.palette_preset == PalettePreset::Custom {
+            ui.horizontal(|ui| {
+                for color in self.custom_palette.colors.iter_mut() {
+                    ui.color_edit_button_srgba_unmultiplied(color);
+                }
+            });
+        }
+        let frame_skip: egui::Response =
+            ui.add(egui::Slider::new(&mut self.frame_skip, 0..=5).text("Frame skip"));
+        if frame_skip.changed() {
+            self.emulator.set_frame_skip(self.frame_skip);
+        }
+        let cpu: CpuState = self.emulator.cpu().snapshot();
+        ui.heading("rgb-emu CPU visualizer");
+        ui.label(format!("A: {:X?}", cpu.a));
+        ui.label(format!("B: {:X?}", cpu.b));
+        ui.label(format!("C: {:X?}", cpu.c));
+        ui.label(format!("D: {:X?}", cpu.d));
+        ui.label(format!("E: {:X?}", cpu.e));
+        ui.label(format!("F: {:X?} ({})", cpu.f, cpu.flag_string()));
+        ui.label(format!("H: {:X?}", cpu.h));
+        ui.label(format!("L: {:X?}", cpu.l));
+        ui.label(format!("SP: {:X?}", cpu.sp));
+        ui.label(format!("PC: {}", self.address_name(cpu.pc)));
+        let banks: BankState = self.emulator.bank_state();
+        ui.label(format!(
+            "ROM banks: {:02X}/{:02X} RAM bank: {:02X} ({}) Mode: {}",
+            banks.rom_bank0,
+            banks.rom_bank,
+            banks.ram_bank,
+            if banks.ram_enabled { "on" } else { "off" },
+            banks.mode
+        ));
+        self.ppu_ui(ui);
+        self.audio_ui(ui);
+        self.access_counts_ui(ui);
+        self.debugger_ui(ui);
+    }
+}
+
+#[cfg(feature = "gui")]
+impl App for Gui {
+    fn update(&mut self, ctx: &egui::Context, _frame: &mut eframe::Frame) {
+        self.update_buttons(ctx);
+        if self.error.is_none()
+            && let Err(e) = self.emulator.try_run_frame()
+        {
+            log::error!("{e}");
+            self.error = Some(e.to_string());
+        }
+        self.frame_times.push(ctx.input(|input| input.time));
+        if ctx.input(|input| input.key_pressed(egui::Key::F1)) {
+            self.show_overlay = !self.show_overlay;
+        }
+        if ctx.input(|input| input.key_pressed(egui::Key::F2)) {
+            self.show_frame_graph = !self.show_frame_graph;
+        }
+        let image: egui::ColorImage = self.screen_image();
+        match &mut self.screen {
+            Some(texture) => texture.set(image, egui::TextureOptions::NEAREST),
+            None => {
+                self.screen = Some(ctx.load_texture("screen", image, egui::TextureOptions::NEAREST))
+            }
+        };
+        egui::SidePanel::right("controls")
+            .exact_width(Gui::CONTROLS_WIDTH)
+            .show(ctx, |ui| {
+                egui::ScrollArea::vertical().show(ui, |ui| self.controls_ui(ui));
+            });
+        egui::CentralPanel::default()
+            .frame(egui::Frame::new().fill(egui::Color32::BLACK))
+            .show(ctx, |ui| {
+                // The overlay is drawn over the screen so hiding it doesn't move the picture
+                if let Some(texture) = &self.screen {
+                    let area: egui::Rect = ui.available_rect_before_wrap();
+                    let (x, y, width, height): (f32, f32, f32, f32) =
+                        screen_placement(area.width(), area.height(), self.scale_mode);
+                    let rect: egui::Rect = egui::Rect::from_min_size(
+                        area.min + egui::vec2(x, y),
+                        egui::vec2(width, height),
+                    );
+                    egui::Image::new(texture).paint_at(ui, rect);
+                }
+                if self.show_overlay {
+                    let stats: SpeedStats = self.frame_times.stats();
+                    ui.colored_label(
+                        egui::Color32::WHITE,
+                        format!(
+                            "{} | {:.1} FPS | {:.0}% speed (F1 to hide, F2 for frame times)",
+                            self.emulator.title(),
+                            stats.fps,
+                            stats.speed
+                        ),
+                    );
+                }
+                if self.show_frame_graph {
+                    self.frame_graph_ui(ui);
+                }
+                if let Some(error) = &self.error {
+                    ui.colored_label(egui::Color32::RED, error);
+                }
+            });
+        ctx.request_repaint();
+    }
+}
+
+// Browser entry point. The page passes in the canvas to draw to and the ROM file it loaded.
+#[cfg(all(feature = "gui", target_arch = "wasm32"))]
+#[wasm_bindgen::prelude::wasm_bindgen]
+pub async fn start_web(
+    canvas: web_sys::HtmlCanvasElement,
+    rom: Vec<u8>,
+) -> Result<(), wasm_bindgen::JsValue> {
+    let emulator: Emulator = match Emulator::new(rom) {
+        Ok(emulator) => emulator,
+        Err(e) => return Err(wasm_bindgen::JsValue::from_str(&e.to_string())),
+    };
+    let gui: Gui = Gui::new(emulator);
+    eframe::WebRunner::new()
+        .start(
+            canvas,
+            eframe::WebOptions::default(),
+            Box::new(|_| Ok(Box::<Gui>::new(gui))),
+        )
+        .await
 }
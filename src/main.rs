@@ -1,18 +1,241 @@
 use eframe::egui;
-use rgb_emu::{Cpu, Gui, MemBus, Rom};
+use rgb_emu::{Emulator, Gui, Model, Rom, RomError, SCREEN_HEIGHT, SCREEN_WIDTH, SaveConfig, SaveNaming, SymbolTable, TcpLink, UnimplementedPolicy};
 use std::env;
+use std::fs;
+use std::io::Write;
+use std::path::{Path, PathBuf};
+use std::process;
+
+const USAGE: &str = "usage: rgb-emu [--boot-rom <path>] [--scale <n>] [--no-gui] [--trace] [--force-dmg | --force-cgb] [--info] [--sym <path>] [--unimplemented <stop|panic|log|nop>] [--link-listen <addr> | --link-connect <addr>] [--patch <path>] [--save-dir <path>] [--save-name <title|rom>] [--audio-latency <ms>] <rom-path>";
+
+struct Options {
+    rom: String,
+    boot_rom: Option<String>,
+    scale: u32,
+    gui: bool,
+    trace: bool,
+    model: Option<Model>,
+    info: bool,
+    symbols: Option<String>,
+    unimplemented: UnimplementedPolicy,
+    link: Option<Link>,
+    patch: Option<String>,
+    saves: SaveConfig,
+    audio_latency: Option<u32>,
+}
+
+enum Link {
+    Listen(String),
+    Connect(String),
+}
+
+fn parse_args(args: &[String]) -> Result<Options, String> {
+    let mut rom: Option<String> = None;
+    let mut boot_rom: Option<String> = None;
+    let mut scale: u32 = 2;
+    let mut gui: bool = true;
+    let mut trace: bool = false;
+    let mut model: Option<Model> = None;
+    let mut info: bool = false;
+    let mut symbols: Option<String> = None;
+    // Stopping lets the frontend report the opcode instead of crashing
+    let mut unimplemented: UnimplementedPolicy = UnimplementedPolicy::Stop;
+    let mut link: Option<Link> = None;
+    let mut patch: Option<String> = None;
+    let mut saves: SaveConfig = SaveConfig::default();
+    let mut audio_latency: Option<u32> = None;
+    let mut args = args.iter();
+    while let Some(arg) = args.next() {
+        match arg.as_str() {
+            "--boot-rom" => match args.next() {
+                Some(path) => boot_rom = Some(path.clone()),
+                None => return Err(String::from("--boot-rom needs a path")),
+            },
+            "--scale" => match args.next().map(|n| n.parse::<u32>()) {
+                Some(Ok(n)) if n > 0 => scale = n,
+                _ => return Err(String::from("--scale needs a whole number above 0")),
+            },
+            "--no-gui" => gui = false,
+            "--trace" => trace = true,
+            "--force-dmg" => model = Some(Model::Dmg),
+            "--force-cgb" => model = Some(Model::Cgb),
+            "--info" => info = true,
+            "--sym" => match args.next() {
+                Some(path) => symbols = Some(path.clone()),
+                None => return Err(String::from("--sym needs a path")),
+            },
+            "--unimplemented" => match args.next().map(|policy| policy.as_str()) {
+                Some("stop") => unimplemented = UnimplementedPolicy::Stop,
+                Some("panic") => unimplemented = UnimplementedPolicy::Panic,
+                Some("log") => unimplemented = UnimplementedPolicy::Log,
+                Some("nop") => unimplemented = UnimplementedPolicy::Nop,
+                _ => return Err(String::from("--unimplemented needs stop, panic, log or nop")),
+            },
+            "--link-listen" => match args.next() {
+                Some(addr) => link = Some(Link::Listen(addr.clone())),
+                None => return Err(String::from("--link-listen needs an address like 0.0.0.0:5555")),
+            },
+            "--link-connect" => match args.next() {
+                Some(addr) => link = Some(Link::Connect(addr.clone())),
+                None => return Err(String::from("--link-connect needs an address like 192.168.1.2:5555")),
+            },
+            "--patch" => match args.next() {
+                Some(path) => patch = Some(path.clone()),
+                None => return Err(String::from("--patch needs a path to an IPS or BPS file")),
+            },
+            "--save-dir" => match args.next() {
+                Some(path) => saves.directory = Some(PathBuf::from(path)),
+                None => return Err(String::from("--save-dir needs a path")),
+            },
+            "--save-name" => match args.next().map(|naming| naming.as_str()) {
+                Some("title") => saves.naming = SaveNaming::Title,
+                Some("rom") => saves.naming = SaveNaming::RomFile,
+                _ => return Err(String::from("--save-name needs title or rom")),
+            },
+            "--audio-latency" => match args.next().map(|ms| ms.parse::<u32>()) {
+                Some(Ok(ms)) => audio_latency = Some(ms),
+                _ => return Err(String::from("--audio-latency needs a whole number of milliseconds")),
+            },
+            flag if flag.starts_with("--") => return Err(format!("Unknown option {flag}")),
+            path => match rom {
+                None => rom = Some(path.to_string()),
+                Some(_) => return Err(format!("Unexpected argument {path}")),
+            },
+        };
+    }
+    match rom {
+        Some(rom) => Ok(Options { rom, boot_rom, scale, gui, trace, model, info, symbols, unimplemented, link, patch, saves, audio_latency }),
+        None => Err(String::from("Missing ROM path")),
+    }
+}
+
+// Runs without a window until emulation stops, echoing anything the game sends over serial. Returns the
+// exit code so the caller exits after the emulator is dropped and battery RAM is flushed.
+fn run_headless(mut emulator: Emulator) -> i32 {
+    let mut printed: usize = 0;
+    loop {
+        if let Err(e) = emulator.try_run_frame() {
+            eprintln!("\nEmulation stopped: {e}");
+            return 1;
+        }
+        let output: &[u8] = emulator.serial_output();
+        if output.len() > printed {
+            print!("{}", String::from_utf8_lossy(&output[printed..]));
+            let _ = std::io::stdout().flush();
+            printed = output.len();
+        }
+    }
+}
 
 fn main() {
-    let args: Vec<String> = env::args().collect();
-    let path: &String = &args[1];
-    let rom: Rom = Rom::new(path);
+    let args: Vec<String> = env::args().skip(1).collect();
+    let options: Options = match parse_args(&args) {
+        Ok(options) => options,
+        Err(e) => {
+            eprintln!("{e}\n{USAGE}");
+            process::exit(2);
+        }
+    };
+    let mut logger: env_logger::Builder = env_logger::Builder::from_default_env();
+    if options.trace {
+        logger.filter_level(log::LevelFilter::Trace);
+    }
+    logger.init();
+    let loaded: Result<Rom, RomError> = match &options.patch {
+        Some(path) => match fs::read(path) {
+            Ok(patch) => Rom::new_with_patch(&options.rom, &patch),
+            Err(e) => {
+                eprintln!("Could not read patch {path}: {e}");
+                process::exit(1);
+            }
+        },
+        None => Rom::new(&options.rom),
+    };
+    let rom: Rom = match loaded {
+        Ok(rom) => rom,
+        Err(e) => {
+            eprintln!("Could not load {}: {e}", options.rom);
+            process::exit(1);
+        }
+    };
+    if options.info {
+        println!("{}", rom.info());
+        return;
+    }
     let title: String = rom.get_title().clone();
-    let cpu: Cpu = Cpu::new(MemBus::new(rom));
+    let mut emulator: Emulator = match Emulator::from_rom(rom) {
+        Ok(emulator) => emulator,
+        Err(e) => {
+            eprintln!("Could not start {}: {e}", options.rom);
+            process::exit(1);
+        }
+    };
+    emulator.set_unimplemented_policy(options.unimplemented);
+    if let Some(model) = options.model {
+        emulator.set_model(model);
+    }
+    // RGBDS writes game.sym next to game.gb, so pick that up unless told otherwise
+    let symbols: Option<String> = options.symbols.clone().or_else(|| {
+        let path: PathBuf = Path::new(&options.rom).with_extension("sym");
+        path.exists().then(|| path.to_string_lossy().to_string())
+    });
+    if let Some(path) = symbols {
+        match SymbolTable::load(&path) {
+            Ok(symbols) => {
+                log::info!("Loaded {} symbols from {path}", symbols.len());
+                emulator.set_symbols(symbols);
+            }
+            Err(e) => {
+                eprintln!("Could not read symbols {path}: {e}");
+                process::exit(1);
+            }
+        };
+    }
+    let save_path: PathBuf = options.saves.path(Path::new(&options.rom), &title, "sav");
+    if let Err(e) = emulator.set_save_path(&save_path) {
+        eprintln!("Could not read save file {}: {e}", save_path.display());
+        process::exit(1);
+    }
+    if let Some(path) = &options.boot_rom {
+        match fs::read(path) {
+            Ok(boot_rom) => emulator.set_boot_rom(boot_rom),
+            Err(e) => {
+                eprintln!("Could not read boot ROM {path}: {e}");
+                process::exit(1);
+            }
+        };
+    }
+    if let Some(link) = &options.link {
+        let connected: std::io::Result<TcpLink> = match link {
+            Link::Listen(addr) => {
+                println!("Waiting for a link partner on {addr}");
+                TcpLink::listen(addr.as_str())
+            }
+            Link::Connect(addr) => TcpLink::connect(addr.as_str()),
+        };
+        match connected {
+            Ok(link) => emulator.set_link(Some(Box::new(link))),
+            Err(e) => {
+                eprintln!("Could not set up the link cable: {e}");
+                process::exit(1);
+            }
+        };
+    }
+    if !options.gui {
+        process::exit(run_headless(emulator));
+    }
+    let scale: f32 = options.scale as f32;
+    let mut gui: Gui = Gui::new(emulator);
+    if let Some(latency) = options.audio_latency {
+        gui.set_audio_latency(latency);
+    }
     let options: eframe::NativeOptions = eframe::NativeOptions {
-        viewport: egui::ViewportBuilder::default().with_inner_size([320.0, 240.0]),
+        viewport: egui::ViewportBuilder::default().with_inner_size([
+            SCREEN_WIDTH as f32 * scale + Gui::CONTROLS_WIDTH,
+            SCREEN_HEIGHT as f32 * scale,
+        ]),
         ..Default::default()
     };
-    let gui: Gui = Gui::new(cpu);
     let _ = eframe::run_native(
         &title,
         options,
@@ -0,0 +1,35 @@
+use rgb_emu::{AccessCounts, Emulator, MemoryRegion};
+
+mod common;
+
+// 16 bytes of code ending in a HALT nothing wakes, so the first frame holds just these accesses
+const PROGRAM: &str =
+    "LD HL,$C000; LD (HL),A; LD B,(HL); LD ($FF80),A; LD HL,$8000; LD B,(HL); LD ($FF47),A; HALT";
+
+#[test]
+fn a_known_sequence_gives_known_counts() {
+    let mut emulator: Emulator = common::program_emulator(&common::assemble(PROGRAM));
+    emulator.set_access_counting(true);
+    emulator.run_frame();
+    let counts: AccessCounts = emulator.access_counts().expect("counting is on");
+    let reads: Vec<u32> = MemoryRegion::ALL
+        .map(|region| counts.reads(region))
+        .to_vec();
+    let writes: Vec<u32> = MemoryRegion::ALL
+        .map(|region| counts.writes(region))
+        .to_vec();
+    // Rom, Vram, CartRam, Wram, Oam, Io, Hram
+    assert_eq!(reads, [16, 1, 0, 1, 0, 0, 0]);
+    assert_eq!(writes, [0, 0, 0, 1, 0, 1, 1]);
+}
+
+#[test]
+fn counting_is_off_until_asked_for() {
+    let mut emulator: Emulator = common::program_emulator(&common::assemble(PROGRAM));
+    emulator.run_frame();
+    assert_eq!(emulator.access_counts(), None);
+    emulator.set_access_counting(true);
+    assert_eq!(emulator.access_counts(), Some(AccessCounts::default()));
+    emulator.set_access_counting(false);
+    assert_eq!(emulator.access_counts(), None);
+}
@@ -0,0 +1,27 @@
+mod common;
+
+use common::{assemble, program_cpu};
+use rgb_emu::{Cpu, CpuState};
+
+#[test]
+fn add_a_hl_reads_the_byte_at_hl_in_8_cycles() {
+    let mut cpu: Cpu = program_cpu(&assemble("LD HL,$C100; LD (HL),$30; LD A,$12; ADD A,(HL)"));
+    cpu.run_for(3);
+    assert_eq!(cpu.step(), 8);
+    let state: CpuState = cpu.snapshot();
+    assert_eq!(state.a, 0x42);
+    assert_eq!(state.pc, 0xC008);
+}
+
+#[test]
+fn every_alu_op_on_hl_takes_8_cycles() {
+    for op in ["ADD", "ADC", "SUB", "SBC", "AND", "XOR", "OR", "CP"] {
+        let program: Vec<u8> = assemble(&format!("LD HL,$C100; {op} A,(HL)"));
+        let mut cpu: Cpu = program_cpu(&program);
+        cpu.step();
+        assert_eq!(cpu.step(), 8, "{op} A,(HL)");
+        // Versus 4 for a register operand
+        let mut cpu: Cpu = program_cpu(&assemble(&format!("{op} A,B")));
+        assert_eq!(cpu.step(), 4, "{op} A,B");
+    }
+}
@@ -0,0 +1,68 @@
+use rgb_emu::{AudioQueue, UnderrunPolicy};
+
+#[test]
+fn size_is_clamped_to_a_safe_range() {
+    assert_eq!(AudioQueue::new(0).capacity(), AudioQueue::MIN_CAPACITY);
+    assert_eq!(
+        AudioQueue::new(1 << 30).capacity(),
+        AudioQueue::MAX_CAPACITY
+    );
+    assert_eq!(AudioQueue::new(4096).capacity(), 4096);
+    assert_eq!(AudioQueue::with_latency(50, 48000).capacity(), 2400);
+    assert_eq!(
+        AudioQueue::default().capacity(),
+        AudioQueue::DEFAULT_CAPACITY
+    );
+}
+
+#[test]
+fn a_fast_producer_drops_the_oldest_samples() {
+    let mut queue: AudioQueue = AudioQueue::new(256);
+    let mut next: u32 = 0;
+    let mut out: [f32; 64] = [0.0; 64];
+    // Three samples made for every one played
+    for _ in 0..20 {
+        for _ in 0..192 {
+            queue.push(next as f32);
+            next += 1;
+        }
+        queue.fill(&mut out);
+    }
+    assert_eq!(queue.len(), 256 - 64);
+    assert_eq!(queue.underruns(), 0);
+    assert!(queue.overruns() > 0);
+    // Latency stays bounded: what's left is the newest audio, in order
+    let last_played: f32 = out[63];
+    assert_eq!(queue.pop(), last_played + 1.0);
+    assert_eq!(last_played, (next - 256 + 63) as f32);
+}
+
+#[test]
+fn a_fast_consumer_pads_with_silence_by_default() {
+    let mut queue: AudioQueue = AudioQueue::new(256);
+    let mut out: [f32; 128] = [1.0; 128];
+    // Half as many samples made as played
+    for _ in 0..4 {
+        for _ in 0..64 {
+            queue.push(0.5);
+        }
+        queue.fill(&mut out);
+        assert!(out[..64].iter().all(|sample| *sample == 0.5));
+        assert!(out[64..].iter().all(|sample| *sample == 0.0));
+    }
+    assert_eq!(queue.underruns(), 4 * 64);
+    assert_eq!(queue.overruns(), 0);
+    assert!(queue.is_empty());
+}
+
+#[test]
+fn repeat_last_holds_the_final_sample_through_an_underrun() {
+    let mut queue: AudioQueue = AudioQueue::new(256);
+    queue.set_underrun_policy(UnderrunPolicy::RepeatLast);
+    queue.push(0.25);
+    queue.push(-0.75);
+    let mut out: [f32; 4] = [0.0; 4];
+    queue.fill(&mut out);
+    assert_eq!(out, [0.25, -0.75, -0.75, -0.75]);
+    assert_eq!(queue.underruns(), 2);
+}
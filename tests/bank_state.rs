@@ -0,0 +1,35 @@
+mod common;
+
+use common::mbc1_emulator;
+use rgb_emu::{BankState, Emulator};
+
+#[test]
+fn mbc1_bank_select_is_reported() {
+    let mut emulator: Emulator = mbc1_emulator();
+    assert_eq!(
+        emulator.bank_state(),
+        BankState {
+            rom_bank0: 0,
+            rom_bank: 1,
+            ram_bank: 0,
+            ram_enabled: false,
+            mode: 0,
+        }
+    );
+    emulator.write_byte(0x2000, 0x05);
+    assert_eq!(emulator.bank_state().rom_bank, 5);
+    emulator.write_byte(0x0000, 0x0A);
+    emulator.write_byte(0x4000, 0x02);
+    emulator.write_byte(0x6000, 0x01);
+    let banks: BankState = emulator.bank_state();
+    assert!(banks.ram_enabled);
+    assert_eq!((banks.mode, banks.ram_bank), (1, 2));
+}
+
+#[test]
+fn rom_only_cartridges_stay_on_banks_0_and_1() {
+    let emulator: Emulator = Emulator::new(vec![0x00; 0x8000]).expect("valid test ROM");
+    let banks: BankState = emulator.bank_state();
+    assert_eq!((banks.rom_bank0, banks.rom_bank), (0, 1));
+    assert!(!banks.ram_enabled);
+}
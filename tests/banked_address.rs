@@ -0,0 +1,15 @@
+mod common;
+
+use common::mbc1_emulator;
+use rgb_emu::Emulator;
+
+#[test]
+fn switchable_bank_addresses_carry_the_selected_bank() {
+    let mut emulator: Emulator = mbc1_emulator();
+    assert_eq!(emulator.banked_address(0x4000), "01:4000");
+    emulator.write_byte(0x2000, 0x03);
+    assert_eq!(emulator.banked_address(0x4000), "03:4000");
+    assert_eq!(emulator.banked_address(0x7FFF), "03:7FFF");
+    // Bank 0 stays put
+    assert_eq!(emulator.banked_address(0x0150), "00:0150");
+}
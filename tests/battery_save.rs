@@ -0,0 +1,69 @@
+mod common;
+
+use common::idle_rom;
+use rgb_emu::Emulator;
+use std::path::PathBuf;
+
+fn save_path(name: &str) -> PathBuf {
+    std::env::temp_dir().join(format!("rgb-emu-{}-{name}.sav", std::process::id()))
+}
+
+// MBC1 with 8 KiB of RAM, with or without a battery
+fn battery_emulator(battery: bool) -> Emulator {
+    let mut data: Vec<u8> = idle_rom();
+    data[0x0147] = if battery { 0x03 } else { 0x02 };
+    data[0x0149] = 0x02;
+    Emulator::new(data).expect("valid test ROM")
+}
+
+fn write_ram(emulator: &mut Emulator, value: u8) {
+    emulator.write_byte(0x0000, 0x0A);
+    emulator.write_byte(0xA000, value);
+}
+
+#[test]
+fn dropping_writes_battery_ram() {
+    let path: PathBuf = save_path("drop");
+    let mut emulator: Emulator = battery_emulator(true);
+    emulator.set_save_path(&path).expect("no save file yet");
+    // Nothing changed, so there is nothing to write
+    emulator.flush_save().expect("flush succeeds");
+    assert!(!path.exists());
+    write_ram(&mut emulator, 0x42);
+    drop(emulator);
+    let saved: Vec<u8> = std::fs::read(&path).expect("save file written");
+    assert_eq!(saved.len(), 0x2000);
+    assert_eq!(saved[0], 0x42);
+
+    // The next session starts from the save
+    let mut emulator: Emulator = battery_emulator(true);
+    emulator.set_save_path(&path).expect("save file loads");
+    emulator.write_byte(0x0000, 0x0A);
+    assert_eq!(emulator.read(0xA000), 0x42);
+    drop(emulator);
+    std::fs::remove_file(path).expect("save file can be removed");
+}
+
+#[test]
+fn flushing_is_idempotent() {
+    let path: PathBuf = save_path("flush");
+    let mut emulator: Emulator = battery_emulator(true);
+    emulator.set_save_path(&path).expect("no save file yet");
+    write_ram(&mut emulator, 0x17);
+    emulator.flush_save().expect("flush succeeds");
+    std::fs::remove_file(&path).expect("save file written");
+    // Unchanged RAM isn't written again
+    emulator.flush_save().expect("flush succeeds");
+    drop(emulator);
+    assert!(!path.exists());
+}
+
+#[test]
+fn carts_without_a_battery_never_save() {
+    let path: PathBuf = save_path("no-battery");
+    let mut emulator: Emulator = battery_emulator(false);
+    emulator.set_save_path(&path).expect("ignored");
+    write_ram(&mut emulator, 0x42);
+    drop(emulator);
+    assert!(!path.exists());
+}
@@ -0,0 +1,45 @@
+use rgb_emu::{IoDevice, Oam, Ppu, PpuRenderer, SCREEN_WIDTH, Vram};
+
+const FRAME_DOTS: u32 = 456 * 154;
+
+// Tile 1 is solid color 3 and sits in the last column and last row of the 0x9800 map, everything else
+// is tile 0 (color 0)
+fn edge_vram() -> Vram {
+    let mut vram: Vram = Vram::new();
+    for row in 0..16u16 {
+        vram.set_value(0x8010 + row, 0xFF);
+    }
+    for i in 0..32u16 {
+        vram.set_value(0x9800 + i * 32 + 31, 0x01);
+        vram.set_value(0x9800 + 31 * 32 + i, 0x01);
+    }
+    vram
+}
+
+fn render(renderer: PpuRenderer, scx: u8, scy: u8) -> Vec<u8> {
+    let mut vram: Vram = edge_vram();
+    let oam: Oam = Oam::new();
+    let mut ppu: Ppu = Ppu::new();
+    ppu.set_renderer(renderer);
+    ppu.write(0xFF47, 0b1110_0100);
+    ppu.write(0xFF43, scx);
+    ppu.write(0xFF42, scy);
+    ppu.write(0xFF40, 0b1001_0001);
+    ppu.step(FRAME_DOTS, &mut vram, &oam);
+    ppu.framebuffer().to_vec()
+}
+
+#[test]
+fn scrolling_past_256_wraps_to_the_other_side_of_the_map() {
+    for renderer in [PpuRenderer::Scanline, PpuRenderer::Fifo] {
+        // The left 4 pixels are the right half of map column 31, then column 0 starts over
+        let frame: Vec<u8> = render(renderer, 252, 0);
+        assert_eq!(&frame[0..8], &[3, 3, 3, 3, 0, 0, 0, 0], "{renderer:?}");
+        // The top 4 lines are the bottom half of map row 31
+        let frame: Vec<u8> = render(renderer, 0, 252);
+        for y in 0..4 {
+            assert_eq!(frame[y * SCREEN_WIDTH + 10], 3, "{renderer:?} line {y}");
+        }
+        assert_eq!(frame[4 * SCREEN_WIDTH + 10], 0, "{renderer:?}");
+    }
+}
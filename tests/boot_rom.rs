@@ -0,0 +1,67 @@
+mod common;
+
+use common::{idle_emulator, idle_rom};
+use rgb_emu::{CpuState, Emulator};
+
+fn logo_emulator() -> Emulator {
+    let mut data: Vec<u8> = idle_rom();
+    // First byte of the Nintendo logo, enough to see it copied
+    data[0x0104] = 0xCE;
+    Emulator::new(data).expect("valid test ROM")
+}
+
+// Spins at 0x0000 so the boot ROM never hands over
+fn stub_boot_rom() -> Vec<u8> {
+    let mut boot_rom: Vec<u8> = vec![0x00; 0x100];
+    boot_rom[0x0000] = 0x18;
+    boot_rom[0x0001] = 0xFE;
+    boot_rom
+}
+
+#[test]
+fn skips_to_the_entry_point_without_a_boot_rom() {
+    let emulator: Emulator = logo_emulator();
+    let cpu: CpuState = emulator.cpu().snapshot();
+    assert!(emulator.skip_boot_rom());
+    assert_eq!((cpu.pc, cpu.sp), (0x0100, 0xFFFE));
+    assert_eq!((cpu.a, cpu.f), (0x01, 0xB0));
+    assert_eq!(emulator.read(0xFF40), 0x91);
+    // The logo's top nibble doubled to 8 pixels
+    assert_eq!(emulator.read(0x8010), 0xF0);
+}
+
+#[test]
+fn runs_a_supplied_boot_rom() {
+    let mut emulator: Emulator = idle_emulator();
+    emulator.set_boot_rom(stub_boot_rom());
+    assert!(!emulator.skip_boot_rom());
+    assert_eq!(emulator.cpu().snapshot().pc, 0x0000);
+    assert_eq!(emulator.read(0xFF40), 0x00);
+    assert_eq!(emulator.read(0x0001), 0xFE);
+}
+
+#[test]
+fn skipping_a_supplied_boot_rom_restores_the_post_boot_state() {
+    let mut emulator: Emulator = idle_emulator();
+    emulator.set_boot_rom(stub_boot_rom());
+    emulator.set_skip_boot_rom(true);
+    assert!(emulator.skip_boot_rom());
+    assert_eq!(emulator.cpu().snapshot().pc, 0x0100);
+    assert_eq!(emulator.read(0xFF40), 0x91);
+    assert_eq!(emulator.read(0x0001), 0x00);
+}
+
+#[test]
+fn disabling_the_boot_rom_latches() {
+    let mut emulator: Emulator = idle_emulator();
+    emulator.set_boot_rom(stub_boot_rom());
+    assert_eq!(emulator.read(0xFF50), 0xFE);
+    emulator.write_byte(0xFF50, 0x01);
+    assert_eq!(emulator.read(0xFF50), 0xFF);
+    assert_eq!(emulator.read(0x0001), 0x00);
+    // Clearing the register again can't bring the boot ROM back
+    emulator.write_byte(0xFF50, 0x00);
+    assert_eq!(emulator.read(0xFF50), 0xFF);
+    assert_eq!(emulator.read(0x0001), 0x00);
+    assert!(emulator.skip_boot_rom());
+}
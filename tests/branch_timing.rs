@@ -0,0 +1,40 @@
+use rgb_emu::Cpu;
+
+mod common;
+
+// Cycles taken by the branch after XOR A, which sets Z and clears C
+fn branch_cycles(branch: &[u8]) -> u32 {
+    let mut program: Vec<u8> = vec![0xAF];
+    program.extend_from_slice(branch);
+    let mut cpu: Cpu = common::program_cpu(&program);
+    cpu.step();
+    cpu.step()
+}
+
+#[test]
+fn a_not_taken_jr_is_faster_than_a_taken_one() {
+    // JR Z,+0 and JR NZ,+0
+    assert_eq!(branch_cycles(&[0x28, 0x00]), 12);
+    assert_eq!(branch_cycles(&[0x20, 0x00]), 8);
+    // JR C,+0 and JR NC,+0
+    assert_eq!(branch_cycles(&[0x38, 0x00]), 8);
+    assert_eq!(branch_cycles(&[0x30, 0x00]), 12);
+}
+
+#[test]
+fn jp_call_and_ret_follow_the_opcode_table() {
+    // (taken, not taken) for JP, CALL and RET on Z
+    let pairs: [([u8; 3], [u8; 3], u32, u32); 3] = [
+        ([0xCA, 0x00, 0xC0], [0xC2, 0x00, 0xC0], 16, 12),
+        ([0xCC, 0x00, 0xC0], [0xC4, 0x00, 0xC0], 24, 12),
+        ([0xC8, 0x00, 0x00], [0xC0, 0x00, 0x00], 20, 8),
+    ];
+    for (taken, not_taken, taken_cycles, not_taken_cycles) in pairs {
+        assert_eq!(branch_cycles(&taken), taken_cycles, "{taken:02X?}");
+        assert_eq!(
+            branch_cycles(&not_taken),
+            not_taken_cycles,
+            "{not_taken:02X?}"
+        );
+    }
+}
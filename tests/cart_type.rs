@@ -0,0 +1,31 @@
+use rgb_emu::CartType;
+
+#[test]
+fn battery_predicate_follows_the_variant() {
+    assert!(CartType::MBC3RAMBATTERY.has_battery());
+    assert!(!CartType::ROMONLY.has_battery());
+    // MBC2 keeps its built in RAM alive with a battery but has no external RAM
+    assert!(CartType::MBC2BATTERY.has_battery());
+    assert!(!CartType::MBC2BATTERY.has_ram());
+}
+
+#[test]
+fn ram_timer_and_rumble_predicates() {
+    assert!(CartType::MBC1RAM.has_ram());
+    assert!(!CartType::MBC1RAM.has_battery());
+    assert!(CartType::MBC3TIMERBATTERY.has_timer());
+    assert!(!CartType::MBC3TIMERBATTERY.has_ram());
+    assert!(!CartType::MBC3RAMBATTERY.has_timer());
+    assert!(CartType::MBC5RUMBLERAMBATTERY.has_rumble());
+    assert!(!CartType::MBC5RAMBATTERY.has_rumble());
+}
+
+#[test]
+fn names_match_the_header_documentation() {
+    assert_eq!(CartType::ROMONLY.to_string(), "ROM ONLY");
+    assert_eq!(
+        CartType::MBC3TIMERRAMBATTERY.to_string(),
+        "MBC3+TIMER+RAM+BATTERY"
+    );
+    assert_eq!(format!("{:?}", CartType::MBC1), "MBC1");
+}
@@ -0,0 +1,41 @@
+use rgb_emu::{CartType, CartridgeHeader, Rom};
+
+fn header_rom(old_licensee: u8) -> Rom {
+    let mut data: Vec<u8> = vec![0x00; 0x20000];
+    data[0x0134..0x013C].copy_from_slice(b"TESTGAME");
+    data[0x0143] = 0x80;
+    data[0x0144..0x0146].copy_from_slice(b"01");
+    data[0x0146] = 0x03;
+    data[0x0147] = 0x13;
+    data[0x0148] = 0x02;
+    data[0x0149] = 0x03;
+    data[0x014B] = old_licensee;
+    data[0x014C] = 0x02;
+    Rom::from_bytes(data).expect("valid test ROM")
+}
+
+#[test]
+fn header_fields_are_parsed() {
+    let header: CartridgeHeader = header_rom(0x33).header();
+    assert_eq!(
+        header,
+        CartridgeHeader {
+            title: String::from("TESTGAME"),
+            cart_type: CartType::MBC3RAMBATTERY,
+            rom_size: 131072,
+            rom_banks: 8,
+            ram_size: 32768,
+            ram_banks: 4,
+            cgb_flag: 0x80,
+            sgb_flag: 0x03,
+            licensee: String::from("01"),
+            version: 0x02,
+        }
+    );
+    assert_eq!(header.cart_type.to_string(), "MBC3+RAM+BATTERY");
+}
+
+#[test]
+fn old_licensee_codes_are_shown_in_hex() {
+    assert_eq!(header_rom(0x0A).header().licensee, "0A");
+}
@@ -0,0 +1,33 @@
+mod common;
+
+use common::{assemble, program_emulator};
+use rgb_emu::Emulator;
+
+// Keeps incrementing the byte at 0xC100
+fn counting_emulator() -> Emulator {
+    program_emulator(&assemble("LD HL,$C100; INC (HL); JR -3"))
+}
+
+#[test]
+fn gameshark_code_keeps_a_wram_byte_pinned_each_frame() {
+    let mut emulator: Emulator = counting_emulator();
+    emulator
+        .add_cheat("014200C1")
+        .expect("valid GameShark code");
+    for _ in 0..3 {
+        emulator.run_frame();
+        assert_eq!(emulator.read(0xC100), 0x42);
+    }
+    assert!(emulator.remove_cheat("014200c1"));
+    emulator.run_frame();
+    assert_ne!(emulator.read(0xC100), 0x42);
+}
+
+#[test]
+fn malformed_codes_are_rejected() {
+    let mut emulator: Emulator = counting_emulator();
+    for code in ["", "0142", "01ZZ00C1", "014200C1FF", "ABC-DE"] {
+        assert!(emulator.add_cheat(code).is_err(), "{code:?}");
+    }
+    assert!(!emulator.remove_cheat("014200C1"));
+}
@@ -0,0 +1,156 @@
+// Runs the built binary. `--info` exits right after parsing and loading the ROM, so a zero exit means the
+// flags parsed without a window opening.
+use std::path::PathBuf;
+use std::process::{Command, Output};
+
+mod common;
+
+fn temp_rom(name: &str) -> PathBuf {
+    let path: PathBuf =
+        std::env::temp_dir().join(format!("rgb-emu-{}-{name}.gb", std::process::id()));
+    std::fs::write(&path, common::idle_rom()).expect("temp dir is writable");
+    path
+}
+
+fn run(args: &[&str]) -> Output {
+    Command::new(env!("CARGO_BIN_EXE_rgb-emu"))
+        .args(args)
+        .output()
+        .expect("binary runs")
+}
+
+#[test]
+fn flag_combinations_parse() {
+    let path: PathBuf = temp_rom("flags");
+    let rom: &str = path.to_str().expect("utf-8 temp path");
+    let combinations: [&[&str]; 6] = [
+        &["--info", rom],
+        &["--audio-latency", "80", "--info", rom],
+        &[rom, "--info", "--no-gui", "--trace"],
+        &["--scale", "4", "--boot-rom", "dmg_boot.bin", "--info", rom],
+        &[
+            "--force-cgb",
+            "--unimplemented",
+            "nop",
+            "--save-name",
+            "rom",
+            "--info",
+            rom,
+        ],
+        &[
+            "--info",
+            "--sym",
+            "game.sym",
+            "--save-dir",
+            "saves",
+            "--link-listen",
+            "0.0.0.0:5555",
+            rom,
+        ],
+    ];
+    for args in combinations {
+        let output: Output = run(args);
+        assert!(
+            output.status.success(),
+            "{args:?}: {}",
+            String::from_utf8_lossy(&output.stderr)
+        );
+        assert!(
+            String::from_utf8_lossy(&output.stdout).contains("Title:"),
+            "{args:?}"
+        );
+    }
+    std::fs::remove_file(path).expect("temp file is removable");
+}
+
+#[test]
+fn bad_flags_print_the_reason_and_usage() {
+    let path: PathBuf = temp_rom("bad-flags");
+    let rom: &str = path.to_str().expect("utf-8 temp path");
+    let cases: [(&[&str], &str); 8] = [
+        (
+            &["--scale", "0", rom],
+            "--scale needs a whole number above 0",
+        ),
+        (
+            &["--scale", "big", rom],
+            "--scale needs a whole number above 0",
+        ),
+        (&[rom, "--boot-rom"], "--boot-rom needs a path"),
+        (
+            &["--unimplemented", "ignore", rom],
+            "--unimplemented needs stop, panic, log or nop",
+        ),
+        (
+            &["--save-name", "slot", rom],
+            "--save-name needs title or rom",
+        ),
+        (
+            &["--audio-latency", "-5", rom],
+            "--audio-latency needs a whole number of milliseconds",
+        ),
+        (&["--fast", rom], "Unknown option --fast"),
+        (&[rom, "other.gb"], "Unexpected argument other.gb"),
+    ];
+    for (args, reason) in cases {
+        let output: Output = run(args);
+        let stderr: String = String::from_utf8_lossy(&output.stderr).to_string();
+        assert_eq!(output.status.code(), Some(2), "{args:?}");
+        assert!(stderr.contains(reason), "{args:?}: {stderr}");
+        assert!(stderr.contains("usage: rgb-emu"), "{args:?}: {stderr}");
+    }
+    std::fs::remove_file(path).expect("temp file is removable");
+}
+
+#[test]
+fn no_arguments_print_usage_instead_of_panicking() {
+    let output: Output = run(&[]);
+    let stderr: String = String::from_utf8_lossy(&output.stderr).to_string();
+    assert_eq!(output.status.code(), Some(2));
+    assert!(stderr.contains("Missing ROM path"), "{stderr}");
+    assert!(stderr.contains("usage: rgb-emu"), "{stderr}");
+    assert!(!stderr.contains("panicked"), "{stderr}");
+}
+
+#[test]
+fn bad_rom_paths_surface_the_rom_error() {
+    let missing: PathBuf =
+        std::env::temp_dir().join(format!("rgb-emu-{}-missing.gb", std::process::id()));
+    let output: Output = run(&[missing.to_str().expect("utf-8 temp path")]);
+    let stderr: String = String::from_utf8_lossy(&output.stderr).to_string();
+    assert_eq!(output.status.code(), Some(1));
+    assert!(stderr.starts_with("Could not load"), "{stderr}");
+
+    // The file exists but its header names a cartridge type that doesn't exist
+    let path: PathBuf = temp_rom("bad-header");
+    let mut data: Vec<u8> = common::idle_rom();
+    data[0x0147] = 0xEE;
+    std::fs::write(&path, data).expect("temp dir is writable");
+    let output: Output = run(&[path.to_str().expect("utf-8 temp path")]);
+    let stderr: String = String::from_utf8_lossy(&output.stderr).to_string();
+    assert_eq!(output.status.code(), Some(1));
+    assert!(stderr.starts_with("Could not load"), "{stderr}");
+    assert!(stderr.contains("Unknown cartridge type: EE"), "{stderr}");
+    std::fs::remove_file(path).expect("temp file is removable");
+}
+
+#[test]
+fn headless_stop_still_flushes_battery_ram() {
+    // MBC1+RAM+BATTERY that writes RAM and then hits an unimplemented opcode under the default Stop policy
+    let path: PathBuf = temp_rom("headless-save");
+    let mut data: Vec<u8> = common::idle_rom();
+    let mut program: Vec<u8> = common::assemble("LD A,$0A; LD ($0000),A; LD A,$42; LD ($A000),A");
+    program.push(0xD3);
+    data[0x0100..0x0100 + program.len()].copy_from_slice(&program);
+    data[0x0147] = 0x03;
+    data[0x0149] = 0x02;
+    std::fs::write(&path, data).expect("temp dir is writable");
+    let output: Output = run(&["--no-gui", path.to_str().expect("utf-8 temp path")]);
+    assert_eq!(output.status.code(), Some(1));
+    assert!(String::from_utf8_lossy(&output.stderr).contains("Emulation stopped"));
+    let save: PathBuf = path.with_extension("sav");
+    let saved: Vec<u8> = std::fs::read(&save).expect("save file written");
+    assert_eq!(saved[0], 0x42);
+    std::fs::remove_file(save).expect("save file is removable");
+    std::fs::remove_file(path).expect("temp file is removable");
+}
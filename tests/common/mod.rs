@@ -0,0 +1,161 @@
+// Helpers for instruction tests. Pull them in with `mod common;`. Not every test uses every helper.
+#![allow(dead_code)]
+
+use rgb_emu::{Cpu, Emulator, MemBus, Rom};
+
+const R8: [&str; 8] = ["B", "C", "D", "E", "H", "L", "(HL)", "A"];
+const R16: [&str; 4] = ["BC", "DE", "HL", "SP"];
+const R16_STACK: [&str; 4] = ["BC", "DE", "HL", "AF"];
+const ALU: [&str; 8] = ["ADD", "ADC", "SUB", "SBC", "AND", "XOR", "OR", "CP"];
+
+// Copies a code fragment into memory. Writes to ROM go to the mapper, so load into RAM (0xC000 up).
+pub fn load_program(bus: &mut MemBus, addr: u16, program: &[u8]) {
+    for (i, byte) in program.iter().enumerate() {
+        bus.write_byte(addr + i as u16, *byte);
+    }
+}
+
+// A CPU over an empty ROM about to run the program from 0xC000
+pub fn program_cpu(program: &[u8]) -> Cpu {
+    let rom: Rom = Rom::from_bytes(vec![0x00; 0x8000]).expect("valid test ROM");
+    let mut bus: MemBus = MemBus::new(rom).expect("supported test mapper");
+    load_program(&mut bus, 0xC000, program);
+    Cpu::with_entry_point(bus, 0xC000, 0xFFFE)
+}
+
+// A 32 KiB ROM-only cartridge that spins on JR -2 at the entry point
+pub fn idle_rom() -> Vec<u8> {
+    let mut data: Vec<u8> = vec![0x00; 0x8000];
+    data[0x0100] = 0x18;
+    data[0x0101] = 0xFE;
+    data
+}
+
+pub fn idle_emulator() -> Emulator {
+    Emulator::new(idle_rom()).expect("valid test ROM")
+}
+
+// A 512 KiB MBC1+RAM+BATTERY cartridge with 32 KiB of RAM that spins like idle_rom
+pub fn mbc1_emulator() -> Emulator {
+    let mut data: Vec<u8> = idle_rom();
+    data.resize(0x80000, 0x00);
+    data[0x0147] = 0x03;
+    data[0x0148] = 0x04;
+    data[0x0149] = 0x03;
+    Emulator::new(data).expect("valid test ROM")
+}
+
+// An emulator with the program as cartridge code at the 0x0100 entry point, for tests that also need to
+// look at memory
+pub fn program_emulator(program: &[u8]) -> Emulator {
+    let mut data: Vec<u8> = vec![0x00; 0x8000];
+    data[0x0100..0x0100 + program.len()].copy_from_slice(program);
+    Emulator::new(data).expect("valid test ROM")
+}
+
+// Assembles one instruction per line, or per `;` separated statement. Covers the loads, 8-bit ALU,
+// INC/DEC, jumps and stack instructions tests tend to need. Numbers are decimal, $hex or 0xhex, and
+// a JR operand is the signed offset from the next instruction. Panics on anything else.
+pub fn assemble(source: &str) -> Vec<u8> {
+    let mut bytes: Vec<u8> = Vec::new();
+    for line in source.split(['\n', ';']) {
+        let line: String = line.trim().to_uppercase();
+        if line.is_empty() {
+            continue;
+        }
+        let (mnemonic, operands) = line.split_once(' ').unwrap_or((&line, ""));
+        let operands: Vec<&str> = operands
+            .split(',')
+            .map(str::trim)
+            .filter(|operand| !operand.is_empty())
+            .collect();
+        match assemble_instruction(mnemonic, &operands) {
+            Some(instruction) => bytes.extend(instruction),
+            None => panic!("Can't assemble {line}"),
+        }
+    }
+    bytes
+}
+
+fn assemble_instruction(mnemonic: &str, operands: &[&str]) -> Option<Vec<u8>> {
+    let r8 = |operand: &str| R8.iter().position(|r| *r == operand).map(|i| i as u8);
+    let r16 = |operand: &str| R16.iter().position(|r| *r == operand).map(|i| i as u8);
+    let bytes: Vec<u8> = match (mnemonic, operands) {
+        ("NOP", []) => vec![0x00],
+        ("HALT", []) => vec![0x76],
+        ("DI", []) => vec![0xF3],
+        ("EI", []) => vec![0xFB],
+        ("RET", []) => vec![0xC9],
+        ("LD", ["A", address]) if address.starts_with('(') && r8(address).is_none() => {
+            let [low, high] = number(address.trim_matches(['(', ')']))?.to_le_bytes();
+            vec![0xFA, low, high]
+        }
+        ("LD", [address, "A"]) if address.starts_with('(') && r8(address).is_none() => {
+            let [low, high] = number(address.trim_matches(['(', ')']))?.to_le_bytes();
+            vec![0xEA, low, high]
+        }
+        ("LD", [to, from]) => match (r8(to), r8(from), r16(to)) {
+            (Some(6), Some(6), _) => return None,
+            (Some(to), Some(from), _) => vec![0x40 | to << 3 | from],
+            (Some(to), None, _) => vec![0x06 | to << 3, number(from)? as u8],
+            (None, None, Some(to)) => {
+                let [low, high] = number(from)?.to_le_bytes();
+                vec![0x01 | to << 4, low, high]
+            }
+            _ => return None,
+        },
+        ("INC", [operand]) => match (r8(operand), r16(operand)) {
+            (Some(r), _) => vec![0x04 | r << 3],
+            (None, Some(rr)) => vec![0x03 | rr << 4],
+            _ => return None,
+        },
+        ("DEC", [operand]) => match (r8(operand), r16(operand)) {
+            (Some(r), _) => vec![0x05 | r << 3],
+            (None, Some(rr)) => vec![0x0B | rr << 4],
+            _ => return None,
+        },
+        ("JP", [address]) => {
+            let [low, high] = number(address)?.to_le_bytes();
+            vec![0xC3, low, high]
+        }
+        ("CALL", [address]) => {
+            let [low, high] = number(address)?.to_le_bytes();
+            vec![0xCD, low, high]
+        }
+        ("JR", [offset]) => vec![0x18, signed(offset)?],
+        ("PUSH" | "POP", [operand]) => {
+            let rr: u8 = R16_STACK.iter().position(|r| r == operand)? as u8;
+            let base: u8 = if mnemonic == "PUSH" { 0xC5 } else { 0xC1 };
+            vec![base | rr << 4]
+        }
+        (mnemonic, operands) if ALU.contains(&mnemonic) => {
+            let op: u8 = ALU.iter().position(|alu| *alu == mnemonic)? as u8;
+            // ADD A,B and ADD B are both accepted
+            let operand: &str = match operands {
+                ["A", operand] | [operand] => operand,
+                _ => return None,
+            };
+            match r8(operand) {
+                Some(r) => vec![0x80 | op << 3 | r],
+                None => vec![0xC6 | op << 3, number(operand)? as u8],
+            }
+        }
+        _ => return None,
+    };
+    Some(bytes)
+}
+
+fn number(operand: &str) -> Option<u16> {
+    if let Some(hex) = operand.strip_prefix('$').or(operand.strip_prefix("0X")) {
+        u16::from_str_radix(hex, 16).ok()
+    } else {
+        operand.parse().ok()
+    }
+}
+
+fn signed(operand: &str) -> Option<u8> {
+    match operand.strip_prefix('-') {
+        Some(magnitude) => Some((-(number(magnitude)? as i16)) as i8 as u8),
+        None => Some(number(operand)? as i8 as u8),
+    }
+}
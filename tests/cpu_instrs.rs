@@ -0,0 +1,17 @@
+use rgb_emu::{TestResult, run_test_rom};
+use std::path::PathBuf;
+
+// Blargg's cpu_instrs.gb can't be checked in, so point CPU_INSTRS_ROM at a copy to run it:
+// CPU_INSTRS_ROM=path/to/cpu_instrs.gb cargo test --release --test cpu_instrs -- --ignored
+// Ignored because it can't pass yet: the CB prefixed opcodes, LDH, DAA and the A register rotates
+// aren't implemented, and the ROM stops on the first of them.
+#[test]
+#[ignore]
+fn blargg_cpu_instrs_passes() {
+    let Some(path) = std::env::var_os("CPU_INSTRS_ROM").map(PathBuf::from) else {
+        eprintln!("CPU_INSTRS_ROM is not set, skipping cpu_instrs");
+        return;
+    };
+    let rom: Vec<u8> = std::fs::read(&path).expect("CPU_INSTRS_ROM is readable");
+    assert_eq!(run_test_rom(rom), TestResult::Pass);
+}
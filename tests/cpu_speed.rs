@@ -0,0 +1,39 @@
+mod common;
+
+use common::idle_emulator;
+use rgb_emu::Emulator;
+use std::cell::Cell;
+use std::rc::Rc;
+
+// Counts the JR -2 loop's instructions by the fetches of its opcode
+fn instructions_per_frame(percent: u32) -> u32 {
+    let mut emulator: Emulator = idle_emulator();
+    emulator.set_cpu_speed(percent);
+    let count: Rc<Cell<u32>> = Rc::new(Cell::new(0));
+    let fetches: Rc<Cell<u32>> = count.clone();
+    emulator.set_on_read(Some(Box::new(move |addr, _| {
+        if addr == 0x0100 {
+            fetches.set(fetches.get() + 1);
+        }
+    })));
+    emulator.run_frame();
+    count.get()
+}
+
+#[test]
+fn cpu_speed_scales_instructions_per_frame() {
+    // A 12 cycle JR in a 70224 cycle frame
+    assert_eq!(instructions_per_frame(100), 70224 / 12);
+    assert_eq!(instructions_per_frame(200), 70224 / 6);
+    assert_eq!(instructions_per_frame(50), 70224 / 24);
+}
+
+#[test]
+fn speed_round_trips() {
+    let mut emulator: Emulator = Emulator::new(vec![0x00; 0x8000]).expect("valid test ROM");
+    assert_eq!(emulator.cpu_speed(), 100);
+    emulator.set_cpu_speed(150);
+    assert_eq!(emulator.cpu_speed(), 150);
+    emulator.set_cpu_speed(100);
+    assert_eq!(emulator.cpu_speed(), 100);
+}
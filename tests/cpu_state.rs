@@ -0,0 +1,70 @@
+mod common;
+
+use common::{assemble, program_cpu, program_emulator};
+use rgb_emu::{Cpu, CpuState, Emulator};
+
+#[test]
+fn snapshot_reflects_registers_after_a_few_instructions() {
+    let mut cpu: Cpu = program_cpu(&assemble(
+        "LD BC,$1234; LD DE,$5678; LD HL,$9ABC; LD A,$0F; ADD A,1; EI; NOP",
+    ));
+    cpu.run_for(7);
+    let state: CpuState = cpu.snapshot();
+    assert_eq!(
+        (
+            state.a, state.b, state.c, state.d, state.e, state.h, state.l
+        ),
+        (0x10, 0x12, 0x34, 0x56, 0x78, 0x9A, 0xBC)
+    );
+    assert_eq!((state.pc, state.sp), (0xC00F, 0xFFFE));
+    assert_eq!(state.f, 0x20);
+    assert_eq!(
+        (state.zero, state.subtract, state.half_carry, state.carry),
+        (false, false, true, false)
+    );
+    assert!(state.ime);
+    assert!(!state.halted);
+}
+
+#[test]
+fn halt_sleeps_until_an_enabled_interrupt_is_requested() {
+    // Enable the timer interrupt, then EI; HALT
+    let mut emulator: Emulator = program_emulator(&assemble("LD A,4; LD ($FFFF),A; EI; HALT; NOP"));
+    emulator.write_byte(0xFF0F, 0x00);
+    for _ in 0..4 {
+        emulator.step();
+    }
+    let state: CpuState = emulator.cpu().snapshot();
+    assert!(state.halted);
+    assert_eq!(state.pc, 0x0107);
+    for _ in 0..10 {
+        emulator.step();
+    }
+    assert_eq!(emulator.cpu().snapshot().pc, 0x0107);
+
+    emulator.write_byte(0xFF0F, 0x04);
+    emulator.step();
+    let state: CpuState = emulator.cpu().snapshot();
+    assert!(!state.halted);
+    assert_eq!(state.pc, 0x0050);
+    // The handler returns to the instruction after HALT
+    assert_eq!(emulator.read(state.sp), 0x07);
+    assert_eq!(emulator.read(state.sp + 1), 0x01);
+}
+
+#[test]
+fn halt_with_ime_clear_wakes_without_servicing_the_interrupt() {
+    let mut emulator: Emulator =
+        program_emulator(&assemble("LD A,4; LD ($FFFF),A; HALT; NOP; NOP"));
+    emulator.write_byte(0xFF0F, 0x00);
+    for _ in 0..3 {
+        emulator.step();
+    }
+    assert!(emulator.cpu().snapshot().halted);
+    emulator.write_byte(0xFF0F, 0x04);
+    emulator.step();
+    let state: CpuState = emulator.cpu().snapshot();
+    assert!(!state.halted);
+    assert_eq!(state.pc, 0x0107);
+    assert_eq!(emulator.read(0xFF0F) & 0x04, 0x04);
+}
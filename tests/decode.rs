@@ -0,0 +1,113 @@
+mod common;
+
+use common::{assemble, program_cpu, program_emulator};
+use rgb_emu::{Cpu, CpuState, Emulator};
+
+// B=0x11, C=0x22, D=0x33, E=0x44, HL=0xC100, A=0x77 and 0x66 at (HL)
+const SETUP: &str = "LD B,$11; LD C,$22; LD D,$33; LD E,$44; LD HL,$C100; LD (HL),$66; LD A,$77";
+const SETUP_STEPS: u32 = 7;
+
+fn run_after_setup(op: u8) -> Emulator {
+    let mut program: Vec<u8> = assemble(SETUP);
+    program.push(op);
+    let mut emulator: Emulator = program_emulator(&program);
+    for _ in 0..SETUP_STEPS + 1 {
+        emulator.step();
+    }
+    emulator
+}
+
+fn r8(emulator: &Emulator, index: u8) -> u8 {
+    let state: CpuState = emulator.cpu().snapshot();
+    match index {
+        0 => state.b,
+        1 => state.c,
+        2 => state.d,
+        3 => state.e,
+        4 => state.h,
+        5 => state.l,
+        6 => emulator.read(0xC100),
+        _ => state.a,
+    }
+}
+
+#[test]
+fn ld_block_copies_the_source_field_into_the_destination_field() {
+    let before: Emulator = run_after_setup(0x00);
+    for op in 0x40..=0x7Fu8 {
+        if op == 0x76 {
+            continue;
+        }
+        let after: Emulator = run_after_setup(op);
+        let dest: u8 = (op >> 3) & 0x07;
+        let source: u8 = op & 0x07;
+        assert_eq!(r8(&after, dest), r8(&before, source), "opcode {op:02X}");
+        for other in (0..8).filter(|r| *r != dest) {
+            assert_eq!(r8(&after, other), r8(&before, other), "opcode {op:02X}");
+        }
+    }
+}
+
+#[test]
+fn ld_block_spot_checks() {
+    // The entries the old table had wired up wrong
+    assert_eq!(run_after_setup(0x41).cpu().snapshot().b, 0x22);
+    assert_eq!(run_after_setup(0x44).cpu().snapshot().b, 0xC1);
+    assert_eq!(run_after_setup(0x48).cpu().snapshot().c, 0x11);
+    assert_eq!(run_after_setup(0x7E).cpu().snapshot().a, 0x66);
+    let emulator: Emulator = run_after_setup(0x70);
+    assert_eq!(emulator.read(0xC100), 0x11);
+    let setup_len: u16 = assemble(SETUP).len() as u16;
+    assert_eq!(emulator.cpu().snapshot().pc, 0x0100 + setup_len + 1);
+}
+
+// (A, operand, carry in) -> (A, F) worked out by hand
+fn alu(op: &str, a: u8, value: u8, carry: bool) -> (u8, u8) {
+    let set_carry: &str = if carry { "LD A,$FF; ADD A,1" } else { "AND A" };
+    let program: Vec<u8> = assemble(&format!("{set_carry}; LD B,{value}; LD A,{a}; {op} A,B"));
+    let mut cpu: Cpu = program_cpu(&program);
+    cpu.run_for(if carry { 5 } else { 4 });
+    let state: CpuState = cpu.snapshot();
+    (state.a, state.f)
+}
+
+#[test]
+fn alu_block_matches_hand_worked_results() {
+    assert_eq!(alu("ADD", 0x3A, 0xC6, false), (0x00, 0b1011_0000));
+    assert_eq!(alu("ADD", 0x08, 0x08, false), (0x10, 0b0010_0000));
+    assert_eq!(alu("ADC", 0xE1, 0x0F, true), (0xF1, 0b0010_0000));
+    assert_eq!(alu("ADC", 0xE1, 0x1E, true), (0x00, 0b1011_0000));
+    assert_eq!(alu("SUB", 0x3E, 0x3E, false), (0x00, 0b1100_0000));
+    assert_eq!(alu("SUB", 0x3E, 0x0F, false), (0x2F, 0b0110_0000));
+    assert_eq!(alu("SUB", 0x3E, 0x40, false), (0xFE, 0b0101_0000));
+    assert_eq!(alu("SBC", 0x3B, 0x2A, true), (0x10, 0b0100_0000));
+    assert_eq!(alu("SBC", 0x3B, 0x4F, true), (0xEB, 0b0111_0000));
+    assert_eq!(alu("AND", 0x5A, 0x3F, false), (0x1A, 0b0010_0000));
+    assert_eq!(alu("AND", 0x5A, 0x00, true), (0x00, 0b1010_0000));
+    assert_eq!(alu("XOR", 0xFF, 0xFF, true), (0x00, 0b1000_0000));
+    assert_eq!(alu("XOR", 0xFF, 0x0F, false), (0xF0, 0b0000_0000));
+    assert_eq!(alu("OR", 0x5A, 0x03, true), (0x5B, 0b0000_0000));
+    assert_eq!(alu("CP", 0x3C, 0x2F, false), (0x3C, 0b0110_0000));
+    assert_eq!(alu("CP", 0x3C, 0x3C, false), (0x3C, 0b1100_0000));
+    assert_eq!(alu("CP", 0x3C, 0x40, false), (0x3C, 0b0101_0000));
+}
+
+#[test]
+fn immediate_alu_ops_decode_like_the_register_forms() {
+    for (index, op) in ["ADD", "ADC", "SUB", "SBC", "AND", "XOR", "OR", "CP"]
+        .iter()
+        .enumerate()
+    {
+        let register: Vec<u8> = assemble(&format!("{op} A,B"));
+        let immediate: Vec<u8> = assemble(&format!("{op} A,$12"));
+        assert_eq!(register, vec![0x80 | (index as u8) << 3]);
+        assert_eq!(immediate, vec![0xC6 | (index as u8) << 3, 0x12]);
+        let mut by_register: Cpu = program_cpu(&assemble(&format!("LD A,$35; LD B,$12; {op} A,B")));
+        by_register.run_for(3);
+        let mut by_immediate: Cpu =
+            program_cpu(&assemble(&format!("LD A,$35; LD B,$12; {op} A,$12")));
+        by_immediate.run_for(3);
+        let (register, immediate) = (by_register.snapshot(), by_immediate.snapshot());
+        assert_eq!((register.a, register.f), (immediate.a, immediate.f), "{op}");
+    }
+}
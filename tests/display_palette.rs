@@ -0,0 +1,28 @@
+mod common;
+
+use common::idle_emulator;
+use rgb_emu::{DisplayPalette, Emulator};
+
+#[test]
+fn one_shade_maps_to_different_rgba_under_two_presets() {
+    let green: [u8; 4] = DisplayPalette::DMG_GREEN.color(3);
+    let gray: [u8; 4] = DisplayPalette::POCKET_GRAY.color(3);
+    assert_eq!(green, [0x0F, 0x38, 0x0F, 0xFF]);
+    assert_eq!(gray, [0x00, 0x00, 0x00, 0xFF]);
+    let custom: DisplayPalette = DisplayPalette::custom([[1, 2, 3, 255]; 4]);
+    assert_eq!(custom.color(3), [1, 2, 3, 255]);
+}
+
+#[test]
+fn emulator_output_follows_the_display_palette() {
+    let mut emulator: Emulator = idle_emulator();
+    emulator.run_frame();
+    emulator.set_display_palette(DisplayPalette::DMG_GREEN);
+    let green: Vec<u8> = emulator.framebuffer_rgba();
+    emulator.set_display_palette(DisplayPalette::POCKET_GRAY);
+    let gray: Vec<u8> = emulator.framebuffer_rgba();
+    // The top left pixel is background color 0, shade 0 under the post-boot BGP
+    assert_eq!(&green[0..4], &DisplayPalette::DMG_GREEN.color(0));
+    assert_eq!(&gray[0..4], &DisplayPalette::POCKET_GRAY.color(0));
+    assert_ne!(green, gray);
+}
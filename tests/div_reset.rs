@@ -0,0 +1,37 @@
+use rgb_emu::{IoDevice, Timer};
+
+fn tima(timer: &Timer) -> u8 {
+    timer.read(0xFF05)
+}
+
+#[test]
+fn div_reset_with_the_selected_bit_high_ticks_tima() {
+    let mut timer: Timer = Timer::new();
+    // 262144 Hz follows divider bit 3, which is high from cycle 8 to 15
+    timer.write(0xFF07, 0x05);
+    timer.step(8);
+    assert_eq!(tima(&timer), 0);
+    timer.write(0xFF04, 0x00);
+    assert_eq!(tima(&timer), 1);
+    assert_eq!(timer.read(0xFF04), 0);
+    // The divider starts over, so the next regular tick is a full period away
+    timer.step(15);
+    assert_eq!(tima(&timer), 1);
+    timer.step(1);
+    assert_eq!(tima(&timer), 2);
+}
+
+#[test]
+fn div_reset_with_the_selected_bit_low_does_not_tick() {
+    let mut timer: Timer = Timer::new();
+    timer.write(0xFF07, 0x05);
+    timer.step(4);
+    timer.write(0xFF04, 0x00);
+    assert_eq!(tima(&timer), 0);
+    // Nor does it while the timer is disabled
+    let mut timer: Timer = Timer::new();
+    timer.write(0xFF07, 0x01);
+    timer.step(8);
+    timer.write(0xFF04, 0x00);
+    assert_eq!(tima(&timer), 0);
+}
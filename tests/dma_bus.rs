@@ -0,0 +1,44 @@
+use rgb_emu::{Cpu, CpuState, MemBus, Rom};
+
+mod common;
+
+// Runs the program from HRAM like a real DMA routine, with 0x11 at 0xC000, 0x99 in the DMA source
+// at 0xC100 and 0x77 at 0xFFF0
+fn hram_cpu(program: &[u8]) -> Cpu {
+    let rom: Rom = Rom::from_bytes(vec![0x00; 0x8000]).expect("valid test ROM");
+    let mut bus: MemBus = MemBus::new(rom).expect("supported test mapper");
+    bus.write_byte(0xC000, 0x11);
+    for addr in 0xC100..0xC1A0 {
+        bus.write_byte(addr, 0x99);
+    }
+    bus.write_byte(0xFFF0, 0x77);
+    common::load_program(&mut bus, 0xFF80, program);
+    Cpu::with_entry_point(bus, 0xFF80, 0xFFFE)
+}
+
+#[test]
+fn wram_reads_see_the_dma_byte_while_hram_still_works() {
+    let program: Vec<u8> = common::assemble(
+        "LD A,$C1; LD ($FF46),A; LD HL,$C000; LD B,(HL); LD HL,$FFF0; LD C,(HL); JR -2",
+    );
+    let mut cpu: Cpu = hram_cpu(&program);
+    for _ in 0..6 {
+        cpu.step();
+    }
+    let state: CpuState = cpu.snapshot();
+    assert_eq!(state.b, 0x99, "WRAM read during DMA");
+    assert_eq!(state.c, 0x77, "HRAM read during DMA");
+}
+
+#[test]
+fn wram_is_readable_again_once_the_dma_finishes() {
+    // Waits 64 turns of DEC D; JR NZ,-3 (16 cycles each), longer than the 640 cycle transfer
+    let mut program: Vec<u8> = common::assemble("LD A,$C1; LD ($FF46),A; LD D,64; DEC D");
+    program.extend_from_slice(&[0x20, 0xFD]);
+    program.extend(common::assemble("LD HL,$C000; LD B,(HL); JR -2"));
+    let mut cpu: Cpu = hram_cpu(&program);
+    for _ in 0..3 + 64 * 2 + 2 {
+        cpu.step();
+    }
+    assert_eq!(cpu.snapshot().b, 0x11);
+}
@@ -0,0 +1,40 @@
+use rgb_emu::Emulator;
+
+// Starts at the entry point with a VBlank interrupt already requested and enabled
+fn emulator_with_pending_vblank(code: &[u8]) -> Emulator {
+    let mut data: Vec<u8> = vec![0x00; 0x8000];
+    data[0x0100..0x0100 + code.len()].copy_from_slice(code);
+    let mut emulator: Emulator = Emulator::new(data).expect("valid test ROM");
+    emulator.write_byte(0xFFFF, 0x01);
+    emulator.write_byte(0xFF0F, 0x01);
+    emulator
+}
+
+fn pc(emulator: &Emulator) -> u16 {
+    emulator.cpu().snapshot().pc
+}
+
+#[test]
+fn ei_takes_effect_after_the_next_instruction() {
+    // EI; NOP; NOP
+    let mut emulator: Emulator = emulator_with_pending_vblank(&[0xFB, 0x00, 0x00]);
+    emulator.step();
+    assert_eq!(pc(&emulator), 0x0101);
+    // The pending interrupt waits for the instruction after EI
+    emulator.step();
+    assert_eq!(pc(&emulator), 0x0102);
+    emulator.step();
+    assert_eq!(pc(&emulator), 0x0040);
+    assert_eq!(emulator.read(0xFF0F) & 0x01, 0x00);
+}
+
+#[test]
+fn ei_then_di_never_services_the_interrupt() {
+    // EI; DI; NOP; NOP
+    let mut emulator: Emulator = emulator_with_pending_vblank(&[0xFB, 0xF3, 0x00, 0x00]);
+    for _ in 0..4 {
+        emulator.step();
+    }
+    assert_eq!(pc(&emulator), 0x0104);
+    assert_eq!(emulator.read(0xFF0F) & 0x01, 0x01);
+}
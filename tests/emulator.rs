@@ -0,0 +1,46 @@
+mod common;
+
+use common::{assemble, program_emulator};
+use rgb_emu::{Button, Emulator, SCREEN_HEIGHT, SCREEN_WIDTH};
+
+fn counter_emulator() -> Emulator {
+    // Leaves a marker in WRAM, then spins
+    program_emulator(&assemble("LD A,$42; LD ($C000),A; JR -2"))
+}
+
+#[test]
+fn emulator_from_bytes_runs_a_frame() {
+    let mut emulator: Emulator = counter_emulator();
+    emulator.run_frame();
+    assert_eq!(emulator.read(0xC000), 0x42);
+    assert_eq!(emulator.framebuffer().len(), SCREEN_WIDTH * SCREEN_HEIGHT);
+    // Parked on the JR -2 at the end of the program
+    assert_eq!(emulator.cpu().snapshot().pc, 0x0105);
+}
+
+#[test]
+fn load_state_returns_to_the_saved_machine() {
+    let mut emulator: Emulator = counter_emulator();
+    emulator.run_frame();
+    let state: Vec<u8> = emulator.save_state().expect("state encodes");
+    emulator.write_byte(0xC000, 0x00);
+    emulator.write_byte(0xFF80, 0x99);
+    emulator.run_frame();
+    emulator
+        .load_state(&state)
+        .expect("state from the same ROM");
+    assert_eq!(emulator.read(0xC000), 0x42);
+    assert_eq!(emulator.read(0xFF80), 0x00);
+}
+
+#[test]
+fn set_button_shows_up_in_the_joypad_register() {
+    let mut emulator: Emulator = counter_emulator();
+    // Select the action buttons, nothing pressed reads as 1s
+    emulator.write_byte(0xFF00, 0x10);
+    assert_eq!(emulator.read(0xFF00) & 0x0F, 0x0F);
+    emulator.set_button(Button::A, true);
+    assert_eq!(emulator.read(0xFF00) & 0x0F, 0x0E);
+    emulator.set_button(Button::A, false);
+    assert_eq!(emulator.read(0xFF00) & 0x0F, 0x0F);
+}
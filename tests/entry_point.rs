@@ -0,0 +1,38 @@
+mod common;
+
+use common::{assemble, idle_emulator, load_program};
+use rgb_emu::{Cpu, CpuState, Emulator, MemBus, Rom};
+
+#[test]
+fn cpu_runs_a_fragment_from_wram() {
+    let rom: Rom = Rom::from_bytes(vec![0x00; 0x8000]).expect("valid test ROM");
+    let mut bus: MemBus = MemBus::new(rom).expect("supported test mapper");
+    load_program(&mut bus, 0xC000, &assemble("LD A,$12; LD B,A; INC B"));
+    let mut cpu: Cpu = Cpu::with_entry_point(bus, 0xC000, 0xDFFE);
+    assert_eq!((cpu.snapshot().pc, cpu.snapshot().sp), (0xC000, 0xDFFE));
+
+    cpu.step();
+    assert_eq!((cpu.snapshot().pc, cpu.snapshot().a), (0xC002, 0x12));
+    cpu.step();
+    assert_eq!((cpu.snapshot().pc, cpu.snapshot().b), (0xC003, 0x12));
+    cpu.step();
+    let state: CpuState = cpu.snapshot();
+    assert_eq!((state.pc, state.b, state.sp), (0xC004, 0x13, 0xDFFE));
+}
+
+#[test]
+fn emulator_entry_point_overrides_the_cartridge_start() {
+    let mut emulator: Emulator = idle_emulator();
+    for (i, byte) in assemble("LD A,$12; LD B,A; INC B").iter().enumerate() {
+        emulator.write_byte(0xC000 + i as u16, *byte);
+    }
+    emulator.set_entry_point(0xC000, 0xDFFE);
+    for _ in 0..3 {
+        emulator.step();
+    }
+    let state: CpuState = emulator.cpu().snapshot();
+    assert_eq!(
+        (state.pc, state.a, state.b, state.sp),
+        (0xC004, 0x12, 0x13, 0xDFFE)
+    );
+}
@@ -0,0 +1,46 @@
+use rgb_emu::{Emulator, Error, RomError, UnimplementedPolicy};
+
+fn rom_with(entry: u8) -> Vec<u8> {
+    let mut data: Vec<u8> = vec![0x00; 0x8000];
+    data[0x0100] = entry;
+    data
+}
+
+#[test]
+fn bad_header_sizes_are_errors() {
+    let mut data: Vec<u8> = rom_with(0x00);
+    data[0x0148] = 0x20;
+    let error: Error = Emulator::new(data).err().expect("invalid ROM size");
+    assert!(matches!(error, Error::Rom(RomError::InvalidRomSize(0x20))));
+    assert_eq!(error.to_string(), "Invalid ROM size in header: 20");
+    let mut data: Vec<u8> = rom_with(0x00);
+    data[0x0149] = 0x09;
+    let error: Error = Emulator::new(data).err().expect("invalid RAM size");
+    assert_eq!(error.to_string(), "Invalid RAM size in header: 09");
+}
+
+#[test]
+fn stop_policy_reports_the_opcode() {
+    // 0xD3 isn't an SM83 opcode, so it stays unimplemented
+    let mut emulator: Emulator = Emulator::new(rom_with(0xD3)).expect("valid test ROM");
+    emulator.set_unimplemented_policy(UnimplementedPolicy::Stop);
+    let error: Error = emulator.try_run_frame().expect_err("unimplemented opcode");
+    assert!(matches!(
+        error,
+        Error::UnimplementedOpcode {
+            opcode: 0xD3,
+            pc: 0x0100
+        }
+    ));
+    assert_eq!(error.to_string(), "Opcode D3 at 0100 is not implemented");
+    // The CPU stays on the opcode
+    assert!(emulator.try_run_frame().is_err());
+    assert_eq!(emulator.cpu().snapshot().pc, 0x0100);
+}
+
+#[test]
+fn cheat_errors_carry_their_message() {
+    let mut emulator: Emulator = Emulator::new(rom_with(0x00)).expect("valid test ROM");
+    let error: Error = emulator.add_cheat("XYZ").expect_err("malformed cheat");
+    assert_eq!(error.to_string(), "Malformed cheat code XYZ");
+}
@@ -0,0 +1,33 @@
+use rgb_emu::{Cpu, Flag, MemBus, Rom};
+
+// A CPU about to run ADC A,$00 with A at its post boot 0x01
+fn adc_cpu() -> Cpu {
+    let mut data: Vec<u8> = vec![0x00; 0x8000];
+    data[0x100..0x102].copy_from_slice(&[0xCE, 0x00]);
+    let rom: Rom = Rom::from_bytes(data).expect("valid test ROM");
+    Cpu::new(MemBus::new(rom).expect("supported test mapper"))
+}
+
+#[test]
+fn set_flag_changes_only_that_flag() {
+    let mut cpu: Cpu = adc_cpu();
+    cpu.set_flag(Flag::Z, false);
+    cpu.set_flag(Flag::N, true);
+    assert!(!cpu.flag(Flag::Z));
+    assert!(cpu.flag(Flag::N));
+    assert_eq!(cpu.snapshot().f, 0b0111_0000);
+}
+
+#[test]
+fn adc_adds_the_carry_flag() {
+    let mut cpu: Cpu = adc_cpu();
+    cpu.set_flag(Flag::C, true);
+    cpu.step();
+    assert_eq!(cpu.snapshot().a, 0x02);
+    assert!(!cpu.flag(Flag::C));
+
+    let mut cpu: Cpu = adc_cpu();
+    cpu.set_flag(Flag::C, false);
+    cpu.step();
+    assert_eq!(cpu.snapshot().a, 0x01);
+}
@@ -0,0 +1,47 @@
+use rgb_emu::{IoDevice, Oam, Ppu, Vram};
+
+const LINE_DOTS: u32 = 456;
+const VBLANK_INTERRUPT: u8 = 0x01;
+const STAT_INTERRUPT: u8 = 0x02;
+
+// Tile 0 is solid color 1 and fills the map. BGP decides which shade that shows as.
+fn solid_vram() -> Vram {
+    let mut vram: Vram = Vram::new();
+    for row in 0..8u16 {
+        vram.set_value(0x8000 + row * 2, 0xFF);
+    }
+    vram
+}
+
+// Draws three frames, with color 1 as shade 1, 2 and 3 in turn. Returns the top left shade and the
+// number of VBlank and STAT interrupts of each frame.
+fn three_frames(skip: u32) -> Vec<(u8, u32, u32)> {
+    let mut vram: Vram = solid_vram();
+    let oam: Oam = Oam::new();
+    let mut ppu: Ppu = Ppu::new();
+    ppu.set_frame_skip(skip);
+    // HBlank STAT interrupt on
+    ppu.write(0xFF41, 0b0000_1000);
+    ppu.write(0xFF40, 0b1001_0001);
+    [0b0000_0100, 0b0000_1000, 0b0000_1100]
+        .iter()
+        .map(|bgp| {
+            ppu.write(0xFF47, *bgp);
+            let (mut vblanks, mut stats): (u32, u32) = (0, 0);
+            for _ in 0..154 {
+                let interrupts: u8 = ppu.step(LINE_DOTS, &mut vram, &oam);
+                vblanks += (interrupts & VBLANK_INTERRUPT != 0) as u32;
+                stats += (interrupts & STAT_INTERRUPT != 0) as u32;
+            }
+            (ppu.framebuffer()[0], vblanks, stats)
+        })
+        .collect()
+}
+
+#[test]
+fn frame_skip_1_only_draws_every_other_frame() {
+    // One VBlank and 144 HBlank interrupts a frame either way
+    assert_eq!(three_frames(0), vec![(1, 1, 144), (2, 1, 144), (3, 1, 144)]);
+    // The second frame is skipped, so the first one stays on screen
+    assert_eq!(three_frames(1), vec![(1, 1, 144), (1, 1, 144), (3, 1, 144)]);
+}
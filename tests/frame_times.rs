@@ -0,0 +1,23 @@
+use rgb_emu::{FRAMES_PER_SECOND, FrameTimes};
+
+#[test]
+fn keeps_only_the_most_recent_frames() {
+    let mut times: FrameTimes = FrameTimes::new(4);
+    assert!(times.is_empty());
+    for timestamp in [0.0, 1.0, 3.0, 6.0, 10.0, 15.0] {
+        times.push(timestamp);
+    }
+    assert_eq!(times.len(), 4);
+    assert_eq!(times.durations(), vec![3.0, 4.0, 5.0]);
+}
+
+#[test]
+fn stats_come_from_the_buffered_frames() {
+    let mut times: FrameTimes = FrameTimes::new(120);
+    for frame in 0..200 {
+        times.push(frame as f64 / FRAMES_PER_SECOND);
+    }
+    assert_eq!(times.len(), 120);
+    assert!((times.stats().speed - 100.0).abs() < 0.001);
+    assert!((times.stats().fps - FRAMES_PER_SECOND).abs() < 0.001);
+}
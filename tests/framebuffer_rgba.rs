@@ -0,0 +1,46 @@
+use rgb_emu::{DisplayPalette, IoDevice, Oam, Ppu, Vram};
+
+const FRAME_DOTS: u32 = 456 * 154;
+
+const PALETTE: DisplayPalette = DisplayPalette {
+    colors: [
+        [0x10, 0x20, 0x30, 0xFF],
+        [0x40, 0x50, 0x60, 0xFF],
+        [0x70, 0x80, 0x90, 0xFF],
+        [0xA0, 0xB0, 0xC0, 0xFF],
+    ],
+};
+
+// Every background tile is tile 0, whose rows run through color indices 0, 1, 2, 3 twice
+fn rendered_ppu(bgp: u8) -> Ppu {
+    let mut vram: Vram = Vram::new();
+    for row in 0..8u16 {
+        vram.set_value(0x8000 + row * 2, 0b0101_0101);
+        vram.set_value(0x8001 + row * 2, 0b0011_0011);
+    }
+    let mut ppu: Ppu = Ppu::new();
+    ppu.write(0xFF47, bgp);
+    ppu.write(0xFF40, 0b1001_0001);
+    ppu.step(FRAME_DOTS * 2, &mut vram, &Oam::new());
+    ppu
+}
+
+#[test]
+fn known_indices_produce_the_palette_colors_in_rgba_order() {
+    let ppu: Ppu = rendered_ppu(0b1110_0100);
+    let rgba: Vec<u8> = ppu.framebuffer_rgba(&PALETTE);
+    assert_eq!(rgba.len(), 160 * 144 * 4);
+    for (i, shade) in ppu.framebuffer().iter().enumerate() {
+        assert_eq!(*shade as usize, i % 160 % 4);
+        assert_eq!(rgba[i * 4..i * 4 + 4], PALETTE.colors[*shade as usize]);
+    }
+}
+
+#[test]
+fn bgp_is_applied_before_the_display_palette() {
+    // Reversed BGP: index 0 shows shade 3 and so on
+    let ppu: Ppu = rendered_ppu(0b0001_1011);
+    let rgba: Vec<u8> = ppu.framebuffer_rgba(&PALETTE);
+    let expected: Vec<u8> = [3, 2, 1, 0].map(|shade| PALETTE.colors[shade]).concat();
+    assert_eq!(rgba[0..16], expected);
+}
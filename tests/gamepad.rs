@@ -0,0 +1,31 @@
+// Only built with the gamepad feature, which needs libudev on Linux
+#![cfg(feature = "gamepad")]
+
+use rgb_emu::{Button, Emulator, gamepad_button};
+
+mod common;
+
+#[test]
+fn controller_buttons_map_by_position() {
+    assert_eq!(gamepad_button(gilrs::Button::DPadUp), Some(Button::Up));
+    assert_eq!(gamepad_button(gilrs::Button::East), Some(Button::A));
+    assert_eq!(gamepad_button(gilrs::Button::South), Some(Button::B));
+    assert_eq!(gamepad_button(gilrs::Button::Start), Some(Button::Start));
+    assert_eq!(gamepad_button(gilrs::Button::LeftTrigger), None);
+}
+
+#[test]
+fn a_mapped_press_clears_its_joypad_bit_and_requests_the_interrupt() {
+    let mut emulator: Emulator = common::idle_emulator();
+    emulator.write_byte(0xFF0F, 0x00);
+    // Select the action buttons, where A is bit 0 and Start bit 3
+    emulator.write_byte(0xFF00, 0x10);
+    let button: Button = gamepad_button(gilrs::Button::East).expect("East is mapped");
+    emulator.set_button(button, true);
+    assert_eq!(emulator.read(0xFF00) & 0x0F, 0b1110);
+    // The joypad hands its interrupt to IF on the next tick
+    emulator.step();
+    assert_eq!(emulator.read(0xFF0F) & 0x10, 0x10);
+    emulator.set_button(button, false);
+    assert_eq!(emulator.read(0xFF00) & 0x0F, 0b1111);
+}
@@ -0,0 +1,30 @@
+mod common;
+
+use common::{assemble, program_emulator};
+use rgb_emu::{Emulator, MemBus, Rom};
+
+#[test]
+fn hram_round_trips_through_the_bus() {
+    let rom: Rom = Rom::from_bytes(vec![0x00; 0x8000]).expect("valid test ROM");
+    let mut bus: MemBus = MemBus::new(rom).expect("supported test mapper");
+    bus.write_byte(0xFF85, 0x5A);
+    assert_eq!(bus.read(0xFF85), 0x5A);
+    // Neighbouring cells and IE are separate
+    assert_eq!(bus.read(0xFF84), 0x00);
+    assert_eq!(bus.read(0xFF86), 0x00);
+    bus.write_byte(0xFFFE, 0xA5);
+    bus.write_byte(0xFFFF, 0x01);
+    assert_eq!(bus.read(0xFFFE), 0xA5);
+}
+
+#[test]
+fn cpu_stores_and_loads_hram() {
+    let mut emulator: Emulator = program_emulator(&assemble(
+        "LD A,$5A; LD ($FF85),A; LD A,0; LD HL,$FF85; LD B,(HL)",
+    ));
+    for _ in 0..5 {
+        emulator.step();
+    }
+    assert_eq!(emulator.read(0xFF85), 0x5A);
+    assert_eq!(emulator.cpu().snapshot().b, 0x5A);
+}
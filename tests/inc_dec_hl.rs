@@ -0,0 +1,38 @@
+mod common;
+
+use common::{assemble, program_cpu};
+use rgb_emu::{Cpu, CpuState, Flag};
+
+#[test]
+fn inc_hl_sets_half_carry_on_0x0f() {
+    let mut cpu: Cpu = program_cpu(&assemble("LD HL,$C100; LD (HL),$0F; INC (HL); LD B,(HL)"));
+    cpu.run_for(2);
+    cpu.set_flag(Flag::C, true);
+    assert_eq!(cpu.step(), 12);
+    cpu.step();
+    let state: CpuState = cpu.snapshot();
+    assert_eq!(state.b, 0x10);
+    assert!(state.half_carry);
+    assert!(!state.zero);
+    assert!(!state.subtract);
+    // Carry is untouched
+    assert!(state.carry);
+}
+
+#[test]
+fn dec_hl_borrows_from_bit_4_and_sets_zero() {
+    let mut cpu: Cpu = program_cpu(&assemble("LD HL,$C100; LD (HL),$10; DEC (HL); LD B,(HL)"));
+    cpu.run_for(2);
+    assert_eq!(cpu.step(), 12);
+    cpu.step();
+    let state: CpuState = cpu.snapshot();
+    assert_eq!(state.b, 0x0F);
+    assert!(state.half_carry);
+    assert!(state.subtract);
+
+    let mut cpu: Cpu = program_cpu(&assemble("LD HL,$C100; LD (HL),$01; DEC (HL); LD B,(HL)"));
+    cpu.run_for(4);
+    let state: CpuState = cpu.snapshot();
+    assert_eq!(state.b, 0x00);
+    assert!(state.zero);
+}
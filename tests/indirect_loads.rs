@@ -0,0 +1,32 @@
+use rgb_emu::{CpuState, Emulator};
+
+mod common;
+
+#[test]
+fn ld_a_bc_and_ld_a_de_read_wram() {
+    // LD BC,$C123; LD A,(BC); LD H,A; LD DE,$C200; LD A,(DE)
+    let mut program: Vec<u8> = common::assemble("LD BC,$C123");
+    program.push(0x0A);
+    program.extend(common::assemble("LD H,A; LD DE,$C200"));
+    program.push(0x1A);
+    let mut emulator: Emulator = common::program_emulator(&program);
+    emulator.write_byte(0xC123, 0x5A);
+    emulator.write_byte(0xC200, 0xA5);
+    for _ in 0..3 {
+        emulator.step();
+    }
+    assert_eq!(emulator.cpu().snapshot().h, 0x5A);
+    emulator.step();
+    emulator.step();
+    let cpu: CpuState = emulator.cpu().snapshot();
+    assert_eq!(cpu.a, 0xA5);
+    assert_eq!(cpu.pc, 0x0109);
+}
+
+#[test]
+fn indirect_loads_take_8_cycles() {
+    for opcode in [0x0A, 0x1A] {
+        let mut emulator: Emulator = common::program_emulator(&[opcode]);
+        assert_eq!(emulator.step(), 8, "{opcode:02X}");
+    }
+}
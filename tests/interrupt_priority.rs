@@ -0,0 +1,50 @@
+use rgb_emu::Emulator;
+
+mod common;
+
+// The program at the entry point and RETI at every interrupt vector
+fn emulator_with_handlers(program: &str) -> Emulator {
+    let mut data: Vec<u8> = vec![0x00; 0x8000];
+    for vector in [0x40, 0x48, 0x50, 0x58, 0x60] {
+        data[vector] = 0xD9;
+    }
+    let code: Vec<u8> = common::assemble(program);
+    data[0x0100..0x0100 + code.len()].copy_from_slice(&code);
+    Emulator::new(data).expect("valid test ROM")
+}
+
+#[test]
+fn vblank_is_serviced_before_the_timer_and_joypad() {
+    let mut emulator: Emulator = emulator_with_handlers("EI; NOP; NOP; NOP");
+    emulator.write_byte(0xFFFF, 0x1F);
+    emulator.write_byte(0xFF0F, 0b1_0101);
+    emulator.step();
+    emulator.step();
+    // Dispatch pushes PC, clears the IF bit and jumps, 20 cycles in all
+    assert_eq!(emulator.step(), 20);
+    assert_eq!(emulator.cpu().snapshot().pc, 0x0040);
+    assert_eq!(emulator.read(0xFF0F) & 0x1F, 0b1_0100);
+    emulator.step();
+    emulator.step();
+    assert_eq!(emulator.cpu().snapshot().pc, 0x0050);
+    assert_eq!(emulator.read(0xFF0F) & 0x1F, 0b1_0000);
+    emulator.step();
+    emulator.step();
+    assert_eq!(emulator.cpu().snapshot().pc, 0x0060);
+    assert_eq!(emulator.read(0xFF0F) & 0x1F, 0b0_0000);
+}
+
+#[test]
+fn overwriting_ie_during_the_push_cancels_the_dispatch() {
+    // With SP at 0x0000 the high byte of PC (0x01) lands in IE, which leaves only VBlank enabled
+    let mut emulator: Emulator = emulator_with_handlers("LD SP,$0000; EI; NOP; NOP");
+    emulator.write_byte(0xFFFF, 0x04);
+    emulator.write_byte(0xFF0F, 0x04);
+    for _ in 0..3 {
+        emulator.step();
+    }
+    emulator.step();
+    assert_eq!(emulator.cpu().snapshot().pc, 0x0000);
+    assert_eq!(emulator.read(0xFFFF), 0x01);
+    assert_eq!(emulator.read(0xFF0F) & 0x04, 0x04);
+}
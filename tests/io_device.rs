@@ -0,0 +1,54 @@
+mod common;
+
+use common::idle_emulator;
+use rgb_emu::{Emulator, IoDevice};
+use std::cell::Cell;
+use std::rc::Rc;
+
+// Remembers the last write and answers reads with it inverted, so a read can't come from plain memory
+struct MockDevice {
+    last_write: Rc<Cell<u8>>,
+}
+
+impl IoDevice for MockDevice {
+    fn read(&self, _addr: u16) -> u8 {
+        !self.last_write.get()
+    }
+
+    fn write(&mut self, _addr: u16, value: u8) {
+        self.last_write.set(value);
+    }
+}
+
+#[test]
+fn added_device_sees_its_registers() {
+    let mut emulator: Emulator = idle_emulator();
+    let last_write: Rc<Cell<u8>> = Rc::new(Cell::new(0));
+    emulator.add_io_device(
+        0xFF60,
+        0xFF61,
+        Box::new(MockDevice {
+            last_write: last_write.clone(),
+        }),
+    );
+    emulator.write_byte(0xFF61, 0x5A);
+    assert_eq!(last_write.get(), 0x5A);
+    assert_eq!(emulator.read(0xFF60), 0xA5);
+    assert_eq!(emulator.read(0xFF62), 0xFF);
+}
+
+#[test]
+fn built_in_devices_keep_their_registers() {
+    let mut emulator: Emulator = idle_emulator();
+    let last_write: Rc<Cell<u8>> = Rc::new(Cell::new(0));
+    emulator.add_io_device(
+        0xFF47,
+        0xFF47,
+        Box::new(MockDevice {
+            last_write: last_write.clone(),
+        }),
+    );
+    emulator.write_byte(0xFF47, 0x1B);
+    assert_eq!(last_write.get(), 0);
+    assert_eq!(emulator.read(0xFF47), 0x1B);
+}
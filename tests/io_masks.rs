@@ -0,0 +1,50 @@
+mod common;
+
+use common::idle_emulator;
+use rgb_emu::Emulator;
+
+#[test]
+fn unused_bits_read_back_as_one() {
+    let mut emulator: Emulator = idle_emulator();
+    emulator.write_byte(0xFF07, 0x05); // TAC
+    assert_eq!(emulator.read(0xFF07), 0xFD);
+    emulator.write_byte(0xFF0F, 0x00); // IF
+    assert_eq!(emulator.read(0xFF0F), 0xE0);
+    emulator.write_byte(0xFF02, 0x00); // SC
+    assert_eq!(emulator.read(0xFF02), 0x7E);
+    emulator.write_byte(0xFF00, 0x30); // P1 with neither button group selected
+    assert_eq!(emulator.read(0xFF00), 0xFF);
+    assert_eq!(emulator.read(0xFF41) & 0x80, 0x80); // STAT
+}
+
+#[test]
+fn write_only_registers_read_as_ones() {
+    let mut emulator: Emulator = idle_emulator();
+    emulator.write_byte(0xFF13, 0x12); // NR13
+    assert_eq!(emulator.read(0xFF13), 0xFF);
+    emulator.write_byte(0xFF11, 0x85); // NR11, only the duty reads back
+    assert_eq!(emulator.read(0xFF11), 0xBF);
+}
+
+#[test]
+fn read_only_bits_ignore_writes() {
+    let mut emulator: Emulator = idle_emulator();
+    let ly: u8 = emulator.read(0xFF44);
+    emulator.write_byte(0xFF44, ly.wrapping_add(1));
+    assert_eq!(emulator.read(0xFF44), ly);
+    // Mode and coincidence bits of STAT belong to the PPU
+    let stat: u8 = emulator.read(0xFF41);
+    emulator.write_byte(0xFF41, 0x07);
+    assert_eq!(emulator.read(0xFF41) & 0x07, stat & 0x07);
+    // Only the power bit of NR52 is writable
+    emulator.write_byte(0xFF26, 0x8F);
+    assert_eq!(emulator.read(0xFF26) & 0x0F, 0x01);
+}
+
+#[test]
+fn unmapped_registers_read_0xff() {
+    let emulator: Emulator = idle_emulator();
+    assert_eq!(emulator.read(0xFF03), 0xFF);
+    assert_eq!(emulator.read(0xFF4D), 0xFF);
+    assert_eq!(emulator.read(0xFF7F), 0xFF);
+}
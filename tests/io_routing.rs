@@ -0,0 +1,37 @@
+use rgb_emu::{Emulator, IoDevice};
+
+mod common;
+
+#[test]
+fn timer_registers_go_to_the_timer() {
+    let mut emulator: Emulator = common::idle_emulator();
+    emulator.write_byte(0xFF04, 0x00);
+    // DIV counts up every 256 cycles and JR -2 takes 12
+    for _ in 0..64 {
+        emulator.step();
+    }
+    assert_eq!(emulator.read(0xFF04), 3);
+    emulator.write_byte(0xFF06, 0xAB);
+    assert_eq!(emulator.read(0xFF06), 0xAB);
+}
+
+#[test]
+fn ppu_registers_go_to_the_ppu() {
+    let mut emulator: Emulator = common::idle_emulator();
+    emulator.write_byte(0xFF42, 0x37);
+    assert_eq!(emulator.ppu().read(0xFF42), 0x37);
+    for _ in 0..100 {
+        emulator.step();
+    }
+    assert_ne!(emulator.ppu().ly(), 0);
+    assert_eq!(emulator.read(0xFF44), emulator.ppu().ly());
+}
+
+#[test]
+fn unclaimed_registers_are_open_bus() {
+    let mut emulator: Emulator = common::idle_emulator();
+    for addr in [0xFF03, 0xFF08, 0xFF4C, 0xFF7F] {
+        emulator.write_byte(addr, 0x00);
+        assert_eq!(emulator.read(addr), 0xFF, "{addr:04X}");
+    }
+}
@@ -0,0 +1,18 @@
+use rgb_emu::{Cpu, CpuState};
+
+mod common;
+
+#[test]
+fn ld_hl_n8_writes_the_operand_to_wram_at_hl() {
+    // LD HL,$C100; LD (HL),$5A; LD B,(HL)
+    let mut cpu: Cpu = common::program_cpu(&[0x21, 0x00, 0xC1, 0x36, 0x5A, 0x46]);
+    cpu.step();
+    let before: CpuState = cpu.snapshot();
+    assert_eq!(cpu.step(), 12);
+    // PC skips the operand and no flags change
+    let after: CpuState = cpu.snapshot();
+    assert_eq!(after.pc, 0xC005);
+    assert_eq!(after.f, before.f);
+    cpu.step();
+    assert_eq!(cpu.snapshot().b, 0x5A);
+}
@@ -0,0 +1,103 @@
+mod common;
+
+use common::idle_emulator;
+use rgb_emu::{Emulator, LinkPartner, TcpLink};
+use std::net::{TcpListener, TcpStream};
+use std::thread;
+
+// Both ends of a link cable over loopback
+fn linked_pair() -> (TcpLink, TcpLink) {
+    let listener: TcpListener = TcpListener::bind("127.0.0.1:0").expect("bind loopback");
+    let addr: std::net::SocketAddr = listener.local_addr().expect("listener address");
+    let client: thread::JoinHandle<TcpLink> =
+        thread::spawn(move || TcpLink::connect(addr).expect("connect to loopback"));
+    let (stream, _): (TcpStream, _) = listener.accept().expect("accept partner");
+    let server: TcpLink = TcpLink::from_stream(stream).expect("server link");
+    (server, client.join().expect("client thread"))
+}
+
+// Answers transfers until the partner hangs up
+fn echo_partner(mut link: TcpLink, reply: u8) -> thread::JoinHandle<Vec<u8>> {
+    thread::spawn(move || {
+        let mut received: Vec<u8> = Vec::new();
+        while let Ok(byte) = link.poll(reply) {
+            if let Some(byte) = byte {
+                received.push(byte);
+            }
+            thread::yield_now();
+        }
+        received
+    })
+}
+
+#[test]
+fn exchange_swaps_one_byte_each_way() {
+    let (mut master, slave) = linked_pair();
+    let partner: thread::JoinHandle<Vec<u8>> = echo_partner(slave, 0x34);
+    assert_eq!(master.exchange(0x12).expect("exchange"), 0x34);
+    assert_eq!(master.exchange(0x56).expect("exchange"), 0x34);
+    drop(master);
+    assert_eq!(partner.join().expect("partner thread"), vec![0x12, 0x56]);
+}
+
+#[test]
+fn poll_is_empty_until_the_partner_sends() {
+    let (mut master, mut slave) = linked_pair();
+    assert_eq!(slave.poll(0x00).expect("poll"), None);
+    let sender: thread::JoinHandle<u8> =
+        thread::spawn(move || master.exchange(0xA5).expect("exchange"));
+    let received: u8 = loop {
+        if let Some(byte) = slave.poll(0x5A).expect("poll") {
+            break byte;
+        }
+        thread::yield_now();
+    };
+    assert_eq!(received, 0xA5);
+    assert_eq!(sender.join().expect("sender thread"), 0x5A);
+}
+
+#[test]
+fn internal_clock_transfer_goes_over_the_link() {
+    let (master, slave) = linked_pair();
+    let partner: thread::JoinHandle<Vec<u8>> = echo_partner(slave, 0x77);
+    let mut emulator: Emulator = idle_emulator();
+    emulator.set_link(Some(Box::new(master)));
+    emulator.write_byte(0xFF01, 0x42);
+    emulator.write_byte(0xFF02, 0x81);
+    emulator.run_frame();
+    assert_eq!(emulator.read(0xFF01), 0x77);
+    assert_eq!(emulator.read(0xFF02) & 0x80, 0);
+    emulator.set_link(None);
+    assert_eq!(partner.join().expect("partner thread"), vec![0x42]);
+}
+
+#[test]
+fn disconnect_falls_back_to_an_unplugged_cable() {
+    let (master, slave) = linked_pair();
+    drop(slave);
+    let mut emulator: Emulator = idle_emulator();
+    emulator.set_link(Some(Box::new(master)));
+    emulator.write_byte(0xFF01, 0x42);
+    emulator.write_byte(0xFF02, 0x81);
+    emulator.run_frame();
+    assert!(!emulator.link_connected());
+    assert_eq!(emulator.read(0xFF01), 0xFF);
+}
+
+#[test]
+fn external_clock_transfer_waits_for_the_partner() {
+    let (mut master, slave) = linked_pair();
+    let mut emulator: Emulator = idle_emulator();
+    emulator.set_link(Some(Box::new(slave)));
+    emulator.write_byte(0xFF01, 0x99);
+    emulator.write_byte(0xFF02, 0x80);
+    emulator.run_frame();
+    assert_eq!(emulator.read(0xFF02) & 0x80, 0x80);
+    let sender: thread::JoinHandle<u8> =
+        thread::spawn(move || master.exchange(0x11).expect("exchange"));
+    while emulator.read(0xFF02) & 0x80 != 0 {
+        emulator.run_frame();
+    }
+    assert_eq!(emulator.read(0xFF01), 0x11);
+    assert_eq!(sender.join().expect("sender thread"), 0x99);
+}
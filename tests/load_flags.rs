@@ -0,0 +1,53 @@
+mod common;
+
+use common::{assemble, program_cpu};
+use rgb_emu::Cpu;
+
+// One entry per instruction, covering register, immediate, 16-bit and memory loads. The assembler
+// doesn't know the (BC)/(DE) forms, so they are raw opcodes.
+fn loads() -> Vec<Vec<u8>> {
+    let mut loads: Vec<Vec<u8>> = [
+        "LD B,C",
+        "LD A,H",
+        "LD D,$99",
+        "LD A,0",
+        "LD HL,$C100",
+        "LD DE,$C200",
+        "LD BC,$C201",
+        "LD SP,$DFF0",
+        "LD (HL),A",
+        "LD (HL),$80",
+        "LD E,(HL)",
+        "LD ($C300),A",
+    ]
+    .iter()
+    .map(|source| assemble(source))
+    .collect();
+    // LD (BC),A, LD (DE),A, LD A,(BC) and LD A,(DE)
+    loads.extend([vec![0x02], vec![0x12], vec![0x0A], vec![0x1A]]);
+    loads
+}
+
+// Sets F through POP AF, then checks F after every load
+fn assert_loads_keep(f: u8) {
+    let mut program: Vec<u8> = assemble(&format!("LD BC,${f:04X}; PUSH BC; POP AF"));
+    let loads: Vec<Vec<u8>> = loads();
+    program.extend(loads.concat());
+    let mut cpu: Cpu = program_cpu(&program);
+    cpu.run_for(3);
+    assert_eq!(cpu.snapshot().f, f);
+    for load in loads {
+        cpu.step();
+        assert_eq!(cpu.snapshot().f, f, "after {load:02X?}");
+    }
+}
+
+#[test]
+fn loads_leave_a_set_f_untouched() {
+    assert_loads_keep(0xF0);
+}
+
+#[test]
+fn loads_leave_a_clear_f_untouched() {
+    assert_loads_keep(0x00);
+}
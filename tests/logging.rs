@@ -0,0 +1,46 @@
+use std::sync::{Mutex, MutexGuard};
+
+use log::{Level, Log, Metadata, Record};
+use rgb_emu::{MemBus, Rom};
+
+// Keeps every record so the test can look for the one it expects
+struct CaptureLogger {
+    records: Mutex<Vec<(Level, String)>>,
+}
+
+impl Log for CaptureLogger {
+    fn enabled(&self, _metadata: &Metadata) -> bool {
+        true
+    }
+
+    fn log(&self, record: &Record) {
+        self.records
+            .lock()
+            .unwrap()
+            .push((record.level(), record.args().to_string()));
+    }
+
+    fn flush(&self) {}
+}
+
+static LOGGER: CaptureLogger = CaptureLogger {
+    records: Mutex::new(Vec::new()),
+};
+
+#[test]
+fn a_write_to_unusable_memory_logs_a_warning() {
+    log::set_logger(&LOGGER).expect("no other logger in this test binary");
+    log::set_max_level(log::LevelFilter::Trace);
+    let rom: Rom = Rom::from_bytes(vec![0x00; 0x8000]).expect("valid test ROM");
+    let mut bus: MemBus = MemBus::new(rom).expect("supported test mapper");
+    bus.write_byte(0xFEA0, 0x01);
+
+    let address: String = 0xFEA0.to_string();
+    let records: MutexGuard<Vec<(Level, String)>> = LOGGER.records.lock().unwrap();
+    assert!(
+        records
+            .iter()
+            .any(|(level, message)| *level == Level::Warn && message.contains(&address)),
+        "{records:?}"
+    );
+}
@@ -0,0 +1,30 @@
+use rgb_emu::Rom;
+
+const NINTENDO_LOGO: [u8; 48] = [
+    0xCE, 0xED, 0x66, 0x66, 0xCC, 0x0D, 0x00, 0x0B, 0x03, 0x73, 0x00, 0x83, 0x00, 0x0C, 0x00, 0x0D,
+    0x00, 0x08, 0x11, 0x1F, 0x88, 0x89, 0x00, 0x0E, 0xDC, 0xCC, 0x6E, 0xE6, 0xDD, 0xDD, 0xD9, 0x99,
+    0xBB, 0xBB, 0x67, 0x63, 0x6E, 0x0E, 0xEC, 0xCC, 0xDD, 0xDC, 0x99, 0x9F, 0xBB, 0xB9, 0x33, 0x3E,
+];
+
+fn rom_with_logo(logo: &[u8; 48]) -> Rom {
+    let mut data: Vec<u8> = vec![0x00; 0x8000];
+    data[0x0104..0x0134].copy_from_slice(logo);
+    Rom::from_bytes(data).expect("valid test ROM")
+}
+
+#[test]
+fn the_real_logo_matches() {
+    let rom: Rom = rom_with_logo(&NINTENDO_LOGO);
+    assert!(rom.logo_matches());
+    assert!(rom.info().contains("Nintendo logo: OK"));
+}
+
+#[test]
+fn a_corrupted_logo_does_not_match() {
+    // Change the last byte so the whole range has to be compared
+    let mut logo: [u8; 48] = NINTENDO_LOGO;
+    logo[47] ^= 0x01;
+    let rom: Rom = rom_with_logo(&logo);
+    assert!(!rom.logo_matches());
+    assert!(rom.info().contains("Nintendo logo: does not match"));
+}
@@ -0,0 +1,38 @@
+use rgb_emu::{IoDevice, Oam, Ppu, Vram};
+
+// A PPU with the LCD on, run to the start of line 10
+fn ppu_on_line_10(vram: &mut Vram, oam: &Oam) -> Ppu {
+    let mut ppu: Ppu = Ppu::new();
+    ppu.write(0xFF40, 0b1001_0001);
+    ppu.step(456 * 10, vram, oam);
+    ppu
+}
+
+#[test]
+fn writing_ly_does_not_move_the_scanline() {
+    let mut vram: Vram = Vram::new();
+    let oam: Oam = Oam::new();
+    let mut ppu: Ppu = ppu_on_line_10(&mut vram, &oam);
+    assert_eq!(ppu.read(0xFF44), 10);
+    ppu.write(0xFF44, 0x50);
+    assert_eq!(ppu.read(0xFF44), 10);
+    // Line timing carries on from where it was
+    ppu.step(456, &mut vram, &oam);
+    assert_eq!(ppu.read(0xFF44), 11);
+}
+
+#[test]
+fn writing_lyc_updates_the_coincidence_flag_at_once() {
+    let mut vram: Vram = Vram::new();
+    let oam: Oam = Oam::new();
+    let mut ppu: Ppu = ppu_on_line_10(&mut vram, &oam);
+    ppu.write(0xFF45, 10);
+    assert_ne!(ppu.read(0xFF41) & 0b0000_0100, 0);
+    ppu.write(0xFF45, 11);
+    assert_eq!(ppu.read(0xFF41) & 0b0000_0100, 0);
+    // With the LYC source enabled, a matching write requests the STAT interrupt on the next dot
+    ppu.write(0xFF41, 0b0100_0000);
+    assert_eq!(ppu.step(1, &mut vram, &oam) & 0b0000_0010, 0);
+    ppu.write(0xFF45, 10);
+    assert_ne!(ppu.step(1, &mut vram, &oam) & 0b0000_0010, 0);
+}
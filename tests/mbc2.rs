@@ -0,0 +1,55 @@
+use rgb_emu::{MemBus, Rom};
+
+// A 128 KiB MBC2+BATTERY cartridge with each bank's number at the start of the bank
+fn mbc2_bus() -> MemBus {
+    let mut data: Vec<u8> = vec![0x00; 0x20000];
+    data[0x0147] = 0x06;
+    data[0x0148] = 0x02;
+    for bank in 1..8 {
+        data[bank * 0x4000] = bank as u8;
+    }
+    MemBus::new(Rom::from_bytes(data).expect("valid test ROM")).expect("supported test mapper")
+}
+
+#[test]
+fn ram_keeps_the_low_nibble_and_reads_the_high_nibble_as_ones() {
+    let mut bus: MemBus = mbc2_bus();
+    bus.write_byte(0xA000, 0x05);
+    assert_eq!(bus.read(0xA000), 0xFF, "disabled RAM");
+    bus.write_byte(0x0000, 0x0A);
+    bus.write_byte(0xA000, 0xFF);
+    assert_eq!(bus.read(0xA000), 0xFF);
+    bus.write_byte(0xA001, 0x35);
+    assert_eq!(bus.read(0xA001), 0xF5);
+    bus.write_byte(0xA002, 0x00);
+    assert_eq!(bus.read(0xA002), 0xF0);
+}
+
+#[test]
+fn the_512_cells_repeat_through_the_ram_window() {
+    let mut bus: MemBus = mbc2_bus();
+    bus.write_byte(0x0000, 0x0A);
+    bus.write_byte(0xA123, 0x07);
+    for addr in [0xA323, 0xB123, 0xBF23] {
+        assert_eq!(bus.read(addr), 0xF7, "{addr:04X}");
+    }
+}
+
+#[test]
+fn address_bit_8_selects_the_register() {
+    let mut bus: MemBus = mbc2_bus();
+    // Bit 8 set: ROM bank, not RAM enable. Bank 10 wraps to 2 of 8.
+    bus.write_byte(0x0100, 0x0A);
+    assert_eq!(bus.read(0x4000), 2);
+    bus.write_byte(0xA000, 0x03);
+    assert_eq!(bus.read(0xA000), 0xFF);
+    bus.write_byte(0x2100, 0x03);
+    assert_eq!(bus.read(0x4000), 3);
+    bus.write_byte(0x2100, 0x00);
+    assert_eq!(bus.read(0x4000), 1);
+    // Bit 8 clear: RAM enable, the bank stays
+    bus.write_byte(0x2000, 0x0A);
+    assert_eq!(bus.read(0x4000), 1);
+    bus.write_byte(0xA000, 0x03);
+    assert_eq!(bus.read(0xA000), 0xF3);
+}
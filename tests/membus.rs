@@ -0,0 +1,32 @@
+use rgb_emu::{MemBus, Rom};
+
+fn bus(cart_type: u8) -> MemBus {
+    let mut data: Vec<u8> = vec![0x00; 0x8000];
+    data[0x0147] = cart_type;
+    data[0x0149] = 0x02;
+    data[0x1234] = 0x5A;
+    MemBus::new(Rom::from_bytes(data).expect("valid test ROM")).expect("supported test mapper")
+}
+
+#[test]
+fn write_byte_and_read_round_trip_in_every_ram_region() {
+    let mut bus: MemBus = bus(0x03);
+    // Enable cartridge RAM on the MBC1 first
+    bus.write_byte(0x0000, 0x0A);
+    for addr in [
+        0x8000, 0x9FFF, 0xA000, 0xBFFF, 0xC000, 0xCFFF, 0xD000, 0xDFFF, 0xFE00, 0xFF80, 0xFFFE,
+    ] {
+        bus.write_byte(addr, 0xA5);
+        assert_eq!(bus.read(addr), 0xA5, "{addr:04X}");
+        bus.write_byte(addr, 0x3C);
+        assert_eq!(bus.read(addr), 0x3C, "{addr:04X}");
+    }
+}
+
+#[test]
+fn rom_reads_come_from_the_cartridge_and_ignore_writes() {
+    let mut bus: MemBus = bus(0x00);
+    assert_eq!(bus.read(0x1234), 0x5A);
+    bus.write_byte(0x1234, 0xFF);
+    assert_eq!(bus.read(0x1234), 0x5A);
+}
@@ -0,0 +1,48 @@
+use rgb_emu::Emulator;
+use std::cell::RefCell;
+use std::rc::Rc;
+
+// LD A,$42; LD ($C123),A; LD HL,$C123; LD A,(HL); JR -2
+fn wram_emulator() -> Emulator {
+    let mut data: Vec<u8> = vec![0x00; 0x8000];
+    data[0x100..0x10B].copy_from_slice(&[
+        0x3E, 0x42, 0xEA, 0x23, 0xC1, 0x21, 0x23, 0xC1, 0x7E, 0x18, 0xFE,
+    ]);
+    Emulator::new(data).expect("valid test ROM")
+}
+
+#[test]
+fn write_hook_sees_wram_writes() {
+    let mut emulator: Emulator = wram_emulator();
+    let writes: Rc<RefCell<Vec<(u16, u8)>>> = Rc::new(RefCell::new(Vec::new()));
+    let log: Rc<RefCell<Vec<(u16, u8)>>> = writes.clone();
+    emulator.set_on_write(Some(Box::new(move |addr, value| {
+        log.borrow_mut().push((addr, value))
+    })));
+    for _ in 0..5 {
+        emulator.step();
+    }
+    assert_eq!(*writes.borrow(), vec![(0xC123, 0x42)]);
+    // Tool writes don't go through the CPU
+    emulator.write_byte(0xC000, 0x01);
+    assert_eq!(writes.borrow().len(), 1);
+}
+
+#[test]
+fn read_hook_sees_fetches_and_loads() {
+    let mut emulator: Emulator = wram_emulator();
+    let reads: Rc<RefCell<Vec<(u16, u8)>>> = Rc::new(RefCell::new(Vec::new()));
+    let log: Rc<RefCell<Vec<(u16, u8)>>> = reads.clone();
+    emulator.set_on_read(Some(Box::new(move |addr, value| {
+        log.borrow_mut().push((addr, value))
+    })));
+    for _ in 0..4 {
+        emulator.step();
+    }
+    assert_eq!(reads.borrow()[0], (0x0100, 0x3E));
+    assert_eq!(reads.borrow().last(), Some(&(0xC123, 0x42)));
+
+    emulator.set_on_read(None);
+    emulator.step();
+    assert_eq!(reads.borrow().last(), Some(&(0xC123, 0x42)));
+}
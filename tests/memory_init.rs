@@ -0,0 +1,46 @@
+use rgb_emu::{Emulator, MemBus, MemoryInit, Rom};
+
+fn bus(init: MemoryInit) -> MemBus {
+    let rom: Rom = Rom::from_bytes(vec![0x00; 0x8000]).expect("valid test ROM");
+    MemBus::with_memory_init(rom, init).expect("supported test mapper")
+}
+
+// VRAM, WRAM bank 0 and 1, and HRAM
+fn ram(bus: &MemBus) -> Vec<u8> {
+    (0x8000..=0x9FFF)
+        .chain(0xC000..=0xDFFF)
+        .chain(0xFF80..=0xFFFE)
+        .map(|addr| bus.read(addr))
+        .collect()
+}
+
+#[test]
+fn the_same_seed_gives_the_same_ram() {
+    let first: Vec<u8> = ram(&bus(MemoryInit::Seeded(0x1234)));
+    assert_eq!(first, ram(&bus(MemoryInit::Seeded(0x1234))));
+    assert_ne!(first, ram(&bus(MemoryInit::Seeded(0x1235))));
+    // Noise, not a fill
+    assert!(first.iter().any(|byte| *byte != first[0]));
+}
+
+#[test]
+fn the_default_is_zeroed_ram() {
+    assert_eq!(MemoryInit::default(), MemoryInit::Zeros);
+    assert!(
+        ram(&bus(MemoryInit::Zeros))
+            .iter()
+            .all(|byte| *byte == 0x00)
+    );
+}
+
+#[test]
+fn emulators_with_the_same_seed_start_with_the_same_wram() {
+    let wram = |seed: u64| -> Vec<u8> {
+        let rom: Rom = Rom::from_bytes(vec![0x00; 0x8000]).expect("valid test ROM");
+        let emulator: Emulator =
+            Emulator::with_memory_init(rom, MemoryInit::Seeded(seed)).expect("valid test ROM");
+        (0xC000..=0xDFFF).map(|addr| emulator.read(addr)).collect()
+    };
+    assert_eq!(wram(7), wram(7));
+    assert_ne!(wram(7), wram(8));
+}
@@ -0,0 +1,37 @@
+mod common;
+
+use common::idle_emulator;
+use rgb_emu::{Emulator, SearchValue};
+
+#[test]
+fn finds_a_byte_planted_in_wram() {
+    let mut emulator: Emulator = idle_emulator();
+    emulator.fill_memory(0xC000, 0xDFFF, 0x00);
+    emulator.write_byte(0xC123, 0x5A);
+    emulator.write_byte(0xFF90, 0x5A);
+    assert_eq!(
+        emulator.search_memory(SearchValue::Byte(0x5A)),
+        vec![0xC123, 0xFF90]
+    );
+}
+
+#[test]
+fn finds_words_little_endian() {
+    let mut emulator: Emulator = idle_emulator();
+    emulator.fill_memory(0xC000, 0xDFFF, 0x00);
+    emulator.write_byte(0xD000, 0x34);
+    emulator.write_byte(0xD001, 0x12);
+    assert_eq!(
+        emulator.search_memory(SearchValue::Word(0x1234)),
+        vec![0xD000]
+    );
+}
+
+#[test]
+fn fill_covers_the_whole_range() {
+    let mut emulator: Emulator = idle_emulator();
+    emulator.fill_memory(0xC100, 0xC10F, 0x77);
+    assert_eq!(emulator.read(0xC0FF), 0x00);
+    assert!((0xC100..=0xC10F).all(|addr| emulator.read(addr) == 0x77));
+    assert_eq!(emulator.read(0xC110), 0x00);
+}
@@ -0,0 +1,37 @@
+use rgb_emu::{MemBus, Rom};
+
+// A 2 MiB MMM01 cartridge with each bank's number at the start of the bank
+fn mmm01_bus() -> MemBus {
+    let mut data: Vec<u8> = vec![0x00; 0x200000];
+    data[0x0147] = 0x0B;
+    data[0x0148] = 0x06;
+    for bank in 1..128 {
+        data[bank * 0x4000] = bank as u8;
+    }
+    MemBus::new(Rom::from_bytes(data).expect("valid test ROM")).expect("supported test mapper")
+}
+
+#[test]
+fn starts_on_the_menu_in_the_last_two_banks() {
+    let bus: MemBus = mmm01_bus();
+    assert_eq!(bus.read(0x0000), 126);
+    assert_eq!(bus.read(0x4000), 127);
+}
+
+#[test]
+fn mapping_latches_the_game_the_menu_picked() {
+    let mut bus: MemBus = mmm01_bus();
+    // Outer bank 32 (mid bits 01) with bank 2 of the game, then set the map bit
+    bus.write_byte(0x2000, 0b0010_0010);
+    bus.write_byte(0x0000, 0b0100_0000);
+    assert_eq!(bus.read(0x0000), 32);
+    assert_eq!(bus.read(0x4000), 34);
+
+    // The game only switches within its 32 banks and can't unmap
+    bus.write_byte(0x2000, 0b0110_0011);
+    assert_eq!(bus.read(0x4000), 35);
+    bus.write_byte(0x2000, 0x00);
+    assert_eq!(bus.read(0x4000), 33);
+    bus.write_byte(0x0000, 0x00);
+    assert_eq!(bus.read(0x0000), 32);
+}
@@ -0,0 +1,63 @@
+use rgb_emu::{Cpu, CpuState, Emulator, MemBus, Model, Rom};
+
+fn membus() -> MemBus {
+    let rom: Rom = Rom::from_bytes(vec![0x00; 0x8000]).expect("valid test ROM");
+    MemBus::new(rom).expect("supported test mapper")
+}
+
+fn registers(model: Model) -> CpuState {
+    Cpu::new_with_model(membus(), model).snapshot()
+}
+
+#[test]
+fn a_tells_the_models_apart() {
+    assert_eq!(registers(Model::Dmg0).a, 0x01);
+    assert_eq!(registers(Model::Dmg).a, 0x01);
+    assert_eq!(registers(Model::Mgb).a, 0xFF);
+    assert_eq!(registers(Model::Cgb).a, 0x11);
+    // The DMG0 is the only one that leaves B at 0xFF
+    assert_eq!(registers(Model::Dmg0).b, 0xFF);
+}
+
+#[test]
+fn plain_new_starts_as_a_dmg() {
+    let cpu: CpuState = Cpu::new(membus()).snapshot();
+    assert_eq!(cpu, registers(Model::Dmg));
+    assert_eq!((cpu.pc, cpu.sp), (0x0100, 0xFFFE));
+}
+
+#[test]
+fn emulator_follows_the_forced_model() {
+    let mut emulator: Emulator = Emulator::new(vec![0x00; 0x8000]).expect("valid test ROM");
+    assert_eq!(emulator.model(), Model::Dmg);
+    emulator.set_model(Model::Cgb);
+    assert_eq!(emulator.cpu().snapshot().a, 0x11);
+    emulator.set_model(Model::Mgb);
+    assert_eq!(emulator.cpu().snapshot().a, 0xFF);
+}
+
+fn emulator_with_cgb_flag(flag: u8) -> Emulator {
+    let mut data: Vec<u8> = vec![0x00; 0x8000];
+    data[0x0143] = flag;
+    Emulator::new(data).expect("valid test ROM")
+}
+
+#[test]
+fn the_header_picks_the_model() {
+    let cgb_only: Emulator = emulator_with_cgb_flag(0xC0);
+    assert_eq!(cgb_only.model(), Model::Cgb);
+    assert_eq!(cgb_only.cpu().snapshot().a, 0x11);
+    // CGB enhanced games still run in color when the hardware can
+    assert_eq!(emulator_with_cgb_flag(0x80).model(), Model::Cgb);
+    let dmg: Emulator = emulator_with_cgb_flag(0x00);
+    assert_eq!(dmg.model(), Model::Dmg);
+    assert_eq!(dmg.cpu().snapshot().a, 0x01);
+}
+
+#[test]
+fn forcing_dmg_overrides_a_cgb_header() {
+    let mut emulator: Emulator = emulator_with_cgb_flag(0xC0);
+    emulator.set_model(Model::Dmg);
+    assert_eq!(emulator.model(), Model::Dmg);
+    assert_eq!(emulator.cpu().snapshot().a, 0x01);
+}
@@ -0,0 +1,69 @@
+mod common;
+
+use common::{assemble, program_cpu};
+use rgb_emu::{Cpu, TestResult, mooneye_result, run_test_rom};
+use std::path::{Path, PathBuf};
+
+// Every .gb file under dir and its subdirectories, sorted so failures are listed in a stable order
+fn test_roms(dir: &Path) -> Vec<PathBuf> {
+    let mut roms: Vec<PathBuf> = Vec::new();
+    for entry in std::fs::read_dir(dir).expect("MOONEYE_ACCEPTANCE is a readable directory") {
+        let path: PathBuf = entry.expect("directory entry is readable").path();
+        if path.is_dir() {
+            roms.extend(test_roms(&path));
+        } else if path.extension().is_some_and(|extension| extension == "gb") {
+            roms.push(path);
+        }
+    }
+    roms.sort();
+    roms
+}
+
+// The Mooneye ROMs can't be checked in, so point MOONEYE_ACCEPTANCE at the built acceptance directory:
+// MOONEYE_ACCEPTANCE=path/to/mts/acceptance cargo test --release --test mooneye -- --ignored
+// Ignored because most of the suite can't pass yet: the CB prefixed opcodes, LDH, DAA and the A
+// register rotates aren't implemented, and a ROM using any of them fails on it.
+#[test]
+#[ignore]
+fn mooneye_acceptance_passes() {
+    let Some(dir) = std::env::var_os("MOONEYE_ACCEPTANCE").map(PathBuf::from) else {
+        eprintln!("MOONEYE_ACCEPTANCE is not set, skipping the Mooneye acceptance tests");
+        return;
+    };
+    let roms: Vec<PathBuf> = test_roms(&dir);
+    let mut failures: Vec<String> = Vec::new();
+    for path in roms.iter() {
+        let rom: Vec<u8> = std::fs::read(path).expect("test ROM is readable");
+        if let TestResult::Fail(reason) = run_test_rom(rom) {
+            failures.push(format!("{}: {reason}", path.display()));
+        }
+    }
+    assert!(
+        failures.is_empty(),
+        "{} of {} Mooneye tests failed:\n{}",
+        failures.len(),
+        roms.len(),
+        failures.join("\n")
+    );
+}
+
+fn registers_after(program: &str) -> Cpu {
+    let mut cpu: Cpu = program_cpu(&assemble(program));
+    cpu.run_for(6);
+    cpu
+}
+
+#[test]
+fn signature_decoder_reads_pass_and_fail_from_registers() {
+    let cpu: Cpu = registers_after("LD B,3; LD C,5; LD D,8; LD E,13; LD H,21; LD L,34");
+    assert_eq!(mooneye_result(&cpu.snapshot()), Some(TestResult::Pass));
+    let cpu: Cpu = registers_after("LD B,$42; LD C,B; LD D,B; LD E,B; LD H,B; LD L,B");
+    assert!(matches!(
+        mooneye_result(&cpu.snapshot()),
+        Some(TestResult::Fail(_))
+    ));
+    // Still running, or partway through writing the result
+    assert_eq!(mooneye_result(&program_cpu(&[]).snapshot()), None);
+    let cpu: Cpu = registers_after("LD B,3; LD C,5; LD D,8; LD E,13; LD H,21; LD L,$42");
+    assert_eq!(mooneye_result(&cpu.snapshot()), None);
+}
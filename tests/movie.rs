@@ -0,0 +1,76 @@
+use rgb_emu::{Button, Emulator};
+use std::path::PathBuf;
+
+// Copies P1 into WRAM from 0xC000 on, so the buttons held on every frame end up in the state
+fn joypad_logger() -> Emulator {
+    let mut data: Vec<u8> = vec![0x00; 0x8000];
+    // LD DE,$FF00; LD HL,$C000; loop: LD A,$00; LD (DE),A; LD A,(DE); LD (HL),A; INC HL; JR loop
+    data[0x100..0x10E].copy_from_slice(&[
+        0x11, 0x00, 0xFF, 0x21, 0x00, 0xC0, 0x3E, 0x00, 0x12, 0x1A, 0x77, 0x23, 0x18, 0xF8,
+    ]);
+    Emulator::new(data).expect("valid test ROM")
+}
+
+fn movie_path(name: &str) -> PathBuf {
+    std::env::temp_dir().join(format!("rgb-emu-{}-{name}.rgbm", std::process::id()))
+}
+
+const INPUTS: [&[Button]; 4] = [
+    &[Button::Right],
+    &[Button::Right, Button::A],
+    &[],
+    &[Button::Down],
+];
+
+fn play_inputs(emulator: &mut Emulator) {
+    for buttons in INPUTS {
+        for button in [Button::Right, Button::A, Button::Down] {
+            emulator.set_button(button, buttons.contains(&button));
+        }
+        emulator.run_frame();
+    }
+}
+
+fn state(emulator: &Emulator) -> Vec<u8> {
+    emulator.save_state().expect("state encodes")
+}
+
+#[test]
+fn replaying_a_recording_reproduces_the_final_state() {
+    let path: PathBuf = movie_path("replay");
+    let mut recorded: Emulator = joypad_logger();
+    recorded.run_frame();
+    recorded
+        .start_recording(&path)
+        .expect("movie file can be created");
+    play_inputs(&mut recorded);
+    recorded
+        .stop_recording()
+        .expect("movie file can be written");
+    assert!(!recorded.recording());
+
+    let mut idle: Emulator = joypad_logger();
+    idle.run_frames(5);
+    assert_ne!(state(&idle), state(&recorded));
+
+    let mut replayed: Emulator = joypad_logger();
+    replayed.play_movie(&path).expect("movie loads");
+    assert!(replayed.movie_playing());
+    // The movie's inputs win over the frontend's
+    replayed.set_button(Button::Start, true);
+    replayed.run_frames(INPUTS.len() as u32);
+    assert!(!replayed.movie_playing());
+    assert_eq!(state(&replayed), state(&recorded));
+    std::fs::remove_file(path).expect("movie file can be removed");
+}
+
+#[test]
+fn play_movie_rejects_other_files() {
+    let path: PathBuf = movie_path("invalid");
+    std::fs::write(&path, b"not a movie").expect("file can be written");
+    let mut emulator: Emulator = joypad_logger();
+    let error = emulator.play_movie(&path).expect_err("not a movie");
+    assert_eq!(error.to_string(), "Not an rgb-emu movie");
+    assert!(!emulator.movie_playing());
+    std::fs::remove_file(path).expect("file can be removed");
+}
@@ -0,0 +1,62 @@
+use rgb_emu::{Apu, IoDevice};
+
+const SEQUENCER_CYCLES: u32 = 8192;
+
+fn status(apu: &Apu) -> u8 {
+    apu.read(0xFF26)
+}
+
+#[test]
+fn powering_off_zeroes_the_sound_registers_but_not_wave_ram() {
+    let mut apu: Apu = Apu::new();
+    for addr in 0xFF10..=0xFF25 {
+        apu.write(addr, 0x3C);
+    }
+    apu.write(0xFF30, 0x12);
+    apu.write(0xFF26, 0x00);
+    for addr in 0xFF10..=0xFF25 {
+        assert_eq!(apu.read(addr), 0x00, "{addr:04X}");
+    }
+    assert_eq!(apu.read(0xFF30), 0x12);
+    assert_eq!(status(&apu), 0x00);
+
+    // Writes are dropped until it is powered on again
+    apu.write(0xFF24, 0x77);
+    assert_eq!(apu.read(0xFF24), 0x00);
+    apu.write(0xFF26, 0x80);
+    apu.write(0xFF24, 0x77);
+    assert_eq!(apu.read(0xFF24), 0x77);
+    assert_eq!(status(&apu), 0x80);
+}
+
+#[test]
+fn status_bits_follow_channel_activity() {
+    let mut apu: Apu = Apu::new();
+    // Channel 1 with its DAC on and a length of 1, triggered with the length counter enabled
+    apu.write(0xFF12, 0xF0);
+    apu.write(0xFF11, 63);
+    apu.write(0xFF14, 0xC0);
+    // Channel 3 with its DAC on, no length counter
+    apu.write(0xFF1A, 0x80);
+    apu.write(0xFF1E, 0x80);
+    assert_eq!(status(&apu), 0x80 | 0b0101);
+
+    // The length counter runs out on the first length clock
+    apu.step(SEQUENCER_CYCLES);
+    assert_eq!(status(&apu), 0x80 | 0b0100);
+
+    // Turning a DAC off stops its channel
+    apu.write(0xFF1A, 0x00);
+    assert_eq!(status(&apu), 0x80);
+}
+
+#[test]
+fn triggering_with_the_dac_off_leaves_the_channel_off() {
+    let mut apu: Apu = Apu::new();
+    apu.write(0xFF21, 0x00);
+    apu.write(0xFF23, 0x80);
+    assert_eq!(status(&apu), 0x80);
+    apu.write(0xFF21, 0x10);
+    apu.write(0xFF23, 0x80);
+    assert_eq!(status(&apu), 0x80 | 0b1000);
+}
@@ -0,0 +1,88 @@
+mod common;
+
+use common::{assemble, program_emulator};
+use rgb_emu::{Emulator, Model, OamEntry, PpuMode, PpuState};
+
+// INC HL and DEC HL with HL inside OAM, forever
+fn oam_pointer_emulator(model: Model) -> Emulator {
+    let mut emulator: Emulator = program_emulator(&assemble("LD HL,$FE40; INC HL; DEC HL; JR -4"));
+    emulator.set_model(model);
+    emulator.write_byte(0xFF40, 0x11);
+    for i in 0..0xA0 {
+        emulator.write_byte(0xFE00 + i, (i as u8).wrapping_mul(37).wrapping_add(11));
+    }
+    emulator.write_byte(0xFF40, 0x91);
+    emulator
+}
+
+fn oam_bytes(emulator: &Emulator) -> Vec<u8> {
+    let attr = |entry: &OamEntry| -> u8 {
+        (entry.behind_bg as u8) << 7
+            | (entry.y_flip as u8) << 6
+            | (entry.x_flip as u8) << 5
+            | entry.dmg_palette << 4
+            | entry.bank << 3
+            | entry.cgb_palette
+    };
+    emulator
+        .sprites()
+        .iter()
+        .flat_map(|entry| [entry.y, entry.x, entry.tile, attr(entry)])
+        .collect()
+}
+
+fn word(oam: &[u8], row: usize, word: usize) -> u16 {
+    let addr: usize = row * 8 + word * 2;
+    u16::from_le_bytes([oam[addr], oam[addr + 1]])
+}
+
+// Steps until the next instruction is the INC HL at 0x0103 and runs during OAM scan, past row 0.
+// Returns the OAM row being scanned.
+fn step_to_inc_during_oam_scan(emulator: &mut Emulator) -> usize {
+    for _ in 0..100_000 {
+        let ppu: PpuState = emulator.ppu().snapshot();
+        if emulator.cpu().snapshot().pc == 0x0103 && ppu.mode == PpuMode::OamScan && ppu.dot >= 8 {
+            return (ppu.dot / 4) as usize;
+        }
+        emulator.step();
+    }
+    panic!("INC HL never ran during OAM scan");
+}
+
+#[test]
+fn inc_hl_during_oam_scan_applies_the_write_corruption() {
+    let mut emulator: Emulator = oam_pointer_emulator(Model::Dmg);
+    let row: usize = step_to_inc_during_oam_scan(&mut emulator);
+    let before: Vec<u8> = oam_bytes(&emulator);
+    emulator.step();
+    let after: Vec<u8> = oam_bytes(&emulator);
+
+    // Pan Docs: the first word becomes ((a ^ c) & (b ^ c)) ^ c and the other three are copied from the
+    // preceding row
+    let a: u16 = word(&before, row, 0);
+    let b: u16 = word(&before, row - 1, 0);
+    let c: u16 = word(&before, row - 1, 2);
+    assert_eq!(word(&after, row, 0), ((a ^ c) & (b ^ c)) ^ c, "row {row}");
+    for w in 1..4 {
+        assert_eq!(
+            word(&after, row, w),
+            word(&before, row - 1, w),
+            "row {row} word {w}"
+        );
+    }
+    for other in (0..20).filter(|other| *other != row) {
+        assert_eq!(
+            after[other * 8..other * 8 + 8],
+            before[other * 8..other * 8 + 8]
+        );
+    }
+}
+
+#[test]
+fn the_cgb_does_not_corrupt_oam() {
+    let mut emulator: Emulator = oam_pointer_emulator(Model::Cgb);
+    let before: Vec<u8> = oam_bytes(&emulator);
+    step_to_inc_during_oam_scan(&mut emulator);
+    emulator.run_frame();
+    assert_eq!(oam_bytes(&emulator), before);
+}
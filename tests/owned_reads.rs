@@ -0,0 +1,35 @@
+use rgb_emu::{Oam, Rom, Vram, Wram};
+
+#[test]
+fn reads_return_the_stored_byte_by_value() {
+    let mut data: Vec<u8> = vec![0x00; 0x8000];
+    data[0x0150] = 0x77;
+    let rom: Rom = Rom::from_bytes(data).expect("valid test ROM");
+    let byte: u8 = rom.get_value(0x0150);
+    assert_eq!(byte, 0x77);
+    // Past the end of the ROM reads as 0x00 rather than a reference to a temporary
+    assert_eq!(rom.get_value(0xFFFF), 0x00);
+
+    let mut wram: Wram = Wram::new();
+    let mut vram: Vram = Vram::new();
+    let mut oam: Oam = Oam::new();
+    assert_eq!(
+        (
+            wram.get_value(0xC010),
+            vram.get_value(0x8010),
+            oam.get_value(0xFE10)
+        ),
+        (0x00, 0x00, 0x00)
+    );
+    wram.set_value(0xC010, 0x12);
+    vram.set_value(0x8010, 0x34);
+    oam.set_value(0xFE10, 0x56);
+    assert_eq!(
+        (
+            wram.get_value(0xC010),
+            vram.get_value(0x8010),
+            oam.get_value(0xFE10)
+        ),
+        (0x12, 0x34, 0x56)
+    );
+}
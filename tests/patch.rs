@@ -0,0 +1,110 @@
+use rgb_emu::{Emulator, PatchError, Rom, RomError, apply_patch};
+
+fn base_rom() -> Vec<u8> {
+    let mut data: Vec<u8> = vec![0x00; 0x8000];
+    data[0x0134..0x0138].copy_from_slice(b"BASE");
+    data
+}
+
+// Renames the game and fills 0x0200-0x020F with 0xAA using a run record
+fn ips_patch() -> Vec<u8> {
+    let mut patch: Vec<u8> = b"PATCH".to_vec();
+    patch.extend_from_slice(&[0x00, 0x01, 0x34, 0x00, 0x07]);
+    patch.extend_from_slice(b"PATCHED");
+    patch.extend_from_slice(&[0x00, 0x02, 0x00, 0x00, 0x00, 0x00, 0x10, 0xAA]);
+    patch.extend_from_slice(b"EOF");
+    patch
+}
+
+fn crc32(data: &[u8]) -> u32 {
+    let mut crc: u32 = 0xFFFF_FFFF;
+    for byte in data {
+        crc ^= *byte as u32;
+        for _ in 0..8 {
+            crc = if crc & 1 != 0 {
+                (crc >> 1) ^ 0xEDB8_8320
+            } else {
+                crc >> 1
+            };
+        }
+    }
+    !crc
+}
+
+fn bps_number(mut number: usize, patch: &mut Vec<u8>) {
+    loop {
+        let byte: u8 = (number & 0x7F) as u8;
+        number >>= 7;
+        if number == 0 {
+            patch.push(byte | 0x80);
+            return;
+        }
+        patch.push(byte);
+        number -= 1;
+    }
+}
+
+// Keeps the ROM up to the title, writes a new title, then copies the rest of the original
+fn bps_patch(source: &[u8], target: &[u8]) -> Vec<u8> {
+    let mut patch: Vec<u8> = b"BPS1".to_vec();
+    bps_number(source.len(), &mut patch);
+    bps_number(target.len(), &mut patch);
+    bps_number(0, &mut patch);
+    bps_number((0x0134 - 1) << 2, &mut patch);
+    bps_number((4 - 1) << 2 | 1, &mut patch);
+    patch.extend_from_slice(&target[0x0134..0x0138]);
+    bps_number((source.len() - 0x0138 - 1) << 2 | 2, &mut patch);
+    bps_number(0x0138 << 1, &mut patch);
+    patch.extend_from_slice(&crc32(source).to_le_bytes());
+    patch.extend_from_slice(&crc32(target).to_le_bytes());
+    let patch_crc: u32 = crc32(&patch);
+    patch.extend_from_slice(&patch_crc.to_le_bytes());
+    patch
+}
+
+#[test]
+fn ips_patch_changes_bytes_and_title() {
+    let rom: Rom = Rom::from_bytes_with_patch(base_rom(), &ips_patch()).expect("patched ROM");
+    assert_eq!(rom.get_title(), "PATCHED");
+    let emulator: Emulator = Emulator::from_rom(rom).expect("runnable ROM");
+    assert_eq!(emulator.read(0x0200), 0xAA);
+    assert_eq!(emulator.read(0x020F), 0xAA);
+    assert_eq!(emulator.read(0x0210), 0x00);
+}
+
+#[test]
+fn bps_patch_rebuilds_the_target() {
+    let source: Vec<u8> = base_rom();
+    let mut target: Vec<u8> = source.clone();
+    target[0x0134..0x0138].copy_from_slice(b"MODS");
+    let patched: Vec<u8> = apply_patch(&source, &bps_patch(&source, &target)).expect("patched ROM");
+    assert_eq!(patched, target);
+}
+
+#[test]
+fn bps_patch_rejects_the_wrong_rom() {
+    let source: Vec<u8> = base_rom();
+    let mut target: Vec<u8> = source.clone();
+    target[0x0134..0x0138].copy_from_slice(b"MODS");
+    let patch: Vec<u8> = bps_patch(&source, &target);
+    let mut other: Vec<u8> = source.clone();
+    other[0x7FFF] = 0x01;
+    assert!(matches!(
+        apply_patch(&other, &patch),
+        Err(PatchError::WrongSource { .. })
+    ));
+    let mut damaged: Vec<u8> = patch.clone();
+    damaged[6] ^= 0xFF;
+    assert_eq!(
+        apply_patch(&source, &damaged),
+        Err(PatchError::CorruptPatch)
+    );
+}
+
+#[test]
+fn unknown_patch_format_is_an_error() {
+    assert!(matches!(
+        Rom::from_bytes_with_patch(base_rom(), b"NOTAPATCH"),
+        Err(RomError::Patch(PatchError::UnknownFormat))
+    ));
+}
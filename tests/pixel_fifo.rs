@@ -0,0 +1,138 @@
+use rgb_emu::{IoDevice, Oam, Ppu, PpuMode, PpuRenderer, Vram};
+
+const FRAME_DOTS: u32 = 456 * 154;
+
+// Four tiles (solid, checkerboard, vertical stripes, diagonal) repeated over both maps
+fn scene_vram() -> Vram {
+    let tiles: [[u8; 2]; 4] = [[0xFF, 0x00], [0xAA, 0x55], [0xCC, 0xF0], [0x81, 0x18]];
+    let mut vram: Vram = Vram::new();
+    for (index, [low, high]) in tiles.iter().enumerate() {
+        for row in 0..8u16 {
+            let addr: u16 = 0x8000 + index as u16 * 16 + row * 2;
+            vram.set_value(addr, low.rotate_left(row as u32));
+            vram.set_value(addr + 1, *high);
+        }
+    }
+    for i in 0..0x400u16 {
+        vram.set_value(0x9800 + i, ((i + i / 32) % 4) as u8);
+        vram.set_value(0x9C00 + i, (3 - i % 4) as u8);
+    }
+    vram
+}
+
+// (y, x, tile, attributes) in OAM terms, so y is 16 and x is 8 more than the screen position
+fn oam_with(sprites: &[(u8, u8, u8, u8)]) -> Oam {
+    let mut oam: Oam = Oam::new();
+    for (i, (y, x, tile, attr)) in sprites.iter().enumerate() {
+        let addr: u16 = 0xFE00 + i as u16 * 4;
+        oam.set_value(addr, *y);
+        oam.set_value(addr + 1, *x);
+        oam.set_value(addr + 2, *tile);
+        oam.set_value(addr + 3, *attr);
+    }
+    oam
+}
+
+fn scene_oam() -> Oam {
+    let mut sprites: Vec<(u8, u8, u8, u8)> = vec![
+        // Overlapping, OBP1 and x flip
+        (26, 8, 1, 0x00),
+        (28, 12, 2, 0x10),
+        (30, 6, 3, 0x20),
+        // Partly off the left and right edges
+        (56, 4, 3, 0x00),
+        (60, 164, 1, 0x10),
+        // Behind the background, y flipped
+        (76, 100, 2, 0xC0),
+        // Over the window
+        (130, 120, 1, 0x00),
+    ];
+    // 12 sprites on one line, the last two are dropped
+    sprites.extend((0..12).map(|i| (100, 10 + i * 13, 1 + i % 3, 0x00)));
+    oam_with(&sprites)
+}
+
+fn ppu(renderer: PpuRenderer, lcdc: u8, scx: u8) -> Ppu {
+    let mut ppu: Ppu = Ppu::new();
+    ppu.set_renderer(renderer);
+    ppu.write(0xFF47, 0b1110_0100);
+    ppu.write(0xFF48, 0b1101_0010);
+    ppu.write(0xFF49, 0b0001_1011);
+    ppu.write(0xFF42, 13);
+    ppu.write(0xFF43, scx);
+    ppu.write(0xFF4A, 96);
+    ppu.write(0xFF4B, 87);
+    ppu.write(0xFF40, lcdc);
+    ppu
+}
+
+fn render(renderer: PpuRenderer, cgb: bool) -> Vec<u8> {
+    let mut vram: Vram = scene_vram();
+    let oam: Oam = scene_oam();
+    // LCD, window on the 0x9C00 map, 0x8000 tile data, sprites and background on
+    let mut ppu: Ppu = ppu(renderer, 0b1111_0011, 5);
+    ppu.set_cgb(cgb);
+    ppu.step(FRAME_DOTS * 2, &mut vram, &oam);
+    ppu.framebuffer().to_vec()
+}
+
+#[test]
+fn fifo_renderer_draws_the_same_frame_as_the_scanline_renderer() {
+    // The CGB resolves overlapping sprites by OAM order instead of X
+    for cgb in [false, true] {
+        let scanline: Vec<u8> = render(PpuRenderer::Scanline, cgb);
+        let fifo: Vec<u8> = render(PpuRenderer::Fifo, cgb);
+        let mismatched: Vec<usize> = (0..scanline.len())
+            .filter(|i| scanline[*i] != fifo[*i])
+            .collect();
+        assert!(
+            mismatched.is_empty(),
+            "CGB {cgb}: {} pixels differ, first at {:?}",
+            mismatched.len(),
+            mismatched.first().map(|i| (i % 160, i / 160))
+        );
+    }
+}
+
+// Length of mode 3 on line 0 with a sprite at each OAM x
+fn drawing_dots(lcdc: u8, scx: u8, xs: &[u8]) -> u32 {
+    let mut vram: Vram = scene_vram();
+    let sprites: Vec<(u8, u8, u8, u8)> = xs.iter().map(|x| (16, *x, 1, 0x00)).collect();
+    let oam: Oam = oam_with(&sprites);
+    let mut ppu: Ppu = ppu(PpuRenderer::Fifo, lcdc, scx);
+    let mut dots: u32 = 0;
+    while ppu.ly() == 0 {
+        ppu.step(1, &mut vram, &oam);
+        if ppu.mode() == PpuMode::Drawing {
+            dots += 1;
+        }
+    }
+    dots
+}
+
+const SPRITES_ON: u8 = 0b1001_0011;
+
+#[test]
+fn sprite_fetches_lengthen_mode_3() {
+    let base: u32 = drawing_dots(SPRITES_ON, 0, &[]);
+    // 6 dots for the fetch plus 5 - min(5, (x + SCX) % 8) for the first sprite over a tile
+    assert_eq!(drawing_dots(SPRITES_ON, 0, &[8]), base + 11);
+    assert_eq!(drawing_dots(SPRITES_ON, 0, &[0]), base + 11);
+    assert_eq!(drawing_dots(SPRITES_ON, 0, &[12]), base + 7);
+    assert_eq!(drawing_dots(SPRITES_ON, 0, &[12, 13]), base + 13);
+    assert_eq!(drawing_dots(SPRITES_ON, 0, &[20, 30]), base + 13);
+    // SCX moves the tile boundaries, and its fine scroll costs dots of its own
+    let scrolled: u32 = drawing_dots(SPRITES_ON, 4, &[]);
+    assert_eq!(scrolled, base + 4);
+    assert_eq!(drawing_dots(SPRITES_ON, 4, &[12]), scrolled + 11);
+    assert_eq!(drawing_dots(SPRITES_ON, 0, &[8; 10]), base + 11 + 9 * 6);
+}
+
+#[test]
+fn unfetched_sprites_cost_nothing() {
+    let base: u32 = drawing_dots(SPRITES_ON, 0, &[]);
+    // Off the right edge
+    assert_eq!(drawing_dots(SPRITES_ON, 0, &[168, 200]), base);
+    // Sprites turned off in LCDC
+    assert_eq!(drawing_dots(SPRITES_ON & !0b10, 0, &[8, 40]), base);
+}
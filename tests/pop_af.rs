@@ -0,0 +1,16 @@
+use rgb_emu::{Cpu, CpuState, MemBus, Rom};
+
+#[test]
+fn pop_af_clears_the_low_nibble_of_f() {
+    let mut data: Vec<u8> = vec![0x00; 0x8000];
+    // LD BC,$12FF; PUSH BC; POP AF
+    data[0x100..0x105].copy_from_slice(&[0x01, 0xFF, 0x12, 0xC5, 0xF1]);
+    let rom: Rom = Rom::from_bytes(data).expect("valid test ROM");
+    let mut cpu: Cpu = Cpu::new(MemBus::new(rom).expect("supported test mapper"));
+    for _ in 0..3 {
+        cpu.step();
+    }
+    let state: CpuState = cpu.snapshot();
+    assert_eq!(state.a, 0x12);
+    assert_eq!(state.f, 0xF0);
+}
@@ -0,0 +1,27 @@
+use rgb_emu::Emulator;
+
+mod common;
+
+#[test]
+fn lcdc_and_palettes_start_at_their_post_boot_values() {
+    let emulator: Emulator = common::idle_emulator();
+    assert_eq!(emulator.read(0xFF40), 0x91);
+    assert_eq!(emulator.read(0xFF47), 0xFC);
+    assert_eq!(emulator.read(0xFF48), 0xFF);
+    assert_eq!(emulator.read(0xFF49), 0xFF);
+    assert_eq!(emulator.read(0xFF0F), 0xE1);
+    assert_eq!(emulator.read(0xFF26), 0xF1);
+    assert_eq!(emulator.read(0xFF04), 0xAB);
+}
+
+#[test]
+fn the_logo_is_left_on_the_background_map() {
+    let emulator: Emulator = common::idle_emulator();
+    // Two rows of twelve tiles with the trademark at the end of the top row
+    assert_eq!(emulator.read(0x9904), 0x01);
+    assert_eq!(emulator.read(0x990F), 0x0C);
+    assert_eq!(emulator.read(0x9910), 0x19);
+    assert_eq!(emulator.read(0x9924), 0x0D);
+    assert_eq!(emulator.read(0x992F), 0x18);
+    assert_eq!(emulator.read(0x8190), 0x3C);
+}
@@ -0,0 +1,140 @@
+mod common;
+
+use common::idle_emulator;
+use rgb_emu::{DisplayPalette, Emulator, PpuRenderer, SCREEN_HEIGHT, SCREEN_WIDTH};
+use std::fs;
+use std::path::PathBuf;
+
+// Channels may differ by this much before a pixel counts as changed
+const TOLERANCE: u8 = 8;
+const FRAMES: u32 = 3;
+
+// Set UPDATE_GOLDEN=1 to rewrite the checked-in images after an intended rendering change
+fn golden_path(name: &str) -> PathBuf {
+    PathBuf::from(env!("CARGO_MANIFEST_DIR"))
+        .join("tests")
+        .join("golden")
+        .join(format!("{name}.png"))
+}
+
+// Four tiles (solid light, checkerboard, vertical stripes, diagonal) laid out in a repeating pattern on
+// both tilemaps. The background is scrolled and the window covers the lower right corner.
+fn build_scene(emulator: &mut Emulator) {
+    let tiles: [[u8; 16]; 4] = [
+        [
+            0xFF, 0x00, 0xFF, 0x00, 0xFF, 0x00, 0xFF, 0x00, 0xFF, 0x00, 0xFF, 0x00, 0xFF, 0x00,
+            0xFF, 0x00,
+        ],
+        [
+            0xAA, 0xAA, 0x55, 0x55, 0xAA, 0xAA, 0x55, 0x55, 0xAA, 0xAA, 0x55, 0x55, 0xAA, 0xAA,
+            0x55, 0x55,
+        ],
+        [
+            0xCC, 0xF0, 0xCC, 0xF0, 0xCC, 0xF0, 0xCC, 0xF0, 0xCC, 0xF0, 0xCC, 0xF0, 0xCC, 0xF0,
+            0xCC, 0xF0,
+        ],
+        [
+            0x80, 0x01, 0x40, 0x02, 0x20, 0x04, 0x10, 0x08, 0x08, 0x10, 0x04, 0x20, 0x02, 0x40,
+            0x01, 0x80,
+        ],
+    ];
+    for (index, tile) in tiles.iter().enumerate() {
+        for (offset, byte) in tile.iter().enumerate() {
+            emulator.write_byte(0x8000 + (index * 16 + offset) as u16, *byte);
+        }
+    }
+    for i in 0..0x400u16 {
+        emulator.write_byte(0x9800 + i, ((i + i / 32) % 4) as u8);
+        emulator.write_byte(0x9C00 + i, (3 - i % 4) as u8);
+    }
+    emulator.write_byte(0xFF47, 0b1110_0100); // BGP
+    emulator.write_byte(0xFF42, 13); // SCY
+    emulator.write_byte(0xFF43, 5); // SCX
+    emulator.write_byte(0xFF4A, 96); // WY
+    emulator.write_byte(0xFF4B, 87); // WX
+    // LCD on, window on with the 0x9C00 map, 0x8000 tile data, background on
+    emulator.write_byte(0xFF40, 0b1111_0001);
+}
+
+fn render(renderer: PpuRenderer) -> Vec<u8> {
+    let mut emulator: Emulator = idle_emulator();
+    emulator.set_renderer(renderer);
+    emulator.set_display_palette(DisplayPalette::POCKET_GRAY);
+    build_scene(&mut emulator);
+    for _ in 0..FRAMES {
+        emulator.run_frame();
+    }
+    emulator.framebuffer_rgba()
+}
+
+fn read_png(path: &PathBuf) -> Vec<u8> {
+    let file: fs::File =
+        fs::File::open(path).unwrap_or_else(|e| panic!("Missing golden {}: {e}", path.display()));
+    let mut reader = png::Decoder::new(std::io::BufReader::new(file))
+        .read_info()
+        .expect("readable golden PNG");
+    let mut pixels: Vec<u8> = vec![0; reader.output_buffer_size().expect("golden PNG size")];
+    let info: png::OutputInfo = reader
+        .next_frame(&mut pixels)
+        .expect("decodable golden PNG");
+    assert_eq!(info.color_type, png::ColorType::Rgba, "golden must be RGBA");
+    assert_eq!(
+        (info.width as usize, info.height as usize),
+        (SCREEN_WIDTH, SCREEN_HEIGHT)
+    );
+    pixels.truncate(info.buffer_size());
+    pixels
+}
+
+fn write_png(path: &PathBuf, pixels: &[u8]) {
+    fs::create_dir_all(path.parent().expect("golden directory")).expect("create golden directory");
+    let file: fs::File = fs::File::create(path).expect("create golden PNG");
+    let mut encoder: png::Encoder<std::io::BufWriter<fs::File>> = png::Encoder::new(
+        std::io::BufWriter::new(file),
+        SCREEN_WIDTH as u32,
+        SCREEN_HEIGHT as u32,
+    );
+    encoder.set_color(png::ColorType::Rgba);
+    encoder.set_depth(png::BitDepth::Eight);
+    let mut writer = encoder.write_header().expect("write golden header");
+    writer
+        .write_image_data(pixels)
+        .expect("write golden pixels");
+}
+
+fn assert_matches_golden(name: &str, actual: &[u8]) {
+    let path: PathBuf = golden_path(name);
+    if std::env::var_os("UPDATE_GOLDEN").is_some() {
+        write_png(&path, actual);
+        return;
+    }
+    let expected: Vec<u8> = read_png(&path);
+    let mismatched: Vec<(usize, usize)> = actual
+        .chunks(4)
+        .zip(expected.chunks(4))
+        .enumerate()
+        .filter(|(_, (a, e))| {
+            a.iter()
+                .zip(e.iter())
+                .any(|(a, e)| a.abs_diff(*e) > TOLERANCE)
+        })
+        .map(|(i, _)| (i % SCREEN_WIDTH, i / SCREEN_WIDTH))
+        .collect();
+    assert!(
+        mismatched.is_empty(),
+        "{name}: {} pixels differ from {}, first at {:?}",
+        mismatched.len(),
+        path.display(),
+        mismatched[0]
+    );
+}
+
+#[test]
+fn scanline_renderer_matches_golden() {
+    assert_matches_golden("synthetic_scene", &render(PpuRenderer::Scanline));
+}
+
+#[test]
+fn fifo_renderer_matches_golden() {
+    assert_matches_golden("synthetic_scene", &render(PpuRenderer::Fifo));
+}
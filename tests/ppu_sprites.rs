@@ -0,0 +1,135 @@
+use rgb_emu::{IoDevice, Oam, Ppu, SCREEN_WIDTH, Vram};
+
+const FRAME_DOTS: u32 = 456 * 154;
+
+// Fills every row of a tile with one color index
+fn solid_tile(vram: &mut Vram, index: u8, color: u8) {
+    let low: u8 = if color & 0b01 != 0 { 0xFF } else { 0x00 };
+    let high: u8 = if color & 0b10 != 0 { 0xFF } else { 0x00 };
+    for row in 0..8u16 {
+        let addr: u16 = 0x8000 + index as u16 * 16 + row * 2;
+        vram.set_value(addr, low);
+        vram.set_value(addr + 1, high);
+    }
+}
+
+fn place_sprite(oam: &mut Oam, sprite: u16, x: u8, y: u8, tile: u8, attr: u8) {
+    let addr: u16 = 0xFE00 + sprite * 4;
+    oam.set_value(addr, y + 16);
+    oam.set_value(addr + 1, x + 8);
+    oam.set_value(addr + 2, tile);
+    oam.set_value(addr + 3, attr);
+}
+
+// Runs one frame with the background off and an identity OBP0, so shades are sprite color indexes
+fn render(vram: &mut Vram, oam: &Oam, lcdc: u8) -> Vec<u8> {
+    let mut ppu: Ppu = Ppu::new();
+    ppu.write(0xFF48, 0b1110_0100);
+    ppu.write(0xFF40, lcdc);
+    ppu.step(FRAME_DOTS, vram, oam);
+    ppu.framebuffer().to_vec()
+}
+
+fn shade(frame: &[u8], x: usize, y: usize) -> u8 {
+    frame[y * SCREEN_WIDTH + x]
+}
+
+#[test]
+fn tall_sprite_ignores_the_low_tile_bit() {
+    let mut vram: Vram = Vram::new();
+    solid_tile(&mut vram, 4, 1);
+    solid_tile(&mut vram, 5, 3);
+    let mut oam: Oam = Oam::new();
+    place_sprite(&mut oam, 0, 10, 20, 5, 0x00);
+    let frame: Vec<u8> = render(&mut vram, &oam, 0b1000_0110);
+    assert_eq!(shade(&frame, 10, 20), 1);
+    assert_eq!(shade(&frame, 10, 27), 1);
+    assert_eq!(shade(&frame, 10, 28), 3);
+    assert_eq!(shade(&frame, 17, 35), 3);
+    assert_eq!(shade(&frame, 10, 36), 0);
+}
+
+#[test]
+fn vertical_flip_swaps_the_tile_pair() {
+    let mut vram: Vram = Vram::new();
+    solid_tile(&mut vram, 4, 1);
+    solid_tile(&mut vram, 5, 3);
+    // A single marked row at the top of the first tile ends up on the last row of the sprite
+    vram.set_value(0x8000 + 4 * 16, 0x00);
+    vram.set_value(0x8000 + 4 * 16 + 1, 0xFF);
+    let mut oam: Oam = Oam::new();
+    place_sprite(&mut oam, 0, 10, 20, 4, 0x40);
+    let frame: Vec<u8> = render(&mut vram, &oam, 0b1000_0110);
+    assert_eq!(shade(&frame, 10, 20), 3);
+    assert_eq!(shade(&frame, 10, 27), 3);
+    assert_eq!(shade(&frame, 10, 28), 1);
+    assert_eq!(shade(&frame, 10, 34), 1);
+    assert_eq!(shade(&frame, 10, 35), 2);
+}
+
+#[test]
+fn short_sprites_flip_within_one_tile() {
+    let mut vram: Vram = Vram::new();
+    solid_tile(&mut vram, 4, 1);
+    vram.set_value(0x8000 + 4 * 16, 0x00);
+    vram.set_value(0x8000 + 4 * 16 + 1, 0xFF);
+    solid_tile(&mut vram, 5, 3);
+    let mut oam: Oam = Oam::new();
+    place_sprite(&mut oam, 0, 10, 20, 4, 0x40);
+    let frame: Vec<u8> = render(&mut vram, &oam, 0b1000_0010);
+    assert_eq!(shade(&frame, 10, 20), 1);
+    assert_eq!(shade(&frame, 10, 27), 2);
+    assert_eq!(shade(&frame, 10, 28), 0);
+}
+
+#[test]
+fn only_ten_sprites_per_line_are_drawn() {
+    let mut vram: Vram = Vram::new();
+    solid_tile(&mut vram, 1, 2);
+    let mut oam: Oam = Oam::new();
+    for sprite in 0..11 {
+        place_sprite(&mut oam, sprite, sprite as u8 * 10, 50, 1, 0x00);
+    }
+    // Off this line, so it doesn't take a slot
+    place_sprite(&mut oam, 11, 150, 70, 1, 0x00);
+    let frame: Vec<u8> = render(&mut vram, &oam, 0b1000_0010);
+    for sprite in 0..10 {
+        assert_eq!(shade(&frame, sprite * 10, 50), 2, "sprite {sprite}");
+    }
+    assert_eq!(shade(&frame, 100, 50), 0);
+    assert_eq!(shade(&frame, 150, 70), 2);
+}
+
+#[test]
+fn dmg_draws_the_leftmost_sprite_on_top() {
+    let mut vram: Vram = Vram::new();
+    solid_tile(&mut vram, 1, 1);
+    solid_tile(&mut vram, 2, 3);
+    let mut oam: Oam = Oam::new();
+    place_sprite(&mut oam, 0, 20, 50, 1, 0x00);
+    place_sprite(&mut oam, 1, 16, 50, 2, 0x00);
+    // Same X as sprite 0 but later in OAM, so sprite 0 wins the tie
+    place_sprite(&mut oam, 2, 20, 60, 1, 0x00);
+    place_sprite(&mut oam, 3, 20, 60, 2, 0x00);
+    let frame: Vec<u8> = render(&mut vram, &oam, 0b1000_0010);
+    assert_eq!(shade(&frame, 20, 50), 3);
+    assert_eq!(shade(&frame, 24, 50), 1);
+    assert_eq!(shade(&frame, 20, 60), 1);
+}
+
+#[test]
+fn cgb_draws_the_first_sprite_in_oam_on_top() {
+    let mut vram: Vram = Vram::new();
+    solid_tile(&mut vram, 1, 1);
+    solid_tile(&mut vram, 2, 3);
+    let mut oam: Oam = Oam::new();
+    place_sprite(&mut oam, 0, 20, 50, 1, 0x00);
+    place_sprite(&mut oam, 1, 16, 50, 2, 0x00);
+    let mut ppu: Ppu = Ppu::new();
+    ppu.set_cgb(true);
+    ppu.write(0xFF40, 0b1000_0010);
+    ppu.step(FRAME_DOTS, &mut vram, &oam);
+    let frame: &[u8] = ppu.framebuffer();
+    assert_eq!(shade(frame, 20, 50), 1);
+    assert_eq!(shade(frame, 16, 50), 3);
+}
@@ -0,0 +1,45 @@
+use rgb_emu::{IoDevice, Oam, Ppu, PpuMode, PpuState, Vram};
+
+// A PPU with the LCD just switched on, at the start of OAM scan on line 0
+fn enabled_ppu() -> Ppu {
+    let mut ppu: Ppu = Ppu::new();
+    ppu.write(0xFF43, 7); // SCX
+    ppu.write(0xFF4B, 20); // WX
+    ppu.write(0xFF40, 0b1001_0001);
+    ppu
+}
+
+#[test]
+fn snapshot_follows_the_modes_of_a_line() {
+    let mut vram: Vram = Vram::new();
+    let oam: Oam = Oam::new();
+    let mut ppu: Ppu = enabled_ppu();
+    assert_eq!(ppu.snapshot().mode, PpuMode::OamScan);
+    ppu.step(80, &mut vram, &oam);
+    let drawing: PpuState = ppu.snapshot();
+    assert_eq!(drawing.mode, PpuMode::Drawing);
+    assert_eq!((drawing.ly, drawing.dot), (0, 80));
+    assert_eq!(drawing.stat & 0b11, 3);
+    ppu.step(172, &mut vram, &oam);
+    let hblank: PpuState = ppu.snapshot();
+    assert_eq!(hblank.mode, PpuMode::HBlank);
+    assert_eq!(hblank.stat & 0b11, 0);
+    ppu.step(456 - 80 - 172, &mut vram, &oam);
+    let next_line: PpuState = ppu.snapshot();
+    assert_eq!(next_line.mode, PpuMode::OamScan);
+    assert_eq!((next_line.ly, next_line.dot), (1, 0));
+}
+
+#[test]
+fn snapshot_reports_vblank_and_registers() {
+    let mut vram: Vram = Vram::new();
+    let oam: Oam = Oam::new();
+    let mut ppu: Ppu = enabled_ppu();
+    ppu.step(456 * 144, &mut vram, &oam);
+    let state: PpuState = ppu.snapshot();
+    assert_eq!(state.mode, PpuMode::VBlank);
+    assert_eq!(state.ly, 144);
+    assert_eq!(state.stat & 0b11, 1);
+    assert_eq!(state.lcdc, 0b1001_0001);
+    assert_eq!((state.scx, state.wx), (7, 20));
+}
@@ -0,0 +1,41 @@
+mod common;
+
+use common::{assemble, load_program, program_cpu};
+use rgb_emu::{Cpu, CpuState, MemBus, Rom};
+
+#[test]
+fn assembler_matches_hand_written_opcodes() {
+    assert_eq!(
+        assemble("LD A,$12\nADD A,$30\nLD B,A"),
+        vec![0x3E, 0x12, 0xC6, 0x30, 0x47]
+    );
+    assert_eq!(
+        assemble("ld hl,0xC100; ld (hl),7; inc hl; xor a; jr -2"),
+        vec![0x21, 0x00, 0xC1, 0x36, 0x07, 0x23, 0xAF, 0x18, 0xFE]
+    );
+    assert_eq!(
+        assemble("LD ($FF80),A\nLD A,($FF80)\nPUSH AF\nPOP BC"),
+        vec![0xEA, 0x80, 0xFF, 0xFA, 0x80, 0xFF, 0xF5, 0xC1]
+    );
+}
+
+#[test]
+fn loaded_program_runs() {
+    let mut cpu: Cpu = program_cpu(&assemble("LD A,$12\nADD A,$30\nLD B,A"));
+    cpu.run_for(3);
+    let state: CpuState = cpu.snapshot();
+    assert_eq!(state.a, 0x42);
+    assert_eq!(state.b, 0x42);
+    assert_eq!(state.pc, 0xC005);
+}
+
+#[test]
+fn load_program_writes_into_the_bus() {
+    let rom: Rom = Rom::from_bytes(vec![0x00; 0x8000]).expect("valid test ROM");
+    let mut bus: MemBus = MemBus::new(rom).expect("supported test mapper");
+    load_program(&mut bus, 0xD000, &[0x01, 0x02, 0x03]);
+    assert_eq!(
+        [bus.read(0xD000), bus.read(0xD001), bus.read(0xD002)],
+        [0x01, 0x02, 0x03]
+    );
+}
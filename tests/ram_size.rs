@@ -0,0 +1,37 @@
+use rgb_emu::{CartridgeHeader, Rom, RomError};
+
+fn rom_with_ram_size(byte: u8) -> Result<Rom, RomError> {
+    let mut data: Vec<u8> = vec![0x00; 0x8000];
+    // MBC1+RAM+BATTERY, so the RAM size byte means something
+    data[0x0147] = 0x03;
+    data[0x0149] = byte;
+    Rom::from_bytes(data)
+}
+
+#[test]
+fn every_defined_ram_size_is_read() {
+    let expected: [(u8, u32, u32); 6] = [
+        (0x00, 0, 0),
+        (0x01, 2048, 1),
+        (0x02, 8192, 1),
+        (0x03, 32768, 4),
+        (0x04, 131072, 16),
+        (0x05, 65536, 8),
+    ];
+    for (byte, size, banks) in expected {
+        let header: CartridgeHeader = rom_with_ram_size(byte).expect("valid RAM size").header();
+        assert_eq!(
+            (header.ram_size, header.ram_banks),
+            (size, banks),
+            "byte {byte:02X}"
+        );
+    }
+}
+
+#[test]
+fn unknown_ram_sizes_are_errors() {
+    for byte in [0x06, 0x10, 0xFF] {
+        let error: RomError = rom_with_ram_size(byte).err().expect("invalid RAM size");
+        assert!(matches!(error, RomError::InvalidRamSize(b) if b == byte));
+    }
+}
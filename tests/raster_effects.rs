@@ -0,0 +1,62 @@
+use rgb_emu::{IoDevice, Oam, Ppu, PpuRenderer, SCREEN_WIDTH, Vram};
+
+const LINE_DOTS: u32 = 456;
+const FRAME_DOTS: u32 = LINE_DOTS * 154;
+
+// Every tile is four columns of color 1 then four of color 0, so any horizontal scroll shows
+fn striped_vram() -> Vram {
+    let mut vram: Vram = Vram::new();
+    for row in 0..8u16 {
+        vram.set_value(0x8000 + row * 2, 0xF0);
+    }
+    vram
+}
+
+fn enabled_ppu(renderer: PpuRenderer) -> Ppu {
+    let mut ppu: Ppu = Ppu::new();
+    ppu.set_renderer(renderer);
+    ppu.write(0xFF47, 0b1110_0100);
+    ppu.write(0xFF40, 0b1001_0001);
+    ppu
+}
+
+fn line(ppu: &Ppu, y: usize) -> Vec<u8> {
+    ppu.framebuffer()[y * SCREEN_WIDTH..(y + 1) * SCREEN_WIDTH].to_vec()
+}
+
+#[test]
+fn scx_written_on_line_80_only_moves_the_lines_below() {
+    for renderer in [PpuRenderer::Scanline, PpuRenderer::Fifo] {
+        let mut vram: Vram = striped_vram();
+        let oam: Oam = Oam::new();
+        let mut ppu: Ppu = enabled_ppu(renderer);
+        ppu.step(LINE_DOTS * 80, &mut vram, &oam);
+        assert_eq!(ppu.snapshot().ly, 80);
+        ppu.write(0xFF43, 2);
+        ppu.step(FRAME_DOTS - LINE_DOTS * 80, &mut vram, &oam);
+
+        let unscrolled: Vec<u8> = line(&ppu, 0);
+        assert_eq!(&unscrolled[0..8], &[1, 1, 1, 1, 0, 0, 0, 0]);
+        assert_eq!(line(&ppu, 79), unscrolled);
+        for y in [80, 100, 143] {
+            assert_eq!(
+                line(&ppu, y)[..SCREEN_WIDTH - 2],
+                unscrolled[2..],
+                "{renderer:?} line {y}"
+            );
+        }
+    }
+}
+
+#[test]
+fn bgp_can_change_between_lines() {
+    let mut vram: Vram = striped_vram();
+    let oam: Oam = Oam::new();
+    let mut ppu: Ppu = enabled_ppu(PpuRenderer::Scanline);
+    ppu.step(LINE_DOTS * 80, &mut vram, &oam);
+    // Color 1 shows as shade 3 from here on
+    ppu.write(0xFF47, 0b1110_1100);
+    ppu.step(FRAME_DOTS - LINE_DOTS * 80, &mut vram, &oam);
+    assert_eq!(line(&ppu, 79)[0], 1);
+    assert_eq!(line(&ppu, 80)[0], 3);
+}
@@ -0,0 +1,30 @@
+use rgb_emu::{CpuState, Emulator};
+
+mod common;
+
+// CALL $0104; NOP; RETI
+const CALL_THEN_RETI: [u8; 5] = [0xCD, 0x04, 0x01, 0x00, 0xD9];
+
+#[test]
+fn reti_returns_to_the_caller_with_ime_set() {
+    let mut emulator: Emulator = common::program_emulator(&CALL_THEN_RETI);
+    emulator.step();
+    let before: CpuState = emulator.cpu().snapshot();
+    assert_eq!((before.pc, before.ime), (0x0104, false));
+    emulator.step();
+    let after: CpuState = emulator.cpu().snapshot();
+    assert_eq!((after.pc, after.sp, after.ime), (0x0103, 0xFFFE, true));
+}
+
+#[test]
+fn a_pending_interrupt_fires_right_after_reti() {
+    let mut emulator: Emulator = common::program_emulator(&CALL_THEN_RETI);
+    emulator.write_byte(0xFFFF, 0x01);
+    emulator.write_byte(0xFF0F, 0x01);
+    emulator.step();
+    emulator.step();
+    // Unlike EI there is no one instruction delay, so the NOP at the return address waits
+    emulator.step();
+    assert_eq!(emulator.cpu().snapshot().pc, 0x0040);
+    assert_eq!(emulator.read(0xFF0F) & 0x01, 0x00);
+}
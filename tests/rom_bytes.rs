@@ -0,0 +1,28 @@
+use rgb_emu::Rom;
+
+fn rom() -> Rom {
+    let mut data: Vec<u8> = vec![0x00; 0x8000];
+    data[0x0134..0x0134 + 11].copy_from_slice(b"TITLE BYTES");
+    data[0x7FFF] = 0xEE;
+    Rom::from_bytes(data).expect("valid test ROM")
+}
+
+#[test]
+fn slice_returns_the_title_region() {
+    let mut expected: Vec<u8> = b"TITLE BYTES".to_vec();
+    expected.resize(0x10, 0x00);
+    assert_eq!(rom().slice(0x0134, 0x10), expected);
+}
+
+#[test]
+fn slice_stops_at_the_end_of_the_rom() {
+    assert_eq!(rom().slice(0x7FFE, 0x10), vec![0x00, 0xEE]);
+}
+
+#[test]
+fn bytes_iterates_the_whole_rom() {
+    let rom: Rom = rom();
+    assert_eq!(rom.bytes().count(), 0x8000);
+    assert_eq!(rom.bytes().nth(0x0134), Some(b'T'));
+    assert_eq!(rom.bytes().last(), Some(0xEE));
+}
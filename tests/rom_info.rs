@@ -0,0 +1,50 @@
+use rgb_emu::Rom;
+
+// An MBC1+RAM+BATTERY header with a matching checksum but no Nintendo logo
+fn header_bytes() -> Vec<u8> {
+    let mut data: Vec<u8> = vec![0x00; 0x10000];
+    data[0x0134..0x0139].copy_from_slice(b"HELLO");
+    data[0x0143] = 0x80;
+    data[0x0146] = 0x03;
+    data[0x0147] = 0x03;
+    data[0x0148] = 0x01;
+    data[0x0149] = 0x02;
+    data[0x014B] = 0x33;
+    data[0x0144..0x0146].copy_from_slice(b"8K");
+    data[0x014C] = 0x01;
+    let mut checksum: u8 = 0;
+    for byte in &data[0x0134..=0x014C] {
+        checksum = checksum.wrapping_sub(*byte).wrapping_sub(1);
+    }
+    data[0x014D] = checksum;
+    data
+}
+
+#[test]
+fn info_lists_the_header_one_field_per_line() {
+    let rom: Rom = Rom::from_bytes(header_bytes()).expect("valid test ROM");
+    let expected: &str = "Title: HELLO
+Cartridge type: MBC1+RAM+BATTERY
+ROM size: 64 KiB (4 banks)
+RAM size: 8 KiB (1 banks)
+CGB flag: 80 (CGB enhanced)
+SGB support: yes
+Licensee: 8K
+Version: 1
+Header checksum: OK
+Nintendo logo: does not match, the boot ROM will lock up";
+    assert_eq!(rom.info(), expected);
+}
+
+#[test]
+fn a_bad_checksum_shows_both_values() {
+    let mut data: Vec<u8> = header_bytes();
+    let computed: u8 = data[0x014D];
+    data[0x014D] = computed.wrapping_add(1);
+    let rom: Rom = Rom::from_bytes(data).expect("valid test ROM");
+    let line: String = format!(
+        "Header checksum: bad (header says {:02X}, computed {computed:02X})",
+        computed.wrapping_add(1)
+    );
+    assert!(rom.info().lines().any(|l| l == line), "{}", rom.info());
+}
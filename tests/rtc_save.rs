@@ -0,0 +1,70 @@
+use rgb_emu::Emulator;
+
+const DAY: u64 = 86400;
+
+// MBC3+TIMER+RAM+BATTERY with 8 KiB of RAM
+fn mbc3_emulator() -> Emulator {
+    let mut data: Vec<u8> = vec![0x00; 0x8000];
+    data[0x0147] = 0x10;
+    data[0x0149] = 0x02;
+    let mut emulator: Emulator = Emulator::new(data).expect("valid test ROM");
+    emulator.write_byte(0x0000, 0x0A);
+    emulator
+}
+
+// Register 0x08-0x0C through the latch
+fn read_rtc(emulator: &mut Emulator, register: u8) -> u8 {
+    emulator.write_byte(0x6000, 0x00);
+    emulator.write_byte(0x6000, 0x01);
+    emulator.write_byte(0x4000, register);
+    emulator.read(0xA000)
+}
+
+fn write_rtc(emulator: &mut Emulator, register: u8, value: u8) {
+    emulator.write_byte(0x4000, register);
+    emulator.write_byte(0xA000, value);
+}
+
+// A save taken on day 5 at 10:00:00, with its timestamp moved back by the given number of seconds
+fn save_from_the_past(seconds: u64) -> Vec<u8> {
+    let mut emulator: Emulator = mbc3_emulator();
+    write_rtc(&mut emulator, 0x0A, 10);
+    write_rtc(&mut emulator, 0x0B, 5);
+    let mut save: Vec<u8> = emulator.save_ram();
+    assert_eq!(save.len(), 0x2000 + 48);
+    let timestamp_at: usize = save.len() - 8;
+    let saved_at: u64 = u64::from_le_bytes(save[timestamp_at..].try_into().unwrap());
+    save[timestamp_at..].copy_from_slice(&(saved_at - seconds).to_le_bytes());
+    save
+}
+
+#[test]
+fn the_clock_catches_up_with_the_time_since_the_save() {
+    let save: Vec<u8> = save_from_the_past(2 * DAY + 3 * 3600);
+    let mut emulator: Emulator = mbc3_emulator();
+    emulator.load_ram(&save);
+    assert_eq!(read_rtc(&mut emulator, 0x0B), 7);
+    assert_eq!(read_rtc(&mut emulator, 0x0A), 13);
+}
+
+#[test]
+fn a_32_bit_timestamp_is_read_too() {
+    let mut save: Vec<u8> = save_from_the_past(DAY);
+    save.truncate(save.len() - 4);
+    let mut emulator: Emulator = mbc3_emulator();
+    emulator.load_ram(&save);
+    assert_eq!(read_rtc(&mut emulator, 0x0B), 6);
+}
+
+#[test]
+fn a_halted_clock_stays_put() {
+    let mut emulator: Emulator = mbc3_emulator();
+    write_rtc(&mut emulator, 0x0C, 0b0100_0000);
+    write_rtc(&mut emulator, 0x0B, 5);
+    let mut save: Vec<u8> = emulator.save_ram();
+    let timestamp_at: usize = save.len() - 8;
+    save[timestamp_at..].copy_from_slice(&0u64.to_le_bytes());
+    let mut emulator: Emulator = mbc3_emulator();
+    emulator.load_ram(&save);
+    assert_eq!(read_rtc(&mut emulator, 0x0B), 5);
+}
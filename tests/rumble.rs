@@ -0,0 +1,35 @@
+use rgb_emu::Emulator;
+
+// A 32 KiB MBC5 cartridge with 32 KiB of RAM, with or without the rumble motor
+fn mbc5_emulator(cart_type: u8) -> Emulator {
+    let mut data: Vec<u8> = vec![0x00; 0x8000];
+    data[0x0147] = cart_type;
+    data[0x0149] = 0x03;
+    let mut emulator: Emulator = Emulator::new(data).expect("valid test ROM");
+    emulator.write_byte(0x0000, 0x0A);
+    emulator
+}
+
+#[test]
+fn bit_3_of_the_ram_bank_register_drives_the_motor() {
+    let mut emulator: Emulator = mbc5_emulator(0x1E);
+    assert!(!emulator.rumble());
+    emulator.write_byte(0xA000, 0x11);
+    emulator.write_byte(0x4000, 0x08 | 0x02);
+    assert!(emulator.rumble());
+    emulator.write_byte(0xA000, 0x22);
+    // Turning the motor off keeps RAM bank 2 selected
+    emulator.write_byte(0x4000, 0x02);
+    assert!(!emulator.rumble());
+    assert_eq!(emulator.read(0xA000), 0x22);
+    emulator.write_byte(0x4000, 0x08);
+    assert!(emulator.rumble());
+    assert_eq!(emulator.read(0xA000), 0x11);
+}
+
+#[test]
+fn carts_without_a_motor_never_rumble() {
+    let mut emulator: Emulator = mbc5_emulator(0x1B);
+    emulator.write_byte(0x4000, 0x08);
+    assert!(!emulator.rumble());
+}
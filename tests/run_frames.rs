@@ -0,0 +1,29 @@
+use rgb_emu::{Emulator, WatchKind};
+
+mod common;
+
+#[test]
+fn run_frames_matches_running_each_frame() {
+    let mut batched: Emulator = common::idle_emulator();
+    let mut single: Emulator = common::idle_emulator();
+    batched.run_frames(3);
+    for _ in 0..3 {
+        single.run_frame();
+    }
+    assert_eq!(batched.cpu().snapshot(), single.cpu().snapshot());
+    assert_eq!(batched.ppu().snapshot(), single.ppu().snapshot());
+    assert_eq!(batched.read(0xFF04), single.read(0xFF04));
+}
+
+#[test]
+fn run_frames_stops_at_a_watchpoint() {
+    // Increments 0xC000 in a loop, so the first write stops the run
+    let program: &str = "LD HL,$C000; INC (HL); JR -3";
+    let mut emulator: Emulator = common::program_emulator(&common::assemble(program));
+    emulator.add_watchpoint(0xC000, WatchKind::Write);
+    emulator.run_frames(10);
+    assert!(emulator.watch_hit().is_some());
+    let pc: u16 = emulator.cpu().snapshot().pc;
+    emulator.run_frames(10);
+    assert_eq!(emulator.cpu().snapshot().pc, pc);
+}
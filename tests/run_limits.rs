@@ -0,0 +1,26 @@
+use rgb_emu::{Cpu, MemBus, Rom};
+
+fn nop_cpu() -> Cpu {
+    let rom: Rom = Rom::from_bytes(vec![0x00; 0x8000]).expect("valid test ROM");
+    Cpu::new(MemBus::new(rom).expect("supported test mapper"))
+}
+
+#[test]
+fn run_for_executes_exactly_that_many_instructions() {
+    let mut cpu: Cpu = nop_cpu();
+    let cycles: u32 = cpu.run_for(100);
+    assert_eq!(cpu.snapshot().pc, 0x0100 + 100);
+    assert_eq!(cycles, 100 * 4);
+    assert_eq!(cpu.run_for(0), 0);
+    assert_eq!(cpu.snapshot().pc, 0x0100 + 100);
+}
+
+#[test]
+fn run_until_stops_once_the_predicate_holds() {
+    let mut cpu: Cpu = nop_cpu();
+    let cycles: u32 = cpu.run_until(|cpu| cpu.snapshot().pc == 0x0180);
+    assert_eq!(cpu.snapshot().pc, 0x0180);
+    assert_eq!(cycles, 0x80 * 4);
+    // Already true, so nothing runs
+    assert_eq!(cpu.run_until(|_| true), 0);
+}
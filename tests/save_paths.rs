@@ -0,0 +1,47 @@
+use rgb_emu::{SaveConfig, SaveNaming, sanitize_file_name};
+use std::path::{Path, PathBuf};
+
+#[test]
+fn saves_default_to_the_title_next_to_the_rom() {
+    let config: SaveConfig = SaveConfig::default();
+    assert_eq!(
+        config.path(Path::new("roms/red.gb"), "POKEMON RED", "sav"),
+        PathBuf::from("roms/POKEMON RED.sav")
+    );
+}
+
+#[test]
+fn a_custom_directory_is_honored() {
+    let config: SaveConfig = SaveConfig {
+        directory: Some(PathBuf::from("/saves")),
+        naming: SaveNaming::Title,
+    };
+    assert_eq!(
+        config.path(Path::new("/media/cdrom/tetris.gb"), "TETRIS", "sav"),
+        PathBuf::from("/saves/TETRIS.sav")
+    );
+}
+
+#[test]
+fn rom_file_naming_and_empty_titles_use_the_rom_name() {
+    let config: SaveConfig = SaveConfig {
+        directory: None,
+        naming: SaveNaming::RomFile,
+    };
+    assert_eq!(
+        config.path(Path::new("roms/red.gb"), "POKEMON RED", "sav"),
+        PathBuf::from("roms/red.sav")
+    );
+    let config: SaveConfig = SaveConfig::default();
+    assert_eq!(
+        config.path(Path::new("roms/homebrew.gb"), "", "sav"),
+        PathBuf::from("roms/homebrew.sav")
+    );
+}
+
+#[test]
+fn titles_are_made_filesystem_safe() {
+    assert_eq!(sanitize_file_name("../../etc/passwd"), "______etc_passwd");
+    assert_eq!(sanitize_file_name("ZELDA: DX"), "ZELDA_ DX");
+    assert_eq!(sanitize_file_name("  KIRBY  "), "KIRBY");
+}
@@ -0,0 +1,74 @@
+use rgb_emu::{Emulator, Error, StateError};
+
+mod common;
+
+// A 1 MiB MBC1 cartridge whose loop keeps WRAM, the timer and the PPU moving
+fn running_emulator() -> Emulator {
+    let mut data: Vec<u8> = vec![0x00; 0x100000];
+    data[0x0147] = 0x03;
+    data[0x0148] = 0x05;
+    data[0x0149] = 0x02;
+    let program: Vec<u8> = common::assemble("LD HL,$C000; INC (HL); INC HL; JR -4");
+    data[0x0100..0x0100 + program.len()].copy_from_slice(&program);
+    let mut emulator: Emulator = Emulator::new(data).expect("valid test ROM");
+    emulator.write_byte(0xFF07, 0x05);
+    emulator.write_byte(0x0000, 0x0A);
+    emulator.write_byte(0xA123, 0x5A);
+    emulator
+}
+
+#[test]
+fn a_mid_game_state_round_trips() {
+    let mut emulator: Emulator = running_emulator();
+    emulator.run_frames(3);
+    emulator.step();
+    let state: Vec<u8> = emulator.save_state().expect("state encodes");
+    emulator.run_frames(2);
+    let later: Vec<u8> = emulator.save_state().expect("state encodes");
+
+    emulator
+        .load_state(&state)
+        .expect("state from the same ROM");
+    assert_eq!(emulator.save_state().expect("state encodes"), state);
+    emulator.run_frames(2);
+    assert_eq!(emulator.save_state().expect("state encodes"), later);
+    assert_eq!(emulator.read(0xA123), 0x5A);
+}
+
+#[test]
+fn states_leave_the_rom_out() {
+    let emulator: Emulator = running_emulator();
+    let state: Vec<u8> = emulator.save_state().expect("state encodes");
+    // Memory and registers come to about 100 KiB, a tenth of the cartridge
+    assert!(state.len() < 0x40000, "{} bytes", state.len());
+}
+
+#[test]
+fn states_from_another_rom_are_rejected() {
+    let state: Vec<u8> = running_emulator().save_state().expect("state encodes");
+    let mut other: Emulator = common::idle_emulator();
+    let before: Vec<u8> = other.save_state().expect("state encodes");
+    assert!(matches!(
+        other.load_state(&state),
+        Err(Error::State(StateError::WrongRom))
+    ));
+    assert!(matches!(
+        other.load_state(b"not a state"),
+        Err(Error::State(StateError::InvalidHeader))
+    ));
+    assert_eq!(other.save_state().expect("state encodes"), before);
+}
+
+#[test]
+fn a_cut_off_state_fails_without_touching_the_machine() {
+    let mut emulator: Emulator = running_emulator();
+    emulator.run_frame();
+    let state: Vec<u8> = emulator.save_state().expect("state encodes");
+    emulator.run_frame();
+    let before: Vec<u8> = emulator.save_state().expect("state encodes");
+    assert!(matches!(
+        emulator.load_state(&state[..state.len() / 2]),
+        Err(Error::State(StateError::Decode(_)))
+    ));
+    assert_eq!(emulator.save_state().expect("state encodes"), before);
+}
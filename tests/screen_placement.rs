@@ -0,0 +1,35 @@
+use rgb_emu::{ScaleMode, screen_placement};
+
+#[test]
+fn integer_scale_centers_whole_multiples() {
+    // Exactly 3x
+    assert_eq!(
+        screen_placement(480.0, 432.0, ScaleMode::Integer),
+        (0.0, 0.0, 480.0, 432.0)
+    );
+    // Wide window: 3x with bars left and right
+    assert_eq!(
+        screen_placement(800.0, 450.0, ScaleMode::Integer),
+        (160.0, 9.0, 480.0, 432.0)
+    );
+    // Just short of 3x tall drops to 2x
+    assert_eq!(
+        screen_placement(480.0, 431.0, ScaleMode::Integer),
+        (80.0, 71.0, 320.0, 288.0)
+    );
+    // Smaller than the screen stays at 1x
+    assert_eq!(
+        screen_placement(100.0, 100.0, ScaleMode::Integer),
+        (-30.0, -22.0, 160.0, 144.0)
+    );
+}
+
+#[test]
+fn fit_fills_the_limiting_side_and_keeps_10_to_9() {
+    let (x, y, width, height) = screen_placement(800.0, 450.0, ScaleMode::Fit);
+    assert_eq!((x, y, width, height), (150.0, 0.0, 500.0, 450.0));
+    let (x, y, width, height) = screen_placement(320.0, 600.0, ScaleMode::Fit);
+    assert_eq!((x, width), (0.0, 320.0));
+    assert_eq!((y, height), (156.0, 288.0));
+    assert!((width / height - 10.0 / 9.0).abs() < 1e-6);
+}
@@ -0,0 +1,59 @@
+use rgb_emu::{DisplayPalette, Emulator};
+
+mod common;
+
+fn sgb_emulator(sgb_flag: u8) -> Emulator {
+    let mut data: Vec<u8> = common::idle_rom();
+    data[0x0146] = sgb_flag;
+    data[0x014B] = 0x33;
+    Emulator::new(data).expect("valid test ROM")
+}
+
+// Pulses one 16 byte packet through P1: reset, then P14 low for a 0 or P15 low for a 1, each bit
+// followed by both lines high, then the 0 stop bit
+fn send_packet(emulator: &mut Emulator, packet: &[u8; 16]) {
+    emulator.write_byte(0xFF00, 0x00);
+    emulator.write_byte(0xFF00, 0x30);
+    for bit in 0..128 {
+        let one: bool = packet[bit / 8] & (1 << (bit % 8)) != 0;
+        emulator.write_byte(0xFF00, if one { 0x10 } else { 0x20 });
+        emulator.write_byte(0xFF00, 0x30);
+    }
+    emulator.write_byte(0xFF00, 0x20);
+    emulator.write_byte(0xFF00, 0x30);
+}
+
+// PAL01 with color 0 and palette 0's colors 1-3, palette 1 left black
+fn pal01(colors: [u16; 4]) -> [u8; 16] {
+    let mut packet: [u8; 16] = [0; 16];
+    packet[0] = 0x01;
+    for (i, color) in colors.iter().enumerate() {
+        packet[1 + i * 2..3 + i * 2].copy_from_slice(&color.to_le_bytes());
+    }
+    packet
+}
+
+#[test]
+fn a_palette_packet_recolors_the_output() {
+    let colors: [u16; 4] = [0x001F, 0x03E0, 0x7C00, 0x0000];
+    let mut emulator: Emulator = sgb_emulator(0x03);
+    emulator.run_frame();
+    let before: Vec<u8> = emulator.framebuffer_rgba();
+    send_packet(&mut emulator, &pal01(colors));
+    emulator.run_frame();
+    let after: Vec<u8> = emulator.framebuffer_rgba();
+    // The top left pixel is shade 0, now the red from the packet
+    let sgb: DisplayPalette = DisplayPalette::from_rgb555(colors);
+    assert_eq!(after[0..4], sgb.color(0));
+    assert_ne!(before[0..4], after[0..4]);
+}
+
+#[test]
+fn games_without_the_sgb_flag_ignore_packets() {
+    let mut emulator: Emulator = sgb_emulator(0x00);
+    emulator.run_frame();
+    let before: Vec<u8> = emulator.framebuffer_rgba();
+    send_packet(&mut emulator, &pal01([0x001F, 0x03E0, 0x7C00, 0x0000]));
+    emulator.run_frame();
+    assert_eq!(emulator.framebuffer_rgba(), before);
+}
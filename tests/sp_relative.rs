@@ -0,0 +1,63 @@
+mod common;
+
+use common::{assemble, program_cpu};
+use rgb_emu::{Cpu, CpuState};
+
+// LD SP,sp then ADD SP,e8. Returns SP and (Z, N, H, C).
+fn add_sp(sp: u16, offset: i8) -> (u16, (bool, bool, bool, bool)) {
+    let mut program: Vec<u8> = assemble(&format!("LD SP,${sp:04X}"));
+    program.extend([0xE8, offset as u8]);
+    let mut cpu: Cpu = program_cpu(&program);
+    cpu.run_for(2);
+    let state: CpuState = cpu.snapshot();
+    (state.sp, flags(&state))
+}
+
+fn flags(state: &CpuState) -> (bool, bool, bool, bool) {
+    (state.zero, state.subtract, state.half_carry, state.carry)
+}
+
+#[test]
+fn add_sp_takes_h_and_c_from_the_low_byte() {
+    assert_eq!(add_sp(0x000F, 1), (0x0010, (false, false, true, false)));
+    assert_eq!(add_sp(0x00FF, 1), (0x0100, (false, false, true, true)));
+    assert_eq!(add_sp(0x0FFF, -1), (0x0FFE, (false, false, true, true)));
+    assert_eq!(add_sp(0x0F00, -1), (0x0EFF, (false, false, false, false)));
+    // Z is cleared even for a zero result
+    assert_eq!(add_sp(0xFFFF, 1), (0x0000, (false, false, true, true)));
+}
+
+#[test]
+fn ld_hl_sp_e8_sets_the_same_flags_and_leaves_sp() {
+    let mut program: Vec<u8> = assemble("LD SP,$00F8");
+    program.extend([0xF8, 0x08]);
+    let mut cpu: Cpu = program_cpu(&program);
+    cpu.run_for(2);
+    let state: CpuState = cpu.snapshot();
+    assert_eq!((state.h, state.l, state.sp), (0x01, 0x00, 0x00F8));
+    assert_eq!(flags(&state), (false, false, true, true));
+}
+
+// Z set beforehand through POP AF, then LD HL,hl; LD BC,bc; ADD HL,BC
+fn add_hl(hl: u16, bc: u16) -> (u16, (bool, bool, bool, bool)) {
+    let mut program: Vec<u8> = assemble(&format!(
+        "LD BC,$00C0; PUSH BC; POP AF; LD HL,${hl:04X}; LD BC,${bc:04X}"
+    ));
+    program.push(0x09);
+    let mut cpu: Cpu = program_cpu(&program);
+    cpu.run_for(6);
+    let state: CpuState = cpu.snapshot();
+    ((state.h as u16) << 8 | state.l as u16, flags(&state))
+}
+
+#[test]
+fn add_hl_takes_h_and_c_from_bits_11_and_15_and_keeps_z() {
+    assert_eq!(add_hl(0x0FFF, 0x0001), (0x1000, (true, false, true, false)));
+    // A low byte carry that ADD SP,e8 would flag
+    assert_eq!(
+        add_hl(0x00FF, 0x0001),
+        (0x0100, (true, false, false, false))
+    );
+    assert_eq!(add_hl(0x8000, 0x8000), (0x0000, (true, false, false, true)));
+    assert_eq!(add_hl(0xFFFF, 0x0001), (0x0000, (true, false, true, true)));
+}
@@ -0,0 +1,38 @@
+use rgb_emu::{FRAMES_PER_SECOND, SpeedStats};
+
+fn frames_at(fps: f64, count: usize) -> Vec<f64> {
+    (0..count).map(|frame| 10.0 + frame as f64 / fps).collect()
+}
+
+fn assert_close(actual: f64, expected: f64) {
+    assert!((actual - expected).abs() < 0.001, "{actual} != {expected}");
+}
+
+#[test]
+fn speed_is_fps_against_the_hardware_frame_rate() {
+    let full: SpeedStats = SpeedStats::from_timestamps(&frames_at(FRAMES_PER_SECOND, 60));
+    assert_close(full.fps, FRAMES_PER_SECOND);
+    assert_close(full.speed, 100.0);
+    let half: SpeedStats = SpeedStats::from_timestamps(&frames_at(FRAMES_PER_SECOND / 2.0, 30));
+    assert_close(half.speed, 50.0);
+    let fast: SpeedStats = SpeedStats::from_timestamps(&frames_at(240.0, 10));
+    assert_close(fast.fps, 240.0);
+    assert_close(fast.speed, 240.0 / FRAMES_PER_SECOND * 100.0);
+}
+
+#[test]
+fn only_the_first_and_last_timestamps_matter() {
+    // 4 frames in 0.1 s however they are spread out
+    let stats: SpeedStats = SpeedStats::from_timestamps(&[1.0, 1.01, 1.02, 1.09, 1.1]);
+    assert_close(stats.fps, 40.0);
+}
+
+#[test]
+fn too_few_or_unordered_timestamps_give_zero() {
+    for timestamps in [&[][..], &[1.0], &[2.0, 2.0], &[3.0, 1.0]] {
+        assert_eq!(
+            SpeedStats::from_timestamps(timestamps),
+            SpeedStats::default()
+        );
+    }
+}
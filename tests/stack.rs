@@ -0,0 +1,20 @@
+mod common;
+
+use common::{assemble, program_emulator};
+use rgb_emu::{CpuState, Emulator};
+
+#[test]
+fn push_stores_high_byte_above_low_byte_and_pop_restores_it() {
+    let mut emulator: Emulator =
+        program_emulator(&assemble("LD SP,$D000; LD BC,$1234; PUSH BC; POP DE"));
+    emulator.step();
+    emulator.step();
+    emulator.step();
+    assert_eq!(emulator.cpu().snapshot().sp, 0xCFFE);
+    assert_eq!(emulator.read(0xCFFF), 0x12);
+    assert_eq!(emulator.read(0xCFFE), 0x34);
+    emulator.step();
+    let state: CpuState = emulator.cpu().snapshot();
+    assert_eq!((state.d, state.e), (0x12, 0x34));
+    assert_eq!(state.sp, 0xD000);
+}
@@ -0,0 +1,73 @@
+use rgb_emu::{IoDevice, Oam, Ppu, PpuMode, Vram};
+
+const STAT_INTERRUPT: u8 = 0b0000_0010;
+
+// Steps one dot at a time and returns each mode with how many dots it lasted
+fn mode_runs(ppu: &mut Ppu, dots: u32) -> Vec<(PpuMode, u32)> {
+    let mut vram: Vram = Vram::new();
+    let oam: Oam = Oam::new();
+    let mut runs: Vec<(PpuMode, u32)> = vec![(ppu.mode(), 0)];
+    for _ in 0..dots {
+        ppu.step(1, &mut vram, &oam);
+        let last: &mut (PpuMode, u32) = runs.last_mut().expect("runs start with one entry");
+        last.1 += 1;
+        if ppu.mode() != last.0 {
+            runs.push((ppu.mode(), 0));
+        }
+    }
+    runs
+}
+
+fn enabled_ppu() -> Ppu {
+    let mut ppu: Ppu = Ppu::new();
+    ppu.write(0xFF40, 0b1001_0001);
+    ppu
+}
+
+#[test]
+fn a_scanline_visits_modes_2_3_0_with_their_cycle_counts() {
+    let mut ppu: Ppu = enabled_ppu();
+    let runs: Vec<(PpuMode, u32)> = mode_runs(&mut ppu, 456);
+    assert_eq!(
+        runs,
+        vec![
+            (PpuMode::OamScan, 80),
+            (PpuMode::Drawing, 172),
+            (PpuMode::HBlank, 204),
+            (PpuMode::OamScan, 0),
+        ]
+    );
+    assert_eq!(ppu.ly(), 1);
+}
+
+#[test]
+fn vblank_covers_lines_144_to_153() {
+    let mut ppu: Ppu = enabled_ppu();
+    let runs: Vec<(PpuMode, u32)> = mode_runs(&mut ppu, 456 * 154);
+    assert_eq!(runs.len(), 144 * 3 + 2);
+    assert_eq!(runs[144 * 3], (PpuMode::VBlank, 456 * 10));
+    assert_eq!(runs[144 * 3 + 1], (PpuMode::OamScan, 0));
+    assert_eq!(ppu.ly(), 0);
+}
+
+#[test]
+fn mode_changes_request_the_enabled_stat_interrupts() {
+    let mut vram: Vram = Vram::new();
+    let oam: Oam = Oam::new();
+    let mut ppu: Ppu = enabled_ppu();
+    // HBlank source only
+    ppu.write(0xFF41, 0b0000_1000);
+    let fired: Vec<u32> = (1..=456)
+        .filter(|_| ppu.step(1, &mut vram, &oam) & STAT_INTERRUPT != 0)
+        .collect();
+    assert_eq!(fired, vec![80 + 172]);
+    // OAM scan source only, set during HBlank so it fires as the next line starts
+    ppu.step(300, &mut vram, &oam);
+    ppu.write(0xFF41, 0b0010_0000);
+    assert_eq!(ppu.step(155, &mut vram, &oam) & STAT_INTERRUPT, 0);
+    assert_eq!(
+        ppu.step(1, &mut vram, &oam) & STAT_INTERRUPT,
+        STAT_INTERRUPT
+    );
+    assert_eq!(ppu.mode(), PpuMode::OamScan);
+}
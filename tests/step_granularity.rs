@@ -0,0 +1,43 @@
+mod common;
+
+use common::idle_emulator;
+use rgb_emu::{Emulator, PpuMode};
+
+const FRAME_DOTS: u32 = 456 * 154;
+
+#[test]
+fn step_frame_advances_exactly_one_frame() {
+    // The idle loop's JR -2 takes 12 cycles a turn, which divides both a line and a frame
+    let mut emulator: Emulator = idle_emulator();
+    // Line up with the start of a frame first
+    emulator.step_frame();
+    assert_eq!(emulator.ppu().ly(), 0);
+    let dot: u32 = emulator.ppu().snapshot().dot;
+    for _ in 0..3 {
+        assert_eq!(emulator.step_frame(), FRAME_DOTS);
+        assert_eq!(emulator.ppu().ly(), 0);
+        assert_eq!(emulator.ppu().snapshot().dot, dot);
+    }
+}
+
+#[test]
+fn step_scanline_and_step_to_vblank_stop_at_their_boundaries() {
+    let mut emulator: Emulator = idle_emulator();
+    emulator.step_frame();
+    assert_eq!(emulator.step_scanline(), 456);
+    assert_eq!(emulator.ppu().ly(), 1);
+    assert_eq!(emulator.step_to_vblank(), 143 * 456);
+    assert_eq!(emulator.ppu().ly(), 144);
+    assert_eq!(emulator.ppu().mode(), PpuMode::VBlank);
+    // Already in VBlank, so this runs to the next one
+    assert_eq!(emulator.step_to_vblank(), FRAME_DOTS);
+    assert_eq!(emulator.ppu().ly(), 144);
+}
+
+#[test]
+fn stepping_with_the_lcd_off_gives_up_after_a_frame() {
+    let mut emulator: Emulator = idle_emulator();
+    emulator.write_byte(0xFF40, 0x00);
+    assert_eq!(emulator.step_frame(), FRAME_DOTS);
+    assert_eq!(emulator.step_scanline(), FRAME_DOTS);
+}
@@ -0,0 +1,31 @@
+use rgb_emu::{Emulator, SymbolTable};
+
+mod common;
+
+const SYM: &str = "; File generated by rgblink
+00:0150 Main
+00:0150 Main.loop
+00:0000 RST_00 ; restart vector
+03:4000 LevelData
+not a symbol
+";
+
+#[test]
+fn parses_bank_address_label_lines() {
+    let symbols: SymbolTable = SymbolTable::parse(SYM);
+    assert_eq!(symbols.len(), 3);
+    assert_eq!(symbols.lookup(0, 0x0150), Some("Main"));
+    assert_eq!(symbols.lookup(0, 0x0000), Some("RST_00"));
+    assert_eq!(symbols.lookup(3, 0x4000), Some("LevelData"));
+    assert_eq!(symbols.lookup(1, 0x4000), None);
+}
+
+#[test]
+fn labels_follow_the_mapped_bank() {
+    let mut emulator: Emulator = common::mbc1_emulator();
+    emulator.set_symbols(SymbolTable::parse(SYM));
+    assert_eq!(emulator.label(0x0150), Some("Main"));
+    assert_eq!(emulator.label(0x4000), None);
+    emulator.write_byte(0x2000, 0x03);
+    assert_eq!(emulator.label(0x4000), Some("LevelData"));
+}
@@ -0,0 +1,63 @@
+mod common;
+
+use common::assemble;
+use rgb_emu::{TestResult, run_test_rom};
+
+fn rom_with(program: &[u8]) -> Vec<u8> {
+    let mut data: Vec<u8> = vec![0x00; 0x8000];
+    data[0x0100..0x0100 + program.len()].copy_from_slice(program);
+    data
+}
+
+#[test]
+fn mooneye_fibonacci_registers_pass() {
+    let program: Vec<u8> = assemble("LD BC,$0305; LD DE,$080D; LD HL,$1522; JR -2");
+    assert_eq!(run_test_rom(rom_with(&program)), TestResult::Pass);
+}
+
+#[test]
+fn mooneye_failure_signature_fails() {
+    let program: Vec<u8> = assemble("LD BC,$4242; LD DE,$4242; LD HL,$4242; JR -2");
+    assert_eq!(
+        run_test_rom(rom_with(&program)),
+        TestResult::Fail(String::from("Mooneye failure signature"))
+    );
+}
+
+#[test]
+fn an_unimplemented_opcode_fails_instead_of_running_on() {
+    assert_eq!(
+        run_test_rom(rom_with(&[0xD3])),
+        TestResult::Fail(String::from("Opcode D3 at 0100 is not implemented"))
+    );
+}
+
+// Sends the text over serial a byte at a time, waiting out each transfer, then spins
+fn serial_rom(text: &str) -> Vec<u8> {
+    let mut program: Vec<u8> = Vec::new();
+    for byte in text.bytes() {
+        program.extend(assemble(&format!(
+            "LD A,{byte}; LD ($FF01),A; LD A,$81; LD ($FF02),A"
+        )));
+        // Two rounds of LD B,0; DEC B; JR NZ,-3 outlast the 4096 cycle transfer
+        program.extend([0x06, 0x00, 0x05, 0x20, 0xFD].repeat(2));
+    }
+    program.extend(assemble("JR -2"));
+    // Too long to fit before the header, so jump over it
+    let mut data: Vec<u8> = rom_with(&assemble("JP $0150"));
+    data[0x0150..0x0150 + program.len()].copy_from_slice(&program);
+    data
+}
+
+#[test]
+fn blargg_serial_verdict_is_the_last_line() {
+    assert_eq!(run_test_rom(serial_rom("Passed\n")), TestResult::Pass);
+    assert_eq!(
+        run_test_rom(serial_rom("cpu_instrs\n\nPassed all tests\n")),
+        TestResult::Pass
+    );
+    assert_eq!(
+        run_test_rom(serial_rom("Failed #2")),
+        TestResult::Fail(String::from("Failed #2"))
+    );
+}
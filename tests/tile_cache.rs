@@ -0,0 +1,41 @@
+use rgb_emu::{Oam, Ppu, Vram};
+
+#[test]
+fn only_written_tiles_are_decoded_again() {
+    let mut vram: Vram = Vram::new();
+    let oam: Oam = Oam::new();
+    let mut ppu: Ppu = Ppu::new();
+    // A new PPU decodes both banks once
+    ppu.step(4, &mut vram, &oam);
+    assert_eq!(ppu.rebuilt_tiles(), 768);
+    ppu.step(4, &mut vram, &oam);
+    assert_eq!(ppu.rebuilt_tiles(), 0);
+
+    vram.set_value(0x8010, 0xFF);
+    vram.set_value(0x8011, 0x00);
+    assert!(vram.tile_dirty(0, 1));
+    assert!(!vram.tile_dirty(0, 0));
+    assert!(!vram.tile_dirty(0, 2));
+    ppu.step(4, &mut vram, &oam);
+    assert_eq!(ppu.rebuilt_tiles(), 1);
+    assert!(!vram.tile_dirty(0, 1));
+
+    // Tile maps aren't tile data
+    vram.set_value(0x9800, 0x01);
+    ppu.step(4, &mut vram, &oam);
+    assert_eq!(ppu.rebuilt_tiles(), 0);
+}
+
+#[test]
+fn writes_mark_the_selected_bank() {
+    let mut vram: Vram = Vram::new();
+    let oam: Oam = Oam::new();
+    let mut ppu: Ppu = Ppu::new();
+    ppu.step(4, &mut vram, &oam);
+    vram.set_bank(1);
+    vram.set_value(0x8010, 0xFF);
+    assert!(vram.tile_dirty(1, 1));
+    assert!(!vram.tile_dirty(0, 1));
+    ppu.step(4, &mut vram, &oam);
+    assert_eq!(ppu.rebuilt_tiles(), 1);
+}
@@ -0,0 +1,79 @@
+use rgb_emu::{IoDevice, Oam, OamEntry, Ppu, Tile, Vram};
+
+// The example tile from Pan Docs
+const TILE: [u8; 16] = [
+    0x3C, 0x7E, 0x42, 0x42, 0x42, 0x42, 0x42, 0x42, 0x7E, 0x5E, 0x7E, 0x0A, 0x7C, 0x56, 0x38, 0x7C,
+];
+const PIXELS: [[u8; 8]; 8] = [
+    [0, 2, 3, 3, 3, 3, 2, 0],
+    [0, 3, 0, 0, 0, 0, 3, 0],
+    [0, 3, 0, 0, 0, 0, 3, 0],
+    [0, 3, 0, 0, 0, 0, 3, 0],
+    [0, 3, 1, 3, 3, 3, 3, 0],
+    [0, 1, 1, 1, 3, 1, 3, 0],
+    [0, 3, 1, 3, 1, 3, 2, 0],
+    [0, 2, 3, 3, 3, 2, 0, 0],
+];
+
+fn vram_with_tile(bank: u8, tile: u16) -> Vram {
+    let mut vram: Vram = Vram::new();
+    vram.set_bank(bank);
+    for (i, byte) in TILE.iter().enumerate() {
+        vram.set_value(0x8000 + tile * 16 + i as u16, *byte);
+    }
+    vram.set_bank(0);
+    vram
+}
+
+#[test]
+fn vram_tile_decodes_to_its_pixel_matrix() {
+    let vram: Vram = vram_with_tile(0, 5);
+    assert_eq!(vram.tile(0, 5).pixels, PIXELS);
+    assert_eq!(vram.tile(0, 4).pixels, [[0; 8]; 8]);
+    assert_eq!(vram.tile(1, 5).pixels, [[0; 8]; 8]);
+    let vram: Vram = vram_with_tile(1, 5);
+    assert_eq!(vram.tile(1, 5), Tile::from_bytes(&TILE));
+}
+
+#[test]
+fn oam_entry_splits_out_the_attributes() {
+    let mut oam: Oam = Oam::new();
+    for (i, byte) in [0x20, 0x18, 0x05, 0b1101_1010].iter().enumerate() {
+        oam.set_value(0xFE04 + i as u16, *byte);
+    }
+    assert_eq!(
+        oam.entry(1),
+        OamEntry {
+            y: 0x20,
+            x: 0x18,
+            tile: 0x05,
+            behind_bg: true,
+            y_flip: true,
+            x_flip: false,
+            dmg_palette: 1,
+            bank: 1,
+            cgb_palette: 2,
+        }
+    );
+    assert_eq!(oam.entries().len(), 40);
+    assert_eq!(oam.entries()[1], oam.entry(1));
+}
+
+#[test]
+fn sprite_tiles_follow_flips_height_and_cgb_bank() {
+    let vram: Vram = vram_with_tile(1, 5);
+    let mut entry: OamEntry = OamEntry::from_bytes([0x10, 0x08, 0x05, 0b0110_1000]);
+    let mut ppu: Ppu = Ppu::new();
+    // The DMG always reads bank 0
+    assert_eq!(ppu.object_tiles(&vram, &entry), vec![vram.tile(0, 5)]);
+    ppu.set_cgb(true);
+    let flipped: Tile = ppu.object_tiles(&vram, &entry)[0];
+    assert_eq!(flipped.pixels[0], [0, 0, 2, 3, 3, 3, 2, 0]);
+    assert_eq!(flipped.pixels[7], [0, 2, 3, 3, 3, 3, 2, 0]);
+    // A flipped 8x16 sprite swaps its pair
+    ppu.write(0xFF40, 0b0000_0100);
+    entry.x_flip = false;
+    let tiles: Vec<Tile> = ppu.object_tiles(&vram, &entry);
+    assert_eq!(tiles[0], vram.tile(1, 5).flipped(false, true));
+    assert_eq!(tiles[1], vram.tile(1, 4));
+}
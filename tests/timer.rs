@@ -0,0 +1,56 @@
+use rgb_emu::{IoDevice, Timer};
+
+fn tima(timer: &Timer) -> u8 {
+    timer.read(0xFF05)
+}
+
+#[test]
+fn each_frequency_increments_at_its_rate() {
+    // 4096, 262144, 65536 and 16384 Hz of the 4194304 Hz clock
+    for (tac, period) in [(0x04, 1024), (0x05, 16), (0x06, 64), (0x07, 256)] {
+        let mut timer: Timer = Timer::new();
+        timer.write(0xFF07, tac);
+        timer.step(period - 1);
+        assert_eq!(tima(&timer), 0, "TAC {tac:02X}");
+        timer.step(1);
+        assert_eq!(tima(&timer), 1, "TAC {tac:02X}");
+        timer.step(period * 9);
+        assert_eq!(tima(&timer), 10, "TAC {tac:02X}");
+    }
+}
+
+#[test]
+fn clearing_the_enable_bit_stops_tima() {
+    let mut timer: Timer = Timer::new();
+    timer.write(0xFF07, 0x05);
+    timer.step(16 * 4);
+    assert_eq!(tima(&timer), 4);
+    // The divider bit is low here, so disabling doesn't tick TIMA on the way out
+    timer.write(0xFF07, 0x01);
+    timer.step(16 * 100);
+    assert_eq!(tima(&timer), 4);
+    timer.write(0xFF07, 0x05);
+    timer.step(16);
+    assert_eq!(tima(&timer), 5);
+}
+
+#[test]
+fn changing_frequency_can_tick_tima() {
+    let mut timer: Timer = Timer::new();
+    timer.write(0xFF07, 0x04);
+    // Divider bit 9 is set but bit 3 isn't, so moving to 262144 Hz is a falling edge
+    timer.step(512);
+    assert_eq!(tima(&timer), 0);
+    timer.write(0xFF07, 0x05);
+    assert_eq!(tima(&timer), 1);
+}
+
+#[test]
+fn overflow_reloads_tma_and_requests_the_interrupt() {
+    let mut timer: Timer = Timer::new();
+    timer.write(0xFF06, 0xAB);
+    timer.write(0xFF05, 0xFF);
+    timer.write(0xFF07, 0x05);
+    assert_eq!(timer.step(16), 0b0000_0100);
+    assert_eq!(tima(&timer), 0xAB);
+}
@@ -0,0 +1,46 @@
+use rgb_emu::{CpuState, Emulator};
+
+mod common;
+
+#[test]
+fn undoing_an_inc_restores_registers_and_flags() {
+    let mut emulator: Emulator = common::program_emulator(&common::assemble("LD A,$0F; INC A"));
+    emulator.set_undo_limit(8);
+    emulator.step();
+    let before: CpuState = emulator.cpu().snapshot();
+    emulator.step();
+    // The half carry out of bit 3 changed A and F
+    assert_eq!(emulator.cpu().snapshot().a, 0x10);
+    assert_ne!(emulator.cpu().snapshot().f, before.f);
+    assert!(emulator.undo());
+    assert_eq!(emulator.cpu().snapshot(), before);
+}
+
+#[test]
+fn undo_reverts_memory_writes() {
+    let program: Vec<u8> = common::assemble("LD HL,$C000; INC (HL); INC (HL)");
+    let mut emulator: Emulator = common::program_emulator(&program);
+    emulator.write_byte(0xC000, 0x41);
+    emulator.set_undo_limit(8);
+    for _ in 0..3 {
+        emulator.step();
+    }
+    assert_eq!(emulator.read(0xC000), 0x43);
+    assert!(emulator.undo());
+    assert_eq!(emulator.read(0xC000), 0x42);
+    assert!(emulator.undo());
+    assert_eq!(emulator.read(0xC000), 0x41);
+}
+
+#[test]
+fn history_is_bounded_by_the_limit() {
+    let mut emulator: Emulator = common::program_emulator(&common::assemble("NOP; NOP; NOP; NOP"));
+    emulator.set_undo_limit(2);
+    for _ in 0..4 {
+        emulator.step();
+    }
+    assert!(emulator.undo());
+    assert!(emulator.undo());
+    assert!(!emulator.undo());
+    assert_eq!(emulator.cpu().snapshot().pc, 0x0102);
+}
@@ -0,0 +1,53 @@
+mod common;
+
+use common::program_cpu;
+use rgb_emu::{Cpu, Error, UnimplementedPolicy};
+
+// 0xD3 isn't an SM83 opcode, so it stays unimplemented. INC B follows it.
+const PROGRAM: [u8; 2] = [0xD3, 0x04];
+
+#[test]
+#[should_panic(expected = "Opcode D3 at C000 is not implemented")]
+fn panic_is_the_default() {
+    let mut cpu: Cpu = program_cpu(&PROGRAM);
+    cpu.step();
+}
+
+#[test]
+fn log_and_nop_skip_the_opcode() {
+    for policy in [UnimplementedPolicy::Log, UnimplementedPolicy::Nop] {
+        let mut cpu: Cpu = program_cpu(&PROGRAM);
+        cpu.set_unimplemented_policy(policy);
+        cpu.step();
+        assert_eq!(cpu.snapshot().pc, 0xC001, "{policy:?}");
+        cpu.step();
+        assert_eq!(cpu.snapshot().b, 0x01, "{policy:?}");
+    }
+}
+
+#[test]
+fn stop_stays_on_the_opcode() {
+    let mut cpu: Cpu = program_cpu(&PROGRAM);
+    cpu.set_unimplemented_policy(UnimplementedPolicy::Stop);
+    let error: Error = cpu.try_step().expect_err("unimplemented opcode");
+    assert!(matches!(
+        error,
+        Error::UnimplementedOpcode {
+            opcode: 0xD3,
+            pc: 0xC000
+        }
+    ));
+    assert_eq!(cpu.snapshot().pc, 0xC000);
+}
+
+#[test]
+fn repeated_unimplemented_opcodes_advance_pc_by_one_each() {
+    let mut cpu: Cpu = program_cpu(&[0xD3, 0xD3, 0xD3, 0x04]);
+    cpu.set_unimplemented_policy(UnimplementedPolicy::Log);
+    for pc in [0xC001, 0xC002, 0xC003] {
+        cpu.step();
+        assert_eq!(cpu.snapshot().pc, pc);
+    }
+    cpu.step();
+    assert_eq!(cpu.snapshot().b, 0x01);
+}
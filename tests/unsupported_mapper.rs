@@ -0,0 +1,37 @@
+use rgb_emu::{CartType, Emulator, Error, Rom, RomError};
+
+fn rom_with_cart_type(cart_type: u8) -> Vec<u8> {
+    let mut data: Vec<u8> = vec![0x00; 0x8000];
+    data[0x0147] = cart_type;
+    data
+}
+
+#[test]
+fn mbc6_header_gives_the_unsupported_mapper_error() {
+    // The header itself parses, the cartridge type is known but has no mapper
+    let rom: Rom = Rom::from_bytes(rom_with_cart_type(0x20)).expect("known cartridge type");
+    assert!(matches!(rom.header().cart_type, CartType::MBC6));
+    assert!(!rom.header().cart_type.is_supported());
+    let error: Error = Emulator::new(rom_with_cart_type(0x20))
+        .err()
+        .expect("MBC6 is unsupported");
+    assert!(matches!(
+        error,
+        Error::Rom(RomError::UnsupportedMapper(0x20))
+    ));
+    assert_eq!(
+        error.to_string(),
+        "Cartridge type 20 uses a mapper that is not supported"
+    );
+}
+
+#[test]
+fn supported_and_unknown_types_are_told_apart() {
+    assert!(Emulator::new(rom_with_cart_type(0x01)).is_ok());
+    let rom: Rom = Rom::from_bytes(rom_with_cart_type(0x01)).expect("MBC1");
+    assert!(rom.header().cart_type.is_supported());
+    assert!(matches!(
+        Emulator::new(rom_with_cart_type(0x04)).err(),
+        Some(Error::Rom(RomError::UnknownCartType(0x04)))
+    ));
+}
@@ -0,0 +1,42 @@
+mod common;
+
+use common::idle_emulator;
+use rgb_emu::{Emulator, PpuMode};
+
+fn step_to_mode(emulator: &mut Emulator, mode: PpuMode) {
+    while emulator.ppu().mode() != mode {
+        emulator.step();
+    }
+}
+
+// 0x42 at the start of VRAM and 0x24 at the start of OAM, written with the LCD off
+fn seeded_emulator() -> Emulator {
+    let mut emulator: Emulator = idle_emulator();
+    emulator.write_byte(0xFF40, 0x00);
+    emulator.write_byte(0x8000, 0x42);
+    emulator.write_byte(0xFE00, 0x24);
+    emulator.write_byte(0xFF40, 0b1001_0001);
+    emulator
+}
+
+#[test]
+fn vram_reads_0xff_during_mode_3() {
+    let mut emulator: Emulator = seeded_emulator();
+    step_to_mode(&mut emulator, PpuMode::Drawing);
+    assert_eq!(emulator.read(0x8000), 0xFF);
+    assert_eq!(emulator.read(0xFE00), 0xFF);
+    // Writes are dropped too
+    emulator.write_byte(0x8000, 0x99);
+    step_to_mode(&mut emulator, PpuMode::HBlank);
+    assert_eq!(emulator.read(0x8000), 0x42);
+    assert_eq!(emulator.read(0xFE00), 0x24);
+}
+
+#[test]
+fn oam_but_not_vram_is_blocked_during_mode_2() {
+    let mut emulator: Emulator = seeded_emulator();
+    step_to_mode(&mut emulator, PpuMode::HBlank);
+    step_to_mode(&mut emulator, PpuMode::OamScan);
+    assert_eq!(emulator.read(0x8000), 0x42);
+    assert_eq!(emulator.read(0xFE00), 0xFF);
+}
@@ -0,0 +1,44 @@
+use rgb_emu::{Emulator, WatchHit, WatchKind};
+
+mod common;
+
+const PROGRAM: &str = "LD A,$42; LD ($C123),A; INC A; LD ($C123),A; LD HL,$C200; LD B,(HL); JR -2";
+
+#[test]
+fn a_write_to_a_watched_address_pauses_the_cpu() {
+    let mut emulator: Emulator = common::program_emulator(&common::assemble(PROGRAM));
+    emulator.write_byte(0xC123, 0x07);
+    emulator.add_watchpoint(0xC123, WatchKind::Write);
+    emulator.run_frame();
+    let hit: WatchHit = emulator.watch_hit().expect("the write is watched");
+    assert_eq!(
+        hit,
+        WatchHit {
+            pc: 0x0102,
+            addr: 0xC123,
+            kind: WatchKind::Write,
+            old: 0x07,
+            new: 0x42
+        }
+    );
+    // Paused until resumed
+    let pc: u16 = emulator.cpu().snapshot().pc;
+    emulator.run_frame();
+    assert_eq!(emulator.cpu().snapshot().pc, pc);
+    emulator.resume();
+    emulator.run_frame();
+    let hit: WatchHit = emulator.watch_hit().expect("the second write is watched");
+    assert_eq!((hit.pc, hit.old, hit.new), (0x0106, 0x42, 0x43));
+}
+
+#[test]
+fn read_watches_report_reads_in_a_range() {
+    let mut emulator: Emulator = common::program_emulator(&common::assemble(PROGRAM));
+    emulator.add_watch_range(0xC1F0, 0xC20F, WatchKind::Read);
+    emulator.run_frame();
+    let hit: WatchHit = emulator.watch_hit().expect("the read is watched");
+    assert_eq!(
+        (hit.pc, hit.addr, hit.kind),
+        (0x010C, 0xC200, WatchKind::Read)
+    );
+}
@@ -0,0 +1,42 @@
+use rgb_emu::{Rom, RomError};
+use std::io::Write;
+use std::path::PathBuf;
+use zip::ZipWriter;
+use zip::write::SimpleFileOptions;
+
+fn temp_path(name: &str) -> PathBuf {
+    std::env::temp_dir().join(format!("rgb-emu-{}-{name}.zip", std::process::id()))
+}
+
+// Writes a zip with the given (name, contents) entries, stored uncompressed
+fn write_zip(name: &str, entries: &[(&str, &[u8])]) -> String {
+    let path: PathBuf = temp_path(name);
+    let mut zip: ZipWriter<std::fs::File> =
+        ZipWriter::new(std::fs::File::create(&path).expect("temp file is writable"));
+    let options: SimpleFileOptions =
+        SimpleFileOptions::default().compression_method(zip::CompressionMethod::Stored);
+    for (entry, contents) in entries {
+        zip.start_file(*entry, options).expect("zip entry starts");
+        zip.write_all(contents).expect("zip entry is written");
+    }
+    zip.finish().expect("zip is finished");
+    path.to_string_lossy().into_owned()
+}
+
+#[test]
+fn zipped_rom_loads_the_first_game_boy_entry() {
+    let mut data: Vec<u8> = vec![0x00; 0x8000];
+    data[0x0134..0x0138].copy_from_slice(b"ZIPT");
+    let path: String = write_zip("rom", &[("README.txt", b"not a rom"), ("Game.GB", &data)]);
+    let rom: Rom = Rom::new(&path).expect("zipped ROM loads");
+    assert_eq!(rom.get_title(), "ZIPT");
+    assert_eq!(rom.get_value(0x0134), b'Z');
+    std::fs::remove_file(path).expect("temp file is removable");
+}
+
+#[test]
+fn zip_without_a_rom_is_an_error() {
+    let path: String = write_zip("empty", &[("README.txt", b"not a rom")]);
+    assert!(matches!(Rom::new(&path), Err(RomError::NoRomInArchive)));
+    std::fs::remove_file(path).expect("temp file is removable");
+}